@@ -0,0 +1,434 @@
+//! # Model calibration
+//!
+//! Adjusts parameters of a [`System`](../../core/system/struct.System.html) so that the simulated performance
+//! matches a measured one. The measured table is a tab-separated file with the same header as the file written
+//! by [`Engine::write_performance_to()`](../../engine/engine/struct.Engine.html#method.write_performance_to).
+//! The column "Speed [RPM]" is required and any subset of the remaining ones can be given, i.e.
+//! torque, air flow and volumetric efficiency.
+//!
+//! The calibrated parameters are listed by [`Parameter`](enum.Parameter.html), each within its lower and upper bounds.
+//! They are found with the Nelder–Mead method by minimizing the sum of the squared relative residuals of
+//! all measured columns over all measured speeds. Each evaluation simulates every speed until steady state,
+//! hence a calibration takes a few hundreds of simulations.
+//!
+//! ### Example
+//! ```no_run
+//! use lmb::analysis::calibration::{Calibration, Parameter};
+//! use lmb::Gas;
+//! use lmb_engine_simulator as lmb;
+//!
+//! let gas = Gas::new("air.json");
+//! let mut builder = lmb::SystemBuilder::new();
+//! builder
+//!     .add_engine("engine.json", &gas)
+//!     .add_environment("ambient", &gas)
+//!     .connect_from_to("valve_int", "ambient")
+//!     .connect_from_to("valve_exh", "ambient");
+//! let mut system = builder.build_system();
+//!
+//! let mut calibration = Calibration::new("measured_performance.txt").unwrap();
+//! calibration
+//!     .add_parameter(Parameter::DischargeCoeff("valve_int".to_string()), 0.7, 1.2)
+//!     .and_then(|c| c.add_parameter(Parameter::HeatTransferMultiplier("cyl_1".to_string()), 0.5, 2.0))
+//!     .and_then(|c| c.add_parameter(Parameter::CombustionStart, 680.0, 710.0))
+//!     .unwrap();
+//! let result = calibration.run(&mut system).unwrap();
+//! println!("{}", result);
+//! result.write_residuals_to("residuals.txt");
+//! ```
+
+use crate::core::system::System;
+use crate::core::traits::ZeroDim;
+use crate::numerics::optimization::nelder_mead;
+use crate::reaction::combustion::WiebeFunction;
+use ndarray::*;
+use std::io::Write;
+
+/// Parameter of the model that can be calibrated
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parameter {
+    /// Discharge coefficient of the orifice, or multiplier of the discharge coefficient of the valve, with the given name
+    DischargeCoeff(String),
+    /// Multiplier of the heat transfer coefficient of the cylinder with the given name
+    HeatTransferMultiplier(String),
+    /// Wall temperature, in K, of the cylinder with the given name
+    WallTemperature(String),
+    /// Start of combustion in CA-deg
+    CombustionStart,
+    /// Efficiency factor `a` of the Wiebe function
+    WiebeA,
+    /// Form factor `m` of the Wiebe function
+    WiebeM,
+    /// Combustion duration of the Wiebe function in CA-deg
+    CombustionDuration,
+}
+
+impl Parameter {
+    fn value(&self, system: &System) -> Result<f64, String> {
+        let engine = system.engine();
+        let cylinder = |cyl: &str| match engine {
+            Some(eng) => match eng.cylinders().iter().find(|c| c.name() == cyl) {
+                Some(c) => Ok(c),
+                None => Err(format!("cylinder `{}` was not found", cyl)),
+            },
+            None => Err("system does not contain an engine".to_string()),
+        };
+        let wiebe = || match engine {
+            Some(eng) => match eng.combustion().wiebe_function() {
+                Some(wiebe) => Ok((eng.combustion().ini_combustion().to_degrees(), wiebe)),
+                None => Err(format!(
+                    "model `{}` does not use a Wiebe function",
+                    eng.combustion_model()
+                )),
+            },
+            None => Err("system does not contain an engine".to_string()),
+        };
+        match self {
+            Parameter::DischargeCoeff(conn) => system.discharge_coeff_of(conn),
+            Parameter::HeatTransferMultiplier(cyl) => Ok(cylinder(cyl)?.heat_transfer_multiplier()),
+            Parameter::WallTemperature(cyl) => Ok(cylinder(cyl)?.wall_temperature()),
+            Parameter::CombustionStart => Ok(wiebe()?.0),
+            Parameter::WiebeA => Ok(wiebe()?.1.a()),
+            Parameter::WiebeM => Ok(wiebe()?.1.m()),
+            Parameter::CombustionDuration => Ok(wiebe()?.1.comb_duration()),
+        }
+    }
+
+    fn set_value(&self, system: &mut System, value: f64) -> Result<(), String> {
+        if let Parameter::DischargeCoeff(conn) = self {
+            return system.set_discharge_coeff_of(conn, value);
+        }
+        let engine = match system.engine_mut() {
+            Some(eng) => eng,
+            None => return Err("system does not contain an engine".to_string()),
+        };
+        match self {
+            Parameter::HeatTransferMultiplier(cyl) => {
+                engine.set_heat_transfer_multiplier_of(cyl, value)
+            }
+            Parameter::WallTemperature(cyl) => engine.set_wall_temperature_of(cyl, value),
+            _ => {
+                let ign_angle = engine.combustion().ini_combustion().to_degrees();
+                let (a, m, duration) = match engine.combustion().wiebe_function() {
+                    Some(w) => (w.a(), w.m(), w.comb_duration()),
+                    None => {
+                        return Err(format!(
+                            "model `{}` does not use a Wiebe function",
+                            engine.combustion_model()
+                        ))
+                    }
+                };
+                let (ign_angle, wiebe) = match self {
+                    Parameter::CombustionStart => (value, WiebeFunction::new(a, m, duration)),
+                    Parameter::WiebeA => (ign_angle, WiebeFunction::new(value, m, duration)),
+                    Parameter::WiebeM => (ign_angle, WiebeFunction::new(a, value, duration)),
+                    _ => (ign_angle, WiebeFunction::new(a, m, value)),
+                };
+                engine.set_wiebe_function(ign_angle, wiebe)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Parameter::DischargeCoeff(name) => write!(f, "discharge coefficient of `{}`", name),
+            Parameter::HeatTransferMultiplier(name) => {
+                write!(f, "heat transfer multiplier of `{}`", name)
+            }
+            Parameter::WallTemperature(name) => write!(f, "wall temperature [K] of `{}`", name),
+            Parameter::CombustionStart => write!(f, "start of combustion [CA-deg]"),
+            Parameter::WiebeA => write!(f, "Wiebe `a`"),
+            Parameter::WiebeM => write!(f, "Wiebe `m`"),
+            Parameter::CombustionDuration => write!(f, "combustion duration [CA-deg]"),
+        }
+    }
+}
+
+/// Calibration of a `System` against a measured performance table
+pub struct Calibration {
+    speed: Vec<f64>,                        // [RPM]
+    measured: Vec<(String, Vec<f64>)>,      // (header, values)
+    parameters: Vec<(Parameter, f64, f64)>, // (parameter, lower, upper)
+    max_iterations: usize,
+}
+
+impl Calibration {
+    /// Reads the measured performance table `file_name`
+    pub fn new(file_name: &str) -> Result<Calibration, String> {
+        let text = match std::fs::read_to_string(file_name) {
+            Ok(text) => text,
+            Err(err) => return Err(format!("unable to read `{}`: {}", file_name, err)),
+        };
+        Calibration::parse(&text, file_name)
+    }
+
+    // parses the text of the measured performance table read from `file_name`
+    fn parse(text: &str, file_name: &str) -> Result<Calibration, String> {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        let header: Vec<String> = match lines.next() {
+            Some(line) => line.split('\t').map(|h| h.trim().to_string()).collect(),
+            None => return Err(format!("`{}` is empty", file_name)),
+        };
+        let mut columns: Vec<Vec<f64>> = vec![Vec::new(); header.len()];
+        for line in lines {
+            let values: Vec<&str> = line.split('\t').collect();
+            if values.len() != header.len() {
+                return Err(format!(
+                    "`{}`: expected {} columns in line \"{}\"",
+                    file_name,
+                    header.len(),
+                    line
+                ));
+            }
+            for (column, value) in columns.iter_mut().zip(values) {
+                match value.trim().parse::<f64>() {
+                    Ok(v) => column.push(v),
+                    Err(_) => return Err(format!("`{}`: invalid number \"{}\"", file_name, value)),
+                }
+            }
+        }
+
+        let mut speed: Option<Vec<f64>> = None;
+        let mut measured: Vec<(String, Vec<f64>)> = Vec::new();
+        for (name, column) in header.into_iter().zip(columns) {
+            if name == "Speed [RPM]" {
+                speed = Some(column);
+            } else {
+                measured.push((name, column));
+            }
+        }
+        let speed = match speed {
+            Some(speed) if !speed.is_empty() => speed,
+            Some(_) => return Err(format!("`{}` has no measured points", file_name)),
+            None => return Err(format!("`{}` has no column \"Speed [RPM]\"", file_name)),
+        };
+        if measured.is_empty() {
+            return Err(format!(
+                "`{}` has no measured column besides the speed",
+                file_name
+            ));
+        }
+        Ok(Calibration {
+            speed,
+            measured,
+            parameters: Vec::new(),
+            max_iterations: 200,
+        })
+    }
+
+    /// Adds `param` to the calibration, bounded by `lower` and `upper`
+    pub fn add_parameter(&mut self, param: Parameter, lower: f64, upper: f64) -> Result<&mut Self, String> {
+        if lower >= upper {
+            return Err(format!(
+                "lower bound of {} must be less than the upper one: {} and {}",
+                param, lower, upper
+            ));
+        }
+        self.parameters.push((param, lower, upper));
+        Ok(self)
+    }
+
+    /// Set the maximum number of iterations of the optimizer (default: 200)
+    pub fn set_max_iterations(&mut self, max_iterations: usize) -> &mut Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Calibrates `system`. At the end, `system` is left with the calibrated parameters and its engine holds
+    /// the operational parameters of the measured speeds.
+    pub fn run(&self, system: &mut System) -> Result<CalibrationResult, String> {
+        if self.parameters.is_empty() {
+            return Err("no parameter was added to the calibration".to_string());
+        }
+        match system.engine() {
+            Some(eng) => {
+                for (name, _) in self.measured.iter() {
+                    if eng.operat_param().column(name).is_none() {
+                        return Err(format!("unknown measured column \"{}\"", name));
+                    }
+                }
+            }
+            None => return Err("system does not contain an engine".to_string()),
+        }
+
+        // initial guess from the current state of the model, normalized by the bounds
+        let mut x0: Array1<f64> = Array1::zeros(self.parameters.len());
+        for (x, (param, lower, upper)) in x0.iter_mut().zip(self.parameters.iter()) {
+            let value = param.value(system)?;
+            *x = ((value - lower) / (upper - lower)).clamp(0.0, 1.0);
+        }
+        let step = Array1::from_elem(self.parameters.len(), 0.2);
+
+        let objective = |x: &Array1<f64>| -> f64 {
+            if x.iter().any(|v| *v < 0.0 || *v > 1.0) {
+                return f64::INFINITY;
+            }
+            match self.evaluate(system, x) {
+                Ok(residuals) => residuals.iter().flatten().map(|r| r * r).sum(),
+                Err(_) => f64::INFINITY,
+            }
+        };
+        let (x, _) = nelder_mead(objective, &x0, &step, 1e-6, self.max_iterations);
+
+        let residuals = self.evaluate(system, &x)?;
+        let parameters = self
+            .parameters
+            .iter()
+            .zip(x.iter())
+            .map(|((param, lower, upper), v)| (param.clone(), lower + v * (upper - lower)))
+            .collect();
+        Ok(CalibrationResult {
+            parameters,
+            speed: self.speed.clone(),
+            columns: self.measured.iter().map(|(name, _)| name.clone()).collect(),
+            residuals,
+        })
+    }
+
+    // Applies the normalized parameters `x`, simulates all measured speeds and returns the relative
+    // residuals, `residuals[point][column]`
+    fn evaluate(&self, system: &mut System, x: &Array1<f64>) -> Result<Vec<Vec<f64>>, String> {
+        for ((param, lower, upper), v) in self.parameters.iter().zip(x.iter()) {
+            param.set_value(system, lower + v * (upper - lower))?;
+        }
+        if let Some(eng) = system.engine_mut() {
+            eng.clear_operat_param();
+        }
+        for speed in self.speed.iter() {
+            if let Some(eng) = system.engine_mut() {
+                eng.set_speed(*speed);
+            }
+            system.advance_to_steady_state();
+        }
+
+        let op = system.engine().unwrap().operat_param();
+        let mut residuals: Vec<Vec<f64>> =
+            vec![Vec::with_capacity(self.measured.len()); self.speed.len()];
+        for (name, measured) in self.measured.iter() {
+            let simulated = op.column(name).unwrap();
+            for (i, point) in residuals.iter_mut().enumerate() {
+                let reference = if measured[i].abs() > 1e-12 {
+                    measured[i]
+                } else {
+                    1.0
+                };
+                point.push((simulated[i] - measured[i]) / reference);
+            }
+        }
+        if residuals.iter().flatten().any(|r| !r.is_finite()) {
+            return Err("simulation returned non-finite values".to_string());
+        }
+        Ok(residuals)
+    }
+}
+
+/// Result of a [`Calibration`](struct.Calibration.html)
+#[derive(Debug, Clone)]
+pub struct CalibrationResult {
+    parameters: Vec<(Parameter, f64)>,
+    speed: Vec<f64>,          // [RPM]
+    columns: Vec<String>,     // headers of the measured columns
+    residuals: Vec<Vec<f64>>, // relative residuals: [point][column]
+}
+
+impl CalibrationResult {
+    /// Returns the calibrated parameters and their values
+    pub fn parameters(&self) -> &Vec<(Parameter, f64)> {
+        &self.parameters
+    }
+
+    /// Returns the relative residuals, (simulated - measured)/measured, of each measured point and column
+    pub fn residuals(&self) -> &Vec<Vec<f64>> {
+        &self.residuals
+    }
+
+    /// Returns the headers of the measured columns, in the same order as the residuals of each point
+    pub fn columns(&self) -> &Vec<String> {
+        &self.columns
+    }
+
+    /// Returns the root-mean-square of all relative residuals
+    pub fn rms_residual(&self) -> f64 {
+        let n = self.residuals.iter().map(|r| r.len()).sum::<usize>() as f64;
+        (self.residuals.iter().flatten().map(|r| r * r).sum::<f64>() / n).sqrt()
+    }
+
+    /// Writes the relative residuals, in %, of each measured point into `file_name`
+    pub fn write_residuals_to(&self, file_name: &str) {
+        let mut header = vec!["Speed [RPM]".to_string()];
+        header.extend(self.columns.iter().cloned());
+        let mut data: Vec<String> = Vec::new();
+        for (speed, point) in self.speed.iter().zip(self.residuals.iter()) {
+            let mut line = vec![format!("{:.1}", speed)];
+            line.extend(point.iter().map(|r| format!("{:.3}", 100.0 * r)));
+            data.push(line.join("\t") + "\n");
+        }
+        let mut file = std::fs::File::create(file_name).expect("Error opening writing file");
+        writeln!(file, "{}", header.join("\t")).expect("Unable to write data");
+        write!(file, "{}", data.join("")).expect("Unable to write data");
+    }
+}
+
+impl std::fmt::Display for CalibrationResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Calibrated parameters:")?;
+        for (param, value) in self.parameters.iter() {
+            writeln!(f, "  {}: {:.4}", param, value)?;
+        }
+        writeln!(f, "Relative residuals [%]:")?;
+        writeln!(f, "  Speed [RPM]\t{}", self.columns.join("\t"))?;
+        for (speed, point) in self.speed.iter().zip(self.residuals.iter()) {
+            let values: Vec<String> = point.iter().map(|r| format!("{:.3}", 100.0 * r)).collect();
+            writeln!(f, "  {:.1}\t{}", speed, values.join("\t"))?;
+        }
+        write!(f, "RMS residual [%]: {:.3}", 100.0 * self.rms_residual())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_measured_table() {
+        let text = "Speed [RPM]\tTorque [Nm]\tAir flow [kg/h]\n2000\t30.5\t20.0\n\n3000\t35.0\t31.5\n";
+        let calibration = Calibration::parse(text, "measured.txt").unwrap();
+        assert_eq!(calibration.speed, vec![2000.0, 3000.0]);
+        assert_eq!(calibration.measured.len(), 2);
+        assert_eq!(calibration.measured[0], ("Torque [Nm]".to_string(), vec![30.5, 35.0]));
+        assert_eq!(calibration.measured[1], ("Air flow [kg/h]".to_string(), vec![20.0, 31.5]));
+    }
+
+    #[test]
+    fn rejects_invalid_tables() {
+        assert!(Calibration::parse("Torque [Nm]\n30.0\n", "no_speed.txt").is_err());
+        assert!(Calibration::parse("Speed [RPM]\n2000\n", "no_column.txt").is_err());
+        assert!(Calibration::parse("Speed [RPM]\tTorque [Nm]\n2000\tabc\n", "bad_number.txt").is_err());
+        assert!(Calibration::parse("Speed [RPM]\tTorque [Nm]\n2000\n", "missing_value.txt").is_err());
+        assert!(Calibration::parse("", "empty.txt").is_err());
+        assert!(Calibration::new("this_file_does_not_exist.txt").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_bounds() {
+        let mut calibration = Calibration::parse("Speed [RPM]\tTorque [Nm]\n2000\t30.0\n", "measured.txt").unwrap();
+        assert!(calibration.add_parameter(Parameter::CombustionStart, 710.0, 680.0).is_err());
+        assert!(calibration.add_parameter(Parameter::CombustionStart, 680.0, 680.0).is_err());
+        calibration.add_parameter(Parameter::CombustionStart, 680.0, 710.0).unwrap();
+        assert_eq!(calibration.parameters.len(), 1);
+    }
+
+    #[test]
+    fn rms_of_relative_residuals() {
+        let result = CalibrationResult {
+            parameters: Vec::new(),
+            speed: vec![2000.0, 3000.0],
+            columns: vec!["Torque [Nm]".to_string()],
+            residuals: vec![vec![0.03], vec![-0.04]],
+        };
+        assert!((result.rms_residual() - (0.00125f64).sqrt()).abs() < 1e-12);
+    }
+}
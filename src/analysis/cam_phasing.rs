@@ -0,0 +1,286 @@
+//! # Cam phasing optimization
+//!
+//! Finds, at each speed, the intake and exhaust cam phases that maximize a column of the performance table
+//! written by [`Engine::write_performance_to()`](../../engine/engine/struct.Engine.html#method.write_performance_to),
+//! i.e. "Torque [Nm]" for a full-load torque curve or "Efficiency [%]" at part load.
+//!
+//! The phases are applied through [`Engine::set_intake_actuation()`](../../engine/engine/struct.Engine.html#method.set_intake_actuation)
+//! and [`Engine::set_exhaust_actuation()`](../../engine/engine/struct.Engine.html#method.set_exhaust_actuation),
+//! keeping the lift and duration scales of the current actuation. Each speed is optimized independently
+//! with the Nelder–Mead method within the given phase ranges, and each evaluation simulates the speed until steady state.
+//!
+//! ### Example
+//! ```no_run
+//! use lmb::analysis::cam_phasing::CamPhasing;
+//! use lmb::Gas;
+//! use lmb_engine_simulator as lmb;
+//!
+//! let gas = Gas::new("air.json");
+//! let mut builder = lmb::SystemBuilder::new();
+//! builder
+//!     .add_engine("engine.json", &gas)
+//!     .add_environment("ambient", &gas)
+//!     .connect_from_to("valve_int", "ambient")
+//!     .connect_from_to("valve_exh", "ambient");
+//! let mut system = builder.build_system();
+//!
+//! let mut phasing = CamPhasing::new(&[2000.0, 3000.0, 4000.0], "Torque [Nm]");
+//! phasing
+//!     .set_intake_range(-30.0, 30.0)
+//!     .and_then(|p| p.set_exhaust_range(-30.0, 30.0))
+//!     .unwrap();
+//! let result = phasing.run(&mut system).unwrap();
+//! println!("{}", result);
+//! result.write_to("cam_phasing.txt");
+//! ```
+
+use crate::connector::valve::ValveActuation;
+use crate::core::system::System;
+use crate::numerics::optimization::nelder_mead;
+use ndarray::*;
+use std::io::Write;
+
+/// Optimization of the cam phases of a `System` at each speed
+pub struct CamPhasing {
+    speed: Vec<f64>,               // [RPM]
+    objective: String,             // header of the maximized column
+    intake: Option<(f64, f64)>,    // [CA-deg] - lower and upper phase
+    exhaust: Option<(f64, f64)>,   // [CA-deg] - lower and upper phase
+    max_iterations: usize,
+}
+
+impl CamPhasing {
+    /// Maximizes the column `objective` of the performance table at each `speed`, in RPM
+    pub fn new(speed: &[f64], objective: &str) -> CamPhasing {
+        CamPhasing {
+            speed: speed.to_vec(),
+            objective: objective.to_string(),
+            intake: None,
+            exhaust: None,
+            max_iterations: 40,
+        }
+    }
+
+    /// Optimizes the intake cam phase, in CA-deg, between `lower` and `upper`
+    pub fn set_intake_range(&mut self, lower: f64, upper: f64) -> Result<&mut Self, String> {
+        self.intake = Some(CamPhasing::checked_range("intake", lower, upper)?);
+        Ok(self)
+    }
+
+    /// Optimizes the exhaust cam phase, in CA-deg, between `lower` and `upper`
+    pub fn set_exhaust_range(&mut self, lower: f64, upper: f64) -> Result<&mut Self, String> {
+        self.exhaust = Some(CamPhasing::checked_range("exhaust", lower, upper)?);
+        Ok(self)
+    }
+
+    /// Set the maximum number of iterations of the optimizer at each speed (default: 40)
+    pub fn set_max_iterations(&mut self, max_iterations: usize) -> &mut Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Optimizes `system` at each speed. At the end, its engine holds the operational parameters of the
+    /// optimal phases of all speeds and is left with the phases of the last one.
+    pub fn run(&self, system: &mut System) -> Result<CamPhasingResult, String> {
+        if self.intake.is_none() && self.exhaust.is_none() {
+            return Err("no cam phase range was set".to_string());
+        }
+        if self.speed.is_empty() {
+            return Err("no speed was given".to_string());
+        }
+        let (intake, exhaust) = match system.engine() {
+            Some(eng) => {
+                if eng.operat_param().column(&self.objective).is_none() {
+                    return Err(format!("unknown objective column \"{}\"", self.objective));
+                }
+                (eng.intake_actuation(), eng.exhaust_actuation())
+            }
+            None => return Err("system does not contain an engine".to_string()),
+        };
+        let ranges: Vec<(f64, f64)> = self.intake.iter().chain(self.exhaust.iter()).cloned().collect();
+
+        let mut optimum: Vec<Array1<f64>> = Vec::new();
+        for speed in self.speed.iter() {
+            // initial guess from the current phases, normalized by the ranges
+            let mut current = Vec::new();
+            if self.intake.is_some() {
+                current.push(intake.phase());
+            }
+            if self.exhaust.is_some() {
+                current.push(exhaust.phase());
+            }
+            let x0: Array1<f64> = current
+                .iter()
+                .zip(ranges.iter())
+                .map(|(phase, (lower, upper))| ((phase - lower) / (upper - lower)).clamp(0.0, 1.0))
+                .collect();
+            let step = Array1::from_elem(ranges.len(), 0.25);
+
+            let reference = self.evaluate(system, *speed, &x0)?.abs().max(1e-12);
+            let objective = |x: &Array1<f64>| -> f64 {
+                if x.iter().any(|v| *v < 0.0 || *v > 1.0) {
+                    return f64::INFINITY;
+                }
+                match self.evaluate(system, *speed, x) {
+                    Ok(value) if value.is_finite() => -value / reference,
+                    _ => f64::INFINITY,
+                }
+            };
+            let (x, _) = nelder_mead(objective, &x0, &step, 1e-4, self.max_iterations);
+            optimum.push(x);
+        }
+
+        // simulating the optimal phases of all speeds
+        if let Some(eng) = system.engine_mut() {
+            eng.clear_operat_param();
+        }
+        let mut intake_phase = Vec::new();
+        let mut exhaust_phase = Vec::new();
+        for (speed, x) in self.speed.iter().zip(optimum.iter()) {
+            let (int, exh) = self.apply(system, x)?;
+            intake_phase.push(int);
+            exhaust_phase.push(exh);
+            if let Some(eng) = system.engine_mut() {
+                eng.set_speed(*speed);
+            }
+            system.advance_to_steady_state();
+        }
+        let value = system.engine().unwrap().operat_param().column(&self.objective).unwrap().clone();
+        Ok(CamPhasingResult {
+            speed: self.speed.clone(),
+            objective: self.objective.clone(),
+            intake_phase,
+            exhaust_phase,
+            value,
+        })
+    }
+
+    fn checked_range(kind: &str, lower: f64, upper: f64) -> Result<(f64, f64), String> {
+        if lower >= upper {
+            return Err(format!(
+                "lower {} phase must be less than the upper one: {} and {}",
+                kind, lower, upper
+            ));
+        }
+        Ok((lower, upper))
+    }
+
+    // Sets the normalized phases `x` and returns the intake and exhaust phases in CA-deg
+    fn apply(&self, system: &mut System, x: &Array1<f64>) -> Result<(f64, f64), String> {
+        let engine = match system.engine_mut() {
+            Some(eng) => eng,
+            None => return Err("system does not contain an engine".to_string()),
+        };
+        let mut values = x.iter();
+        let mut intake = engine.intake_actuation();
+        let mut exhaust = engine.exhaust_actuation();
+        if let Some((lower, upper)) = self.intake {
+            let phase = lower + values.next().unwrap() * (upper - lower);
+            intake = ValveActuation::new(phase, intake.lift_scale(), intake.duration_scale())?;
+            engine.set_intake_actuation(intake);
+        }
+        if let Some((lower, upper)) = self.exhaust {
+            let phase = lower + values.next().unwrap() * (upper - lower);
+            exhaust = ValveActuation::new(phase, exhaust.lift_scale(), exhaust.duration_scale())?;
+            engine.set_exhaust_actuation(exhaust);
+        }
+        Ok((intake.phase(), exhaust.phase()))
+    }
+
+    // Applies the normalized phases `x`, simulates `speed` and returns the objective
+    fn evaluate(&self, system: &mut System, speed: f64, x: &Array1<f64>) -> Result<f64, String> {
+        self.apply(system, x)?;
+        if let Some(eng) = system.engine_mut() {
+            eng.clear_operat_param();
+            eng.set_speed(speed);
+        }
+        system.advance_to_steady_state();
+        let op = system.engine().unwrap().operat_param();
+        Ok(op.column(&self.objective).unwrap()[0])
+    }
+}
+
+/// Result of a [`CamPhasing`](struct.CamPhasing.html) optimization
+#[derive(Debug, Clone)]
+pub struct CamPhasingResult {
+    speed: Vec<f64>,         // [RPM]
+    objective: String,       // header of the maximized column
+    intake_phase: Vec<f64>,  // [CA-deg]
+    exhaust_phase: Vec<f64>, // [CA-deg]
+    value: Vec<f64>,         // objective at the optimal phases
+}
+
+impl CamPhasingResult {
+    /// Returns the optimized speeds in RPM
+    pub fn speed(&self) -> &Vec<f64> {
+        &self.speed
+    }
+
+    /// Returns the optimal intake cam phase, in CA-deg, of each speed
+    pub fn intake_phase(&self) -> &Vec<f64> {
+        &self.intake_phase
+    }
+
+    /// Returns the optimal exhaust cam phase, in CA-deg, of each speed
+    pub fn exhaust_phase(&self) -> &Vec<f64> {
+        &self.exhaust_phase
+    }
+
+    /// Returns the maximized objective of each speed
+    pub fn value(&self) -> &Vec<f64> {
+        &self.value
+    }
+
+    /// Writes the optimal phases and objective of each speed into `file_name`
+    pub fn write_to(&self, file_name: &str) {
+        let mut data: Vec<String> = Vec::new();
+        for i in 0..self.speed.len() {
+            data.push(format!(
+                "{:.1}\t{:.2}\t{:.2}\t{:.4}\n",
+                self.speed[i], self.intake_phase[i], self.exhaust_phase[i], self.value[i]
+            ));
+        }
+        let mut file = std::fs::File::create(file_name).expect("Error opening writing file");
+        writeln!(
+            file,
+            "Speed [RPM]\tIntake phase [CA-deg]\tExhaust phase [CA-deg]\t{}",
+            self.objective
+        )
+        .expect("Unable to write data");
+        write!(file, "{}", data.join("")).expect("Unable to write data");
+    }
+}
+
+impl std::fmt::Display for CamPhasingResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Optimal cam phases:")?;
+        write!(f, "  Speed [RPM]\tIntake [CA-deg]\tExhaust [CA-deg]\t{}", self.objective)?;
+        for i in 0..self.speed.len() {
+            write!(
+                f,
+                "\n  {:.1}\t{:.2}\t{:.2}\t{:.4}",
+                self.speed[i], self.intake_phase[i], self.exhaust_phase[i], self.value[i]
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        let mut phasing = CamPhasing::new(&[2000.0], "Torque [Nm]");
+        assert!(phasing.set_intake_range(30.0, -30.0).is_err());
+        assert!(phasing.set_exhaust_range(10.0, 10.0).is_err());
+        assert!(phasing.intake.is_none() && phasing.exhaust.is_none());
+        phasing
+            .set_intake_range(-30.0, 30.0)
+            .and_then(|p| p.set_exhaust_range(-20.0, 10.0))
+            .unwrap();
+        assert_eq!(phasing.intake, Some((-30.0, 30.0)));
+        assert_eq!(phasing.exhaust, Some((-20.0, 10.0)));
+    }
+}
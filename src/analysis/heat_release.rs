@@ -0,0 +1,425 @@
+//! # Heat-release analysis
+//!
+//! Inverse analysis of a cylinder pressure trace. From a pressure-vs-crank-angle trace and the geometry
+//! of a [`Cylinder`](../../zero_dim/cylinder/struct.Cylinder.html), the struct [`HeatRelease`](struct.HeatRelease.html)
+//! computes the apparent and gross heat-release rate, the mass fraction burned (MFB) and its characteristic angles.
+//! A [`WiebeFunction`](../../reaction/combustion/struct.WiebeFunction.html) is fitted to the MFB curve and can be
+//! written back in the [`JsonCombustion`](../../engine/json_reader/struct.JsonCombustion.html) format.
+//!
+//! The analysis is done over the closed phase, from the trapping angle, when the last valve or port closes,
+//! to the exhaust opening, over a cycle of 720 CA-deg for four-stroke and 360 CA-deg for two-stroke engines.
+//! The gross heat release adds the wall heat transfer of the cylinder model to the apparent one. Therefore,
+//! the engine speed must be set to the speed at which the trace was measured. The start and end of
+//! combustion are the angles, before and after the peak, at which the gross heat-release rate crosses 1% of its peak.
+//! The Wiebe `comb_duration` is the interval between them, while `a` and `m` are fitted by least squares.
+//!
+//! ### Example
+//! ```no_run
+//! use lmb::analysis::heat_release::HeatRelease;
+//! use lmb::{Engine, Gas};
+//! use lmb_engine_simulator as lmb;
+//!
+//! let gas = Gas::new("air.json");
+//! let engine = Engine::new("engine.json", &gas).unwrap();
+//! let angle = ndarray::Array1::linspace(0.0, 719.5, 1440); // [CA-deg]
+//! let press = ndarray::Array1::from_elem(1440, 1.0);         // measured pressure [bar]
+//! let analysis = HeatRelease::new(&engine, "cyl_1", angle.view(), press.view(), 450e-6).unwrap();
+//! println!("{}", analysis);
+//! analysis.write_combustion_to("combustion.json").unwrap();
+//! ```
+
+use crate::core::traits::ZeroDim;
+use crate::engine::engine::Engine;
+use crate::engine::json_reader::{JsonCombustion, JsonWiebe};
+use crate::numerics::optimization::nelder_mead;
+use crate::reaction::combustion::WiebeFunction;
+use ndarray::*;
+
+/// Fraction of the peak gross heat-release rate that sets the start and end of combustion
+const COMB_THRESHOLD: f64 = 0.01;
+
+/// Heat-release analysis of a pressure trace over the closed phase of a cylinder
+#[derive(Debug, Clone)]
+pub struct HeatRelease {
+    angle: Array1<f64>,            // [CA-deg] - unwrapped: starting at trapping and may exceed the cycle
+    apparent_hrr: Array1<f64>,     // [J/CA-deg]
+    gross_hrr: Array1<f64>,        // [J/CA-deg]
+    burned_mass_frac: Array1<f64>, // [-]
+    start_of_combustion: f64,      // [CA-deg]
+    end_of_combustion: f64,        // [CA-deg]
+    mfb10: f64,                    // [CA-deg]
+    mfb50: f64,                    // [CA-deg]
+    mfb90: f64,                    // [CA-deg]
+    wiebe: JsonWiebe,
+    wiebe_residual: f64, // root-mean-square error of the fitted MFB
+    cycle_angle: f64,    // [CA-deg]
+}
+
+impl HeatRelease {
+    /// Analyses the pressure trace of cylinder `cyl_name` of `engine`. Inputs are the crank-angle, `angle`,
+    /// in CA-deg (reference at TDC of compression), the pressure, `press`, in bar and the trapped mass,
+    /// `trapped_mass`, in kg.
+    pub fn new(
+        engine: &Engine,
+        cyl_name: &str,
+        angle: ArrayView1<f64>,
+        press: ArrayView1<f64>,
+        trapped_mass: f64,
+    ) -> Result<HeatRelease, String> {
+        if angle.len() != press.len() {
+            return Err(format!(
+                "`angle` and `press` must have the same length. `angle`: {}, `press`: {}",
+                angle.len(),
+                press.len()
+            ));
+        }
+        if trapped_mass <= 0.0 {
+            return Err(format!("`trapped_mass` must be greater than zero: {}", trapped_mass));
+        }
+        let cylinder = match engine.cylinders().iter().find(|c| c.name() == cyl_name) {
+            Some(c) => c,
+            None => return Err(format!("cylinder `{}` was not found", cyl_name)),
+        };
+
+        // selecting the closed phase: angles are unwrapped to start at the trapping angle
+        let cycle_angle = cylinder.cycle_angle().to_degrees();
+        let ivc = cylinder.trapping_angle().rem_euclid(cycle_angle);
+        let mut evo = cylinder.exhaust_opening().rem_euclid(cycle_angle);
+        if evo < ivc {
+            evo += cycle_angle;
+        }
+        let mut closed_phase: Vec<(f64, f64)> = angle
+            .iter()
+            .zip(press.iter())
+            .map(|(a, p)| {
+                let a = a.rem_euclid(cycle_angle);
+                if a < ivc { (a + cycle_angle, *p) } else { (a, *p) }
+            })
+            .filter(|(a, _)| *a <= evo)
+            .collect();
+        closed_phase.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        closed_phase.dedup_by(|a, b| a.0 == b.0);
+        if closed_phase.len() < 3 {
+            return Err(format!(
+                "the pressure trace has less than 3 points between trapping ({:.1} CA-deg) and exhaust opening ({:.1} CA-deg)",
+                ivc, evo
+            ));
+        }
+
+        let gas_const = cylinder.get_state().gas_const;
+        let angle: Array1<f64> = closed_phase.iter().map(|(a, _)| *a).collect();
+        let press: Array1<f64> = closed_phase.iter().map(|(_, p)| *p).collect();
+        let vol: Array1<f64> = angle.mapv(|a| cylinder.volume_at(a.to_radians()).0 * 1e6);
+        let mut temp: Array1<f64> = Array1::zeros(angle.len());
+        Zip::from(&mut temp)
+            .and(&press)
+            .and(&vol)
+            .apply(|t, p, v| *t = p * 1e5 * v * 1e-6 / (trapped_mass * gas_const));
+
+        // heat-release rates in the middle of each interval
+        let apparent_hrr =
+            apparent_heat_release_rate(angle.view(), press.view(), vol.view(), temp.view(), cycle_angle);
+        let deg_per_sec = engine.sec_to_rad().to_degrees();
+        let mut gross_hrr = apparent_hrr.clone();
+        let mut mid_angle: Array1<f64> = Array1::zeros(apparent_hrr.len());
+        let mut cumulative: Array1<f64> = Array1::zeros(apparent_hrr.len());
+        let mut total_heat = 0.0;
+        for i in 0..apparent_hrr.len() {
+            let p = 0.5 * (press[i + 1] + press[i]) * 1e5;
+            let v = 0.5 * (vol[i + 1] + vol[i]) * 1e-6;
+            let t = 0.5 * (temp[i + 1] + temp[i]);
+            gross_hrr[i] -= cylinder.wall_heat_transfer(v, t, p) / deg_per_sec;
+            mid_angle[i] = 0.5 * (angle[i + 1] + angle[i]);
+            total_heat += gross_hrr[i] * (angle[i + 1] - angle[i]);
+            cumulative[i] = total_heat;
+        }
+
+        // start and end of combustion: where the gross heat-release rate crosses a fraction of its peak
+        let i_peak = argmax(&gross_hrr);
+        let threshold = COMB_THRESHOLD * gross_hrr[i_peak];
+        let i_start = (0..i_peak).rev().find(|i| gross_hrr[*i] <= threshold).unwrap_or(0);
+        let i_end = (i_peak..gross_hrr.len())
+            .find(|i| gross_hrr[*i] <= threshold)
+            .unwrap_or(gross_hrr.len() - 1);
+        let released_heat = cumulative[i_end] - cumulative[i_start];
+        if i_end <= i_start || released_heat <= 0.0 {
+            return Err(format!(
+                "no heat release was detected in the pressure trace of `{}`",
+                cyl_name
+            ));
+        }
+        let burned_mass_frac =
+            cumulative.mapv(|q| ((q - cumulative[i_start]) / released_heat).clamp(0.0, 1.0));
+        let start_of_combustion = mid_angle[i_start];
+        let end_of_combustion = mid_angle[i_end];
+        let mfb10 = crossing_angle(&mid_angle, &burned_mass_frac, 0.1, i_start);
+        let mfb50 = crossing_angle(&mid_angle, &burned_mass_frac, 0.5, i_start);
+        let mfb90 = crossing_angle(&mid_angle, &burned_mass_frac, 0.9, i_start);
+
+        // fitting `a` and `m` of a Wiebe function with the measured combustion duration
+        let comb_duration = end_of_combustion - start_of_combustion;
+        let fit_angle = mid_angle.slice(s![i_start..=i_end]);
+        let fit_mfb = burned_mass_frac.slice(s![i_start..=i_end]);
+        let squared_error = |x: &Array1<f64>| -> f64 {
+            if x[0] <= 0.0 || x[1] <= -1.0 {
+                return f64::INFINITY;
+            }
+            let wiebe = WiebeFunction::new(x[0], x[1], comb_duration);
+            fit_angle
+                .iter()
+                .zip(fit_mfb.iter())
+                .map(|(a, xb)| {
+                    let xb_wiebe =
+                        wiebe.burned_mass_frac(a.to_radians(), start_of_combustion.to_radians());
+                    (xb_wiebe - xb) * (xb_wiebe - xb)
+                })
+                .sum()
+        };
+        let (wiebe_param, error) =
+            nelder_mead(squared_error, &array![6.908, 2.0], &array![1.0, 0.5], 1e-12, 2000);
+        let wiebe_residual = (error / fit_angle.len() as f64).sqrt();
+
+        Ok(HeatRelease {
+            angle: mid_angle,
+            apparent_hrr,
+            gross_hrr,
+            burned_mass_frac,
+            start_of_combustion: start_of_combustion.rem_euclid(cycle_angle),
+            end_of_combustion: end_of_combustion.rem_euclid(cycle_angle),
+            mfb10: mfb10.rem_euclid(cycle_angle),
+            mfb50: mfb50.rem_euclid(cycle_angle),
+            mfb90: mfb90.rem_euclid(cycle_angle),
+            wiebe: JsonWiebe {
+                a: wiebe_param[0],
+                m: wiebe_param[1],
+                comb_duration,
+            },
+            wiebe_residual,
+            cycle_angle,
+        })
+    }
+
+    /// Returns the crank-angle, in CA-deg, of the heat-release arrays. It starts at the trapping angle and may exceed the cycle.
+    pub fn angle(&self) -> &Array1<f64> {
+        &self.angle
+    }
+    /// Returns the apparent heat-release rate in J/CA-deg
+    pub fn apparent_hrr(&self) -> &Array1<f64> {
+        &self.apparent_hrr
+    }
+    /// Returns the gross heat-release rate, apparent plus wall heat transfer, in J/CA-deg
+    pub fn gross_hrr(&self) -> &Array1<f64> {
+        &self.gross_hrr
+    }
+    /// Returns the mass fraction burned
+    pub fn burned_mass_frac(&self) -> &Array1<f64> {
+        &self.burned_mass_frac
+    }
+    /// Returns the start of combustion in CA-deg
+    pub fn start_of_combustion(&self) -> f64 {
+        self.start_of_combustion
+    }
+    /// Returns the end of combustion in CA-deg
+    pub fn end_of_combustion(&self) -> f64 {
+        self.end_of_combustion
+    }
+    /// Returns the combustion duration, from start to end of combustion, in CA-deg
+    pub fn comb_duration(&self) -> f64 {
+        self.wiebe.comb_duration
+    }
+    /// Returns the crank-angle, in CA-deg, at which 10% of the mass is burned
+    pub fn mfb10(&self) -> f64 {
+        self.mfb10
+    }
+    /// Returns the crank-angle, in CA-deg, at which 50% of the mass is burned
+    pub fn mfb50(&self) -> f64 {
+        self.mfb50
+    }
+    /// Returns the crank-angle, in CA-deg, at which 90% of the mass is burned
+    pub fn mfb90(&self) -> f64 {
+        self.mfb90
+    }
+    /// Returns the 10-90% burn duration in CA-deg
+    pub fn burn_duration_10_90(&self) -> f64 {
+        (self.mfb90 - self.mfb10).rem_euclid(self.cycle_angle)
+    }
+    /// Returns the parameters of the fitted Wiebe function
+    pub fn wiebe(&self) -> &JsonWiebe {
+        &self.wiebe
+    }
+    /// Returns the root-mean-square error between the fitted Wiebe function and the mass fraction burned
+    pub fn wiebe_residual(&self) -> f64 {
+        self.wiebe_residual
+    }
+
+    /// Returns the fitted combustion in the same format it is read from the engine .json file
+    pub fn to_json_combustion(&self) -> JsonCombustion {
+        JsonCombustion {
+            model: "Two-zone model".to_string(),
+            comb_ini: self.start_of_combustion,
+            wiebe: self.wiebe.clone(),
+        }
+    }
+
+    /// Writes the fitted combustion into `file_name` as the `"combustion"` field of the engine .json file
+    pub fn write_combustion_to(&self, file_name: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.to_json_combustion())
+            .map_err(|err| format!("unable to serialize the combustion: {}", err))?;
+        std::fs::write(file_name, json).map_err(|err| format!("unable to write '{}': {}", file_name, err))
+    }
+}
+
+impl std::fmt::Display for HeatRelease {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "
+            Start of combustion [CA-deg]: {:.1}
+            End of combustion [CA-deg]: {:.1}
+            MFB10 [CA-deg]: {:.1}
+            MFB50 [CA-deg]: {:.1}
+            MFB90 [CA-deg]: {:.1}
+            Burn duration 10-90 [CA-deg]: {:.1}
+            Wiebe: a = {:.3}, m = {:.3}, comb_duration = {:.1} [CA-deg] (rms error: {:.4})
+            ",
+            self.start_of_combustion,
+            self.end_of_combustion,
+            self.mfb10,
+            self.mfb50,
+            self.mfb90,
+            self.burn_duration_10_90(),
+            self.wiebe.a,
+            self.wiebe.m,
+            self.wiebe.comb_duration,
+            self.wiebe_residual,
+        )
+    }
+}
+
+/// Estimates the apparent heat-release rate, in J/CA-deg, between consecutive points of a pressure trace.
+/// The specific heat ratio is a linear-quadratic function of temperature (Gatowski et al., 1984).
+/// The crank-angle wraps at `cycle_angle`: 720 CA-deg for four-stroke and 360 CA-deg for two-stroke engines.
+/// The output has one element less than the inputs.
+pub fn apparent_heat_release_rate(
+    angle: ArrayView1<f64>, // CA-deg
+    press: ArrayView1<f64>, // bar
+    vol: ArrayView1<f64>,   // cm³
+    temp: ArrayView1<f64>,  // K
+    cycle_angle: f64,       // CA-deg
+) -> Array1<f64> {
+    let mut hrr: Array1<f64> = Array1::zeros(angle.len().saturating_sub(1));
+    for i in 0..hrr.len() {
+        let mut d_angle = angle[i + 1] - angle[i];
+        if d_angle < 0.0 {
+            d_angle += cycle_angle;
+        }
+        if d_angle <= 0.0 {
+            continue;
+        }
+        let p = 0.5 * (press[i + 1] + press[i]) * 1e5;
+        let v = 0.5 * (vol[i + 1] + vol[i]) * 1e-6;
+        let t = 0.5 * (temp[i + 1] + temp[i]);
+        let k = 1.338 - 6.0e-5 * t + 1.0e-8 * t * t;
+        let dp = (press[i + 1] - press[i]) * 1e5;
+        let dv = (vol[i + 1] - vol[i]) * 1e-6;
+        hrr[i] = (k / (k - 1.0) * p * dv + 1.0 / (k - 1.0) * v * dp) / d_angle;
+    }
+    hrr
+}
+
+/// Returns the index of the highest value of `values`
+fn argmax(values: &Array1<f64>) -> usize {
+    let mut i_max = 0;
+    for i in 0..values.len() {
+        if values[i] > values[i_max] {
+            i_max = i;
+        }
+    }
+    i_max
+}
+
+/// Returns the angle, linearly interpolated, at which `values` first reaches `target` from index `start` on
+fn crossing_angle(angle: &Array1<f64>, values: &Array1<f64>, target: f64, start: usize) -> f64 {
+    for i in (start + 1)..values.len() {
+        if values[i] >= target {
+            let frac = (target - values[i - 1]) / (values[i] - values[i - 1]);
+            return angle[i - 1] + frac * (angle[i] - angle[i - 1]);
+        }
+    }
+    angle[angle.len() - 1]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reaction::gas::Gas;
+
+    const SOC: f64 = 700.0; // [CA-deg]
+    const DURATION: f64 = 50.0; // [CA-deg]
+    const HEAT: f64 = 1000.0; // [J]
+    const MASS: f64 = 450e-6; // [kg]
+
+    // closed-phase trace of `cyl_1` releasing the gross heat of a Wiebe function, `(angle, press)` in CA-deg and bar
+    fn wiebe_trace(engine: &Engine) -> (Array1<f64>, Array1<f64>) {
+        let cylinder = &engine.cylinders()[0];
+        let gas_const = cylinder.get_state().gas_const;
+        let deg_per_sec = engine.sec_to_rad().to_degrees();
+        let wiebe = WiebeFunction::new(6.908, 2.0, DURATION);
+        let burned = |a: f64| wiebe.burned_mass_frac(a.to_radians(), SOC.to_radians());
+        let start = cylinder.trapping_angle();
+        let end = cylinder.exhaust_opening() + cylinder.cycle_angle().to_degrees();
+        let step = 0.05;
+        let steps = ((end - start) / step) as usize;
+        let mut angle = vec![start];
+        let mut press = vec![1e5];
+        for i in 0..steps {
+            let (a0, a1) = (start + i as f64 * step, start + (i + 1) as f64 * step);
+            let (v0, v1) = (cylinder.volume_at(a0.to_radians()).0, cylinder.volume_at(a1.to_radians()).0);
+            let (v, p) = (0.5 * (v0 + v1), press[i]);
+            let t = p * v / (MASS * gas_const);
+            let k = 1.338 - 6.0e-5 * t + 1.0e-8 * t * t;
+            let heat = HEAT * (burned(a1) - burned(a0)) + cylinder.wall_heat_transfer(v, t, p) / deg_per_sec * step;
+            press.push(p + ((k - 1.0) * heat - k * p * (v1 - v0)) / v);
+            angle.push(a1);
+        }
+        (Array1::from(angle), Array1::from(press) * 1e-5)
+    }
+
+    #[test]
+    fn recovers_wiebe_heat_release() {
+        let gas = Gas::new("air.json");
+        let engine = Engine::new("engine.json", &gas).unwrap();
+        let (angle, press) = wiebe_trace(&engine);
+        let analysis = HeatRelease::new(&engine, "cyl_1", angle.view(), press.view(), MASS).unwrap();
+
+        // MFB50 of the Wiebe function: a*x^(m+1) = ln(2)
+        let mfb50 = SOC + DURATION * (2f64.ln() / 6.908).powf(1.0 / 3.0);
+        assert!((analysis.mfb50() - mfb50.rem_euclid(720.0)).abs() < 0.5, "{}", analysis);
+        assert!((analysis.start_of_combustion() - SOC).abs() < 5.0, "{}", analysis);
+        assert!(analysis.comb_duration() > 30.0 && analysis.comb_duration() < DURATION + 5.0, "{}", analysis);
+        assert!(analysis.wiebe_residual() < 0.02, "{}", analysis);
+        let released: f64 = analysis
+            .gross_hrr()
+            .iter()
+            .zip(analysis.angle().windows(2))
+            .map(|(hrr, a)| hrr * (a[1] - a[0]))
+            .sum();
+        assert!((released - HEAT).abs() < 0.02 * HEAT, "{}", released);
+        // the angles of the analysis wrap at the end of the cycle
+        assert!(analysis.mfb90() < 720.0 && analysis.end_of_combustion() < 720.0);
+    }
+
+    #[test]
+    fn rejects_invalid_traces() {
+        let gas = Gas::new("air.json");
+        let engine = Engine::new("engine.json", &gas).unwrap();
+        let (angle, press) = wiebe_trace(&engine);
+        assert!(HeatRelease::new(&engine, "cyl_2", angle.view(), press.view(), MASS).is_err());
+        assert!(HeatRelease::new(&engine, "cyl_1", angle.view(), press.slice(s![1..]), MASS).is_err());
+        assert!(HeatRelease::new(&engine, "cyl_1", angle.view(), press.view(), 0.0).is_err());
+        // no point in the closed phase
+        let open_phase = array![400.0, 450.0, 500.0];
+        assert!(HeatRelease::new(&engine, "cyl_1", open_phase.view(), press.slice(s![..3]), MASS).is_err());
+    }
+}
@@ -0,0 +1,4 @@
+//! Contains analyses of measured or simulated engine data
+pub mod heat_release;
+pub mod calibration;
+pub mod cam_phasing;
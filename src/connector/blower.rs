@@ -0,0 +1,326 @@
+#![allow(non_snake_case)]
+
+use crate::core::traits::{Conn, Connector, SaveData};
+use crate::{BasicProperties, FlowRatio};
+use ndarray::*;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+struct JsonBlowerMap {
+    /// blower speed [RPM]
+    speed: Vec<f64>,
+    pressure_ratio: Vec<f64>,
+    /// one row per speed
+    volumetric_efficiency: Vec<Vec<f64>>,
+    /// one row per speed
+    isentropic_efficiency: Vec<Vec<f64>>,
+}
+
+/// Volumetric and isentropic efficiency map of a positive-displacement blower, tabulated against
+/// blower speed and pressure ratio. The map is interpolated bilinearly and is constant beyond its limits.
+///
+/// The `.json` file has the form:
+/// ```json
+/// {
+///     "speed": [5000, 10000, 15000],
+///     "pressure_ratio": [1.0, 1.4, 1.8],
+///     "volumetric_efficiency": [[0.85, 0.75, 0.65], [0.90, 0.84, 0.78], [0.92, 0.88, 0.84]],
+///     "isentropic_efficiency": [[0.60, 0.55, 0.48], [0.62, 0.60, 0.55], [0.60, 0.58, 0.55]]
+/// }
+/// ```
+/// where `speed` is in RPM and each row of the efficiencies corresponds to one speed.
+#[derive(Debug, Clone)]
+pub struct BlowerMap {
+    speed: Vec<f64>,       // [RPM]
+    press_ratio: Vec<f64>, // [-]
+    vol_effic: Array2<f64>,
+    isen_effic: Array2<f64>,
+}
+
+impl BlowerMap {
+    /// `speed` in RPM and `press_ratio` must be increasing. The efficiencies have one row per speed
+    /// and one column per pressure ratio.
+    pub fn new(
+        speed: &[f64],
+        press_ratio: &[f64],
+        vol_effic: Array2<f64>,
+        isen_effic: Array2<f64>,
+    ) -> Result<BlowerMap, String> {
+        if speed.is_empty() || press_ratio.is_empty() {
+            return Err("blower map must have at least one speed and one pressure ratio".to_string());
+        }
+        if speed.windows(2).any(|w| w[1] <= w[0]) || press_ratio.windows(2).any(|w| w[1] <= w[0]) {
+            return Err("blower map speeds and pressure ratios must be increasing".to_string());
+        }
+        let dim = (speed.len(), press_ratio.len());
+        if vol_effic.dim() != dim || isen_effic.dim() != dim {
+            return Err(format!(
+                "blower map efficiencies must have {} rows (speeds) and {} columns (pressure ratios)",
+                dim.0, dim.1
+            ));
+        }
+        if vol_effic.iter().any(|e| *e < 0.0) {
+            return Err("volumetric efficiency must be positive".to_string());
+        }
+        if isen_effic.iter().any(|e| *e <= 0.0 || *e > 1.0) {
+            return Err("isentropic efficiency must be between 0.0 and 1.0".to_string());
+        }
+        Ok(BlowerMap {
+            speed: speed.to_vec(),
+            press_ratio: press_ratio.to_vec(),
+            vol_effic,
+            isen_effic,
+        })
+    }
+
+    /// Reads the map from a `.json` file
+    pub fn from_file(file_name: &str) -> Result<BlowerMap, String> {
+        let json_file = match std::fs::read_to_string(file_name) {
+            Ok(f) => f,
+            Err(err) => return Err(format!("unable to read '{}': {}", file_name, err)),
+        };
+        let json_map: JsonBlowerMap = match serde_json::from_str(&json_file) {
+            Ok(m) => m,
+            Err(err) => return Err(format!("unable to parse '{}': {}", file_name, err)),
+        };
+        let to_array = |rows: &Vec<Vec<f64>>| -> Result<Array2<f64>, String> {
+            let ncols = json_map.pressure_ratio.len();
+            if rows.len() != json_map.speed.len() || rows.iter().any(|r| r.len() != ncols) {
+                return Err(format!(
+                    "blower map efficiencies must have {} rows (speeds) and {} columns (pressure ratios)",
+                    json_map.speed.len(),
+                    ncols
+                ));
+            }
+            let flat: Vec<f64> = rows.iter().flatten().copied().collect();
+            Ok(Array2::from_shape_vec((rows.len(), ncols), flat).unwrap())
+        };
+        BlowerMap::new(
+            &json_map.speed,
+            &json_map.pressure_ratio,
+            to_array(&json_map.volumetric_efficiency)?,
+            to_array(&json_map.isentropic_efficiency)?,
+        )
+    }
+
+    /// Returns the volumetric and isentropic efficiencies at `speed`, in RPM, and `press_ratio`
+    pub fn efficiencies(&self, speed: f64, press_ratio: f64) -> (f64, f64) {
+        let (i, fi) = BlowerMap::bracket(&self.speed, speed);
+        let (j, fj) = BlowerMap::bracket(&self.press_ratio, press_ratio);
+        let interp = |table: &Array2<f64>| -> f64 {
+            let i1 = (i + 1).min(self.speed.len() - 1);
+            let j1 = (j + 1).min(self.press_ratio.len() - 1);
+            let low = table[[i, j]] + fj * (table[[i, j1]] - table[[i, j]]);
+            let high = table[[i1, j]] + fj * (table[[i1, j1]] - table[[i1, j]]);
+            low + fi * (high - low)
+        };
+        (interp(&self.vol_effic), interp(&self.isen_effic))
+    }
+
+    // index of the lower point and the interpolation fraction, clamped to the table
+    fn bracket(points: &[f64], value: f64) -> (usize, f64) {
+        let last = points.len() - 1;
+        if last == 0 || value <= points[0] {
+            return (0, 0.0);
+        }
+        if value >= points[last] {
+            return (last, 0.0);
+        }
+        let i = points.iter().position(|p| *p > value).unwrap() - 1;
+        (i, (value - points[i]) / (points[i + 1] - points[i]))
+    }
+}
+
+/// Connector struct representing a positive-displacement blower or supercharger driven by the crankshaft.
+/// Its speed is a fixed ratio of the `Engine` speed and it delivers the flow from the first to the
+/// second connected object. The power it draws is subtracted from the engine power.
+pub struct Blower {
+    name: String,
+    displacement: f64, // [m³/rev]
+    drive_ratio: f64,
+    map: BlowerMap,
+    connecting: Vec<String>,
+    flow_ratio: Vec<FlowRatio>,
+    speed: f64, // [RPM]
+    press_ratio: f64,
+    vol_effic: f64,
+    isen_effic: f64,
+    outlet_temp: f64, // [K]
+    power: f64,       // [W] - drawn from the crankshaft
+}
+
+impl Blower {
+    /// `displacement` is the volume, in m³, displaced per blower revolution and `drive_ratio` is the
+    /// ratio between blower and engine speed
+    pub fn new(
+        name: &str,
+        displacement: f64,
+        drive_ratio: f64,
+        map: BlowerMap,
+        connecting: Vec<String>,
+    ) -> Result<Blower, String> {
+        if connecting.len() != 2 {
+            return Err(format!(
+                "Connector `Blower` must connect only two elements, connecting: {}",
+                connecting.len()
+            ));
+        }
+        if displacement <= 0.0 {
+            return Err(format!("`displacement` must be greater than zero: {}", displacement));
+        }
+        if drive_ratio <= 0.0 {
+            return Err(format!("`drive_ratio` must be greater than zero: {}", drive_ratio));
+        }
+        Ok(Blower {
+            name: name.to_string(),
+            displacement,
+            drive_ratio,
+            map,
+            connecting,
+            flow_ratio: vec![FlowRatio::new(), FlowRatio::new()],
+            speed: 0.0,
+            press_ratio: 1.0,
+            vol_effic: 0.0,
+            isen_effic: 0.0,
+            outlet_temp: 0.0,
+            power: 0.0,
+        })
+    }
+
+    /// Returns the power, in W, drawn from the crankshaft
+    pub fn power(&self) -> f64 {
+        self.power
+    }
+}
+
+impl Connector for Blower {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn connecting(&self) -> &Vec<String> {
+        &self.connecting
+    }
+    fn connect_to(&mut self, elem_name: &str) -> Result<(), String> {
+        self.connecting.push(elem_name.to_string());
+        self.flow_ratio.push(FlowRatio::new());
+        if self.connecting.len() != 2 {
+            return Err("Wrong the number of connections. Blower should connect only two elements".to_string());
+        }
+        Ok(())
+    }
+    fn update_flow_ratio(&mut self, prop: Vec<BasicProperties>, _step: f64) {
+        let inlet = &prop[0];
+        let T_in = inlet.temperature;
+        let k = inlet.cp_cv;
+        let R = inlet.gas_const;
+        let cp = k * R / (k - 1.0);
+
+        self.press_ratio = prop[1].pressure / inlet.pressure;
+        let (vol_effic, isen_effic) = self.map.efficiencies(self.speed, self.press_ratio);
+        self.vol_effic = vol_effic;
+        self.isen_effic = isen_effic;
+        let density = inlet.pressure / (R * T_in);
+        let m_dot = vol_effic * self.displacement * self.speed / 60.0 * density;
+
+        let isentropic_rise = T_in * (self.press_ratio.powf((k - 1.0) / k) - 1.0);
+        self.outlet_temp = T_in + isentropic_rise.max(0.0) / isen_effic;
+        self.power = m_dot * cp * (self.outlet_temp - T_in);
+
+        self.flow_ratio[0].mass_flow = -m_dot;
+        self.flow_ratio[0].enthalpy_flow = -m_dot * cp * T_in;
+        self.flow_ratio[0].species_flow = -m_dot * &inlet.mass_frac;
+        self.flow_ratio[1].mass_flow = m_dot;
+        self.flow_ratio[1].enthalpy_flow = m_dot * cp * self.outlet_temp;
+        self.flow_ratio[1].species_flow = m_dot * &inlet.mass_frac;
+    }
+    fn get_flow_ratio(&self, elem_name: &str) -> Result<&FlowRatio, String> {
+        match self.connecting.iter().position(|name| name == elem_name) {
+            Some(i) => Ok(&self.flow_ratio[i]),
+            None => Err(format!(
+                "object '{}' was not found in '{}'",
+                elem_name,
+                self.name()
+            )),
+        }
+    }
+    fn shaft_power(&self) -> Option<f64> {
+        Some(-self.power)
+    }
+    fn set_shaft_speed(&mut self, speed: f64) -> Result<(), String> {
+        self.speed = speed;
+        Ok(())
+    }
+    fn drive_ratio(&self) -> Option<f64> {
+        Some(self.drive_ratio)
+    }
+}
+
+impl SaveData for Blower {
+    fn get_headers(&self) -> String {
+        "mass flow [kg/s]\tspeed [RPM]\tpressure ratio [-]\tvolumetric efficiency [-]\tisentropic efficiency [-]\toutlet temperature [K]\tpower [W]".to_string()
+    }
+    fn num_storable_variables(&self) -> usize {
+        7
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        array![
+            self.flow_ratio[1].mass_flow,
+            self.speed,
+            self.press_ratio,
+            self.vol_effic,
+            self.isen_effic,
+            self.outlet_temp,
+            self.power
+        ]
+    }
+}
+
+impl Conn for Blower {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> BlowerMap {
+        BlowerMap::new(
+            &[1000.0, 3000.0],
+            &[1.2, 1.6, 2.0],
+            array![[0.8, 0.7, 0.6], [0.9, 0.85, 0.8]],
+            array![[0.5, 0.6, 0.55], [0.6, 0.7, 0.65]],
+        )
+        .unwrap()
+    }
+
+    fn assert_effic(effic: (f64, f64), expected: (f64, f64)) {
+        assert!((effic.0 - expected.0).abs() < 1e-12, "{:?} != {:?}", effic, expected);
+        assert!((effic.1 - expected.1).abs() < 1e-12, "{:?} != {:?}", effic, expected);
+    }
+
+    #[test]
+    fn interpolates_at_nodes_and_midpoints() {
+        let map = map();
+        assert_effic(map.efficiencies(1000.0, 1.2), (0.8, 0.5));
+        assert_effic(map.efficiencies(3000.0, 1.6), (0.85, 0.7));
+        assert_effic(map.efficiencies(2000.0, 1.6), (0.775, 0.65));
+        assert_effic(map.efficiencies(3000.0, 1.8), (0.825, 0.675));
+        assert_effic(map.efficiencies(2000.0, 1.4), (0.8125, 0.6));
+    }
+
+    #[test]
+    fn clamps_outside_the_map() {
+        let map = map();
+        assert_effic(map.efficiencies(500.0, 2.5), (0.6, 0.55));
+        assert_effic(map.efficiencies(4000.0, 1.0), (0.9, 0.6));
+        let single = BlowerMap::new(&[2000.0], &[1.5], array![[0.9]], array![[0.6]]).unwrap();
+        assert_effic(single.efficiencies(1000.0, 2.0), (0.9, 0.6));
+    }
+
+    #[test]
+    fn rejects_invalid_maps() {
+        let vol = array![[0.8, 0.7], [0.9, 0.85]];
+        let isen = array![[0.5, 0.6], [0.6, 0.7]];
+        assert!(BlowerMap::new(&[3000.0, 1000.0], &[1.2, 1.6], vol.clone(), isen.clone()).is_err());
+        assert!(BlowerMap::new(&[1000.0, 3000.0], &[1.2, 1.6, 2.0], vol.clone(), isen.clone()).is_err());
+        assert!(BlowerMap::new(&[1000.0, 3000.0], &[1.2, 1.6], vol, isen * 2.0).is_err());
+    }
+}
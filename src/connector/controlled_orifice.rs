@@ -0,0 +1,136 @@
+use crate::connector::orifice::Orifice;
+use crate::core::traits::{Conn, Connector, SaveData};
+use crate::{BasicProperties, FlowRatio};
+use ndarray::*;
+
+/// Connector struct representing an orifice whose area is controlled by its opening, such as
+/// an EGR valve or a wastegate
+pub struct ControlledOrifice {
+    orifice: Orifice,
+    max_area: f64,
+    opening: f64, // between 0 and 1
+}
+
+impl ControlledOrifice {
+    /// `diam` is the diameter, in m, when fully open and `opening` is between 0 (closed) and 1 (fully open)
+    pub fn new(
+        name: &str,
+        diam: f64,
+        discharge_coeff: f64,
+        opening: f64,
+        connecting: Vec<String>,
+    ) -> Result<ControlledOrifice, String> {
+        let mut orifice = Orifice::new(name, diam, discharge_coeff, connecting)?;
+        let max_area = orifice.area();
+        if !(0.0..=1.0).contains(&opening) {
+            return Err(format!("`opening` must be between 0.0 and 1.0: {}", opening));
+        }
+        orifice.set_area(opening * max_area);
+        Ok(ControlledOrifice {
+            orifice,
+            max_area,
+            opening,
+        })
+    }
+
+    /// Returns the opening, between 0 (closed) and 1 (fully open)
+    pub fn opening(&self) -> f64 {
+        self.opening
+    }
+}
+
+impl Connector for ControlledOrifice {
+    fn name(&self) -> &str {
+        self.orifice.name()
+    }
+    fn connecting(&self) -> &Vec<String> {
+        self.orifice.connecting()
+    }
+    fn connect_to(&mut self, elem_name: &str) -> Result<(), String> {
+        self.orifice.connect_to(elem_name)
+    }
+    fn update_flow_ratio(&mut self, prop: Vec<BasicProperties>, step: f64) {
+        self.orifice.update_flow_ratio(prop, step)
+    }
+    fn get_flow_ratio<'a>(&'a self, elem_name: &str) -> Result<&'a FlowRatio, String> {
+        self.orifice.get_flow_ratio(elem_name)
+    }
+    fn discharge_coeff(&self) -> Option<f64> {
+        self.orifice.discharge_coeff()
+    }
+    fn set_discharge_coeff(&mut self, discharge_coeff: f64) -> Result<(), String> {
+        self.orifice.set_discharge_coeff(discharge_coeff)
+    }
+    fn set_opening(&mut self, opening: f64) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&opening) {
+            return Err(format!("`opening` must be between 0.0 and 1.0: {}", opening));
+        }
+        self.opening = opening;
+        self.orifice.set_area(opening * self.max_area);
+        Ok(())
+    }
+}
+
+impl SaveData for ControlledOrifice {
+    fn get_headers(&self) -> String {
+        "mass flow [kg/s]\tenthalpy flow [J/s]\topening [-]".to_string()
+    }
+    fn num_storable_variables(&self) -> usize {
+        3
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        let flow = self.orifice.get_storable_data();
+        array![flow[0], flow[1], self.opening]
+    }
+}
+
+impl Conn for ControlledOrifice {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reaction::gas::Gas;
+
+    fn state<'a>(name: &'a str, pressure: f64, gas: &Gas) -> BasicProperties<'a> {
+        BasicProperties {
+            name,
+            pressure,
+            temperature: gas.T(),
+            cp: gas.cp(),
+            cv: gas.cv(),
+            cp_cv: gas.k(),
+            gas_const: gas.R(),
+            crank_angle: None,
+            mass_frac: gas.mass_frac(),
+        }
+    }
+
+    fn mass_flow(orifice: &mut ControlledOrifice, gas: &Gas) -> f64 {
+        orifice.update_flow_ratio(vec![state("exhaust", 1.5e5, gas), state("intake", 1e5, gas)], 1e-5);
+        orifice.get_flow_ratio("intake").unwrap().mass_flow
+    }
+
+    #[test]
+    fn mass_flow_follows_opening() {
+        let gas = Gas::new("air.json");
+        let connecting = vec!["exhaust".to_string(), "intake".to_string()];
+        let mut orifice = ControlledOrifice::new("egr", 0.02, 0.8, 1.0, connecting).unwrap();
+        let full = mass_flow(&mut orifice, &gas);
+        assert!(full > 0.0);
+        orifice.set_opening(0.25).unwrap();
+        assert_eq!(orifice.opening(), 0.25);
+        assert!((mass_flow(&mut orifice, &gas) - 0.25 * full).abs() < 1e-12 * full);
+        orifice.set_opening(0.0).unwrap();
+        assert_eq!(mass_flow(&mut orifice, &gas), 0.0);
+        assert_eq!(orifice.get_storable_data()[2], 0.0);
+    }
+
+    #[test]
+    fn rejects_openings_out_of_range() {
+        let connecting = vec!["exhaust".to_string(), "intake".to_string()];
+        assert!(ControlledOrifice::new("egr", 0.02, 0.8, 1.5, connecting.clone()).is_err());
+        let mut orifice = ControlledOrifice::new("egr", 0.02, 0.8, 0.5, connecting).unwrap();
+        assert!(orifice.set_opening(-0.1).is_err());
+        assert_eq!(orifice.opening(), 0.5);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::connector::orifice::Orifice;
+use crate::core::traits::{Conn, Connector, SaveData};
+use crate::{BasicProperties, FlowRatio};
+use ndarray::*;
+
+/// Thermal performance of a cooler
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeatExchange {
+    /// Constant effectiveness, between 0 and 1
+    Effectiveness(f64),
+    /// Overall heat transfer coefficient times the area, `UA` in W/K. The effectiveness is
+    /// `1 - exp(-NTU)`, with `NTU = UA/(m_dot*cp)`, since the coolant capacity is assumed infinite
+    UA(f64),
+}
+
+impl HeatExchange {
+    /// Returns the effectiveness for the gas `mass_flow`, in kg/s, and `cp`, in J/(kg*K)
+    pub fn effectiveness(&self, mass_flow: f64, cp: f64) -> f64 {
+        match self {
+            HeatExchange::Effectiveness(eff) => *eff,
+            HeatExchange::UA(ua) => {
+                if mass_flow <= 0.0 {
+                    1.0
+                } else {
+                    1.0 - (-ua / (mass_flow * cp)).exp()
+                }
+            }
+        }
+    }
+}
+
+/// Connector struct representing an EGR cooler. The flow is restricted as through an orifice and
+/// the gas leaves it cooled towards the fixed coolant temperature.
+pub struct EgrCooler {
+    orifice: Orifice,
+    heat_exchange: HeatExchange,
+    coolant_temp: f64, // [K]
+    flow_ratio: Vec<FlowRatio>,
+    inlet_temp: f64,  // [K]
+    outlet_temp: f64, // [K]
+    heat_flow: f64,   // [W] - removed from the gas
+}
+
+impl EgrCooler {
+    /// `diam` is the diameter, in m, of the equivalent orifice and `coolant_temp` is in K
+    pub fn new(
+        name: &str,
+        diam: f64,
+        discharge_coeff: f64,
+        heat_exchange: HeatExchange,
+        coolant_temp: f64,
+        connecting: Vec<String>,
+    ) -> Result<EgrCooler, String> {
+        match heat_exchange {
+            HeatExchange::Effectiveness(eff) if !(0.0..=1.0).contains(&eff) => {
+                return Err(format!("effectiveness must be between 0.0 and 1.0: {}", eff));
+            }
+            HeatExchange::UA(ua) if ua < 0.0 => {
+                return Err(format!("`UA` must be positive: {}", ua));
+            }
+            _ => {}
+        }
+        if coolant_temp <= 0.0 {
+            return Err(format!("`coolant_temp` must be greater than zero: {}", coolant_temp));
+        }
+        let num_conn = connecting.len();
+        Ok(EgrCooler {
+            orifice: Orifice::new(name, diam, discharge_coeff, connecting)?,
+            heat_exchange,
+            coolant_temp,
+            flow_ratio: vec![FlowRatio::new(); num_conn],
+            inlet_temp: coolant_temp,
+            outlet_temp: coolant_temp,
+            heat_flow: 0.0,
+        })
+    }
+
+    /// Returns the heat, in W, removed from the gas
+    pub fn heat_flow(&self) -> f64 {
+        self.heat_flow
+    }
+}
+
+impl Connector for EgrCooler {
+    fn name(&self) -> &str {
+        self.orifice.name()
+    }
+    fn connecting(&self) -> &Vec<String> {
+        self.orifice.connecting()
+    }
+    fn connect_to(&mut self, elem_name: &str) -> Result<(), String> {
+        self.flow_ratio.push(FlowRatio::new());
+        self.orifice.connect_to(elem_name)
+    }
+    fn update_flow_ratio(&mut self, prop: Vec<BasicProperties>, step: f64) {
+        let states: Vec<(f64, f64)> = prop
+            .iter()
+            .map(|p| (p.temperature, p.cp_cv * p.gas_const / (p.cp_cv - 1.0)))
+            .collect();
+        let names: Vec<String> = prop.iter().map(|p| p.name.to_string()).collect();
+        self.orifice.update_flow_ratio(prop, step);
+
+        // upstream object loses mass: its enthalpy leaves at its own temperature
+        let mut i_up: Option<usize> = None;
+        for (i, name) in self.connecting().clone().iter().enumerate() {
+            self.flow_ratio[i] = self.orifice.get_flow_ratio(name).unwrap().clone();
+            if self.flow_ratio[i].mass_flow < 0.0 {
+                i_up = Some(i);
+            }
+        }
+        let i_up = match i_up {
+            Some(i) => i,
+            None => {
+                self.heat_flow = 0.0;
+                return;
+            }
+        };
+        let i_down = 1 - i_up;
+        let j_up = names
+            .iter()
+            .position(|n| *n == self.connecting()[i_up])
+            .unwrap();
+
+        let (temp_in, cp) = states[j_up];
+        let mass_flow = -self.flow_ratio[i_up].mass_flow;
+        let effectiveness = self.heat_exchange.effectiveness(mass_flow, cp);
+        self.inlet_temp = temp_in;
+        self.outlet_temp = temp_in - effectiveness * (temp_in - self.coolant_temp);
+        self.heat_flow = mass_flow * cp * (self.inlet_temp - self.outlet_temp);
+        self.flow_ratio[i_down].enthalpy_flow = -self.flow_ratio[i_up].enthalpy_flow - self.heat_flow;
+    }
+    fn get_flow_ratio<'a>(&'a self, elem_name: &str) -> Result<&'a FlowRatio, String> {
+        match self.connecting().iter().position(|name| name == elem_name) {
+            Some(i) => Ok(&self.flow_ratio[i]),
+            None => Err(format!(
+                "object '{}' was not found in '{}'",
+                elem_name,
+                self.name()
+            )),
+        }
+    }
+    fn discharge_coeff(&self) -> Option<f64> {
+        self.orifice.discharge_coeff()
+    }
+    fn set_discharge_coeff(&mut self, discharge_coeff: f64) -> Result<(), String> {
+        self.orifice.set_discharge_coeff(discharge_coeff)
+    }
+}
+
+impl SaveData for EgrCooler {
+    fn get_headers(&self) -> String {
+        "mass flow [kg/s]\tinlet temperature [K]\toutlet temperature [K]\theat flow [W]".to_string()
+    }
+    fn num_storable_variables(&self) -> usize {
+        4
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        array![
+            self.flow_ratio[0].mass_flow,
+            self.inlet_temp,
+            self.outlet_temp,
+            self.heat_flow
+        ]
+    }
+}
+
+impl Conn for EgrCooler {}
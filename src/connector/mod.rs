@@ -1,3 +1,9 @@
 //! Contains all **Connector elements**
 pub mod valve;
 pub mod orifice;
+pub mod controlled_orifice;
+pub mod egr_cooler;
+pub mod turbocharger;
+pub mod blower;
+pub mod port;
+pub mod reed_valve;
@@ -41,6 +41,16 @@ impl Orifice {
             flow_ratio,
         })
     }
+
+    /// Returns the flow area in m²
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// Set the flow area, in m²
+    pub fn set_area(&mut self, area: f64) {
+        self.area = area;
+    }
 }
 
 impl Connector for Orifice {
@@ -118,6 +128,7 @@ impl Connector for Orifice {
         let ii = i; // store the position to use in the downstream
         self.flow_ratio[i].mass_flow = -m_dot;
         self.flow_ratio[i].enthalpy_flow = -m_dot * k * R / km * prop[i_up].temperature;
+        self.flow_ratio[i].species_flow = -m_dot * &prop[i_up].mass_frac;
 
         // updating `flow_ratio` for downstream objects
         let i = match self
@@ -138,6 +149,7 @@ impl Connector for Orifice {
         };
         self.flow_ratio[i].mass_flow = -self.flow_ratio[ii].mass_flow;
         self.flow_ratio[i].enthalpy_flow = -self.flow_ratio[ii].enthalpy_flow;
+        self.flow_ratio[i].species_flow = -&self.flow_ratio[ii].species_flow;
     }
     fn get_flow_ratio<'a>(&'a self, elem_name: &str) -> Result<&'a FlowRatio, String> {
         match self.connecting.iter().position(|name| name == elem_name) {
@@ -149,6 +161,19 @@ impl Connector for Orifice {
             )),
         }
     }
+    fn discharge_coeff(&self) -> Option<f64> {
+        Some(self.discharge_coeff)
+    }
+    fn set_discharge_coeff(&mut self, discharge_coeff: f64) -> Result<(), String> {
+        if discharge_coeff > 1.0 || discharge_coeff <= 0.0 {
+            return Err(format!(
+                "`discharge_coeff` must be between 0.0 and 1.0: {}",
+                discharge_coeff
+            ));
+        }
+        self.discharge_coeff = discharge_coeff;
+        Ok(())
+    }
 }
 
 impl SaveData for Orifice {
@@ -0,0 +1,279 @@
+#![allow(non_snake_case)]
+
+use crate::core::traits::{Conn, Connector, SaveData, ZeroDim};
+use crate::zero_dim::cylinder::{Crankshaft, Cylinder};
+use crate::{BasicProperties, FlowRatio};
+use ndarray::*;
+
+/// Edge of the piston that uncovers the ports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PortEdge {
+    /// Transfer and exhaust ports: opened while the piston crown is below the top edge of the ports
+    Crown,
+    /// Intake ports of crankcase-scavenged engines: opened while the piston skirt is above the bottom edge of the ports
+    Skirt,
+}
+
+/// Connector struct representing a set of identical ports in the cylinder liner of a two-stroke engine.
+/// The flow area is the part of the ports uncovered by the piston and the flow follows the orifice equation.
+///
+/// The crank-angle is taken from the connected object that provides it: the cylinder for transfer and exhaust ports.
+#[derive(Clone)]
+pub struct Port {
+    name: String,
+    edge: PortEdge,
+    opening_angle: f64, // [CA deg]
+    closing_angle: f64, // [CA deg]
+    edge_position: f64, // [m] - piston position, from top-dead-center, at which the ports start to open
+    width: f64,         // [m] - total width of the ports
+    height: f64,        // [m]
+    discharge_coeff: f64,
+    crankshaft: Crankshaft,
+    angle: f64, // [CA deg]
+    area: f64,  // [m²]
+    connecting: Vec<String>,
+    flow_ratio: Vec<FlowRatio>,
+}
+
+impl Port {
+    /// Creates the ports of `cylinder` opening at `opening_angle`, in CA-deg. `width`, the total width of the ports, and `height` must be in meters.
+    /// Crown-edge ports are connected to the cylinder, skirt-edge ports are not connected.
+    pub fn new(
+        name: String,
+        edge: PortEdge,
+        opening_angle: f64,
+        width: f64,
+        height: f64,
+        discharge_coeff: f64,
+        cylinder: &Cylinder,
+    ) -> Result<Port, String> {
+        if width <= 0.0 || height <= 0.0 {
+            return Err(format!("port `{}`: width and height must be greater than zero", name));
+        }
+        if discharge_coeff > 1.0 || discharge_coeff <= 0.0 {
+            return Err(format!(
+                "port `{}`: `discharge_coeff` must be between 0.0 and 1.0: {}",
+                name, discharge_coeff
+            ));
+        }
+        let crankshaft = cylinder.crankshaft().clone();
+        let opening = opening_angle.to_radians();
+        let closing_angle = crankshaft.port_closing(opening).to_degrees();
+        let (connecting, flow_ratio) = match edge {
+            PortEdge::Crown => (vec![cylinder.name().to_string()], vec![FlowRatio::new()]),
+            PortEdge::Skirt => (Vec::new(), Vec::new()),
+        };
+        Ok(Port {
+            name,
+            edge,
+            opening_angle,
+            closing_angle,
+            edge_position: crankshaft.kinematics(opening).0,
+            width,
+            height,
+            discharge_coeff,
+            crankshaft,
+            angle: 0.0,
+            area: 0.0,
+            connecting,
+            flow_ratio,
+        })
+    }
+
+    /// Returns the crank-angle, in degrees, at which the ports open
+    pub fn opening_angle(&self) -> f64 {
+        self.opening_angle
+    }
+
+    /// Returns the crank-angle, in degrees, at which the ports close
+    pub fn closing_angle(&self) -> f64 {
+        self.closing_angle
+    }
+
+    /// Returns the flow area, in m², uncovered by the piston at `angle`, in CA radian
+    pub fn area_at(&self, angle: f64) -> f64 {
+        let position = self.crankshaft.kinematics(angle).0;
+        let uncovered = match self.edge {
+            PortEdge::Crown => position - self.edge_position,
+            PortEdge::Skirt => self.edge_position - position,
+        };
+        self.width * uncovered.clamp(0.0, self.height)
+    }
+}
+
+impl Connector for Port {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn connecting(&self) -> &Vec<String> {
+        &self.connecting
+    }
+    fn connect_to(&mut self, elem_name: &str) -> Result<(), String> {
+        self.connecting.push(elem_name.to_string());
+        self.flow_ratio.push(FlowRatio::new());
+        if self.connecting.len() > 2 {
+            return Err("Wrong the number of connections. Port should connect only two elements".to_string());
+        }
+        Ok(())
+    }
+    fn update_flow_ratio(&mut self, prop: Vec<BasicProperties>, _step: f64) {
+        let crank_angle = match prop.iter().find_map(|info| info.crank_angle) {
+            Some(angle) => angle,
+            None => {
+                println!("'{}' did not get crank-angle", self.name());
+                std::process::exit(1)
+            }
+        };
+        self.angle = crank_angle.to_degrees();
+        self.area = self.area_at(crank_angle);
+        self.flow_ratio.iter_mut().for_each(|f| *f = FlowRatio::new());
+        if self.area <= 0.0 {
+            return;
+        }
+
+        // checking flow diretion
+        let press_ratio = prop[0].pressure / prop[1].pressure;
+        let (i_up, i_down) = if press_ratio > 1.00000001 {
+            (0, 1)
+        } else if press_ratio < 0.99999999 {
+            (1, 0)
+        } else {
+            return;
+        };
+
+        let P_up = prop[i_up].pressure;
+        let T_up = prop[i_up].temperature;
+        let k = prop[i_up].cp_cv;
+        let R = prop[i_up].gas_const;
+        let P_du = prop[i_down].pressure / P_up;
+
+        let kp = k + 1.0;
+        let km = k - 1.0;
+        let m_dot = if P_du > (2.0 / kp).powf(k / km) {
+            self.discharge_coeff * self.area * P_up / (R * T_up).sqrt()
+                * (2.0 * k / km * (P_du.powf(2.0 / k) - P_du.powf(kp / k))).sqrt()
+        } else {
+            // chocked flow: independent of downstream pressure
+            self.discharge_coeff * self.area * P_up / (R * T_up).sqrt() * (k * (2.0 / kp).powf(kp / km)).sqrt()
+        };
+
+        let position = |name: &str| -> usize {
+            match self.connecting.iter().position(|n| n == name) {
+                Some(i) => i,
+                None => {
+                    println!("Error at Port::update_flow_ratio:");
+                    println!(" object '{}' is not connected to '{}'", name, self.name);
+                    std::process::exit(1)
+                }
+            }
+        };
+        let i = position(prop[i_up].name);
+        let ii = position(prop[i_down].name);
+        self.flow_ratio[i].mass_flow = -m_dot;
+        self.flow_ratio[i].enthalpy_flow = -m_dot * k * R / km * T_up;
+        self.flow_ratio[i].species_flow = -m_dot * &prop[i_up].mass_frac;
+        self.flow_ratio[ii].mass_flow = m_dot;
+        self.flow_ratio[ii].enthalpy_flow = m_dot * k * R / km * T_up;
+        self.flow_ratio[ii].species_flow = m_dot * &prop[i_up].mass_frac;
+    }
+    fn get_flow_ratio(&self, elem_name: &str) -> Result<&FlowRatio, String> {
+        match self.connecting.iter().position(|name| name == elem_name) {
+            Some(i) => Ok(&self.flow_ratio[i]),
+            None => Err(format!(
+                "object '{}' was not found in '{}'",
+                elem_name,
+                self.name()
+            )),
+        }
+    }
+    fn discharge_coeff(&self) -> Option<f64> {
+        Some(self.discharge_coeff)
+    }
+    fn set_discharge_coeff(&mut self, discharge_coeff: f64) -> Result<(), String> {
+        if discharge_coeff > 1.0 || discharge_coeff <= 0.0 {
+            return Err(format!(
+                "`discharge_coeff` must be between 0.0 and 1.0: {}",
+                discharge_coeff
+            ));
+        }
+        self.discharge_coeff = discharge_coeff;
+        Ok(())
+    }
+}
+
+impl SaveData for Port {
+    fn get_headers(&self) -> String {
+        "crank-angle [deg]\tmass flow [kg/s]\tenthalpy flow [J/s]\tarea [cm²]".to_string()
+    }
+    fn num_storable_variables(&self) -> usize {
+        4
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        let (mass_flow, enthalpy_flow) = match self.flow_ratio.first() {
+            Some(flow) => (flow.mass_flow, flow.enthalpy_flow),
+            None => (0.0, 0.0),
+        };
+        array![self.angle, mass_flow, enthalpy_flow, self.area * 1e4]
+    }
+}
+
+impl Conn for Port {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::engine::Engine;
+    use crate::reaction::gas::Gas;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn crown_ports_are_uncovered_around_bottom_dead_center() {
+        let gas = Gas::new("air.json");
+        let engine = Engine::new("engine_Ryobi.json", &gas).unwrap();
+        let exhaust = engine.ports().iter().find(|p| p.name() == "exhaust").unwrap();
+        let transfer = engine.ports().iter().find(|p| p.name() == "transfer").unwrap();
+
+        // without eccentricity the ports close as far after bottom-dead-center as they open before it
+        assert!((exhaust.closing_angle() - 260.0).abs() < 1e-6);
+        assert!((transfer.closing_angle() - 238.0).abs() < 1e-6);
+
+        for port in [exhaust, transfer] {
+            let opening = port.opening_angle();
+            assert_eq!(port.area_at((opening - 1.0).to_radians()), 0.0);
+            assert_eq!(port.area_at(0.0), 0.0);
+            let area = port.area_at((opening + 5.0).to_radians());
+            assert!(area > 0.0);
+            assert!((port.area_at((360.0 - opening - 5.0).to_radians()) - area).abs() < 1e-12);
+            assert!(port.area_at((opening + 10.0).to_radians()) > area);
+        }
+        // fully uncovered at bottom-dead-center: 16 x 9 mm and two 9 x 5 mm ports
+        assert!((exhaust.area_at(PI) - 16e-3 * 9e-3).abs() < 1e-12);
+        assert!((transfer.area_at(PI) - 2.0 * 9e-3 * 5e-3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn skirt_ports_are_uncovered_around_top_dead_center() {
+        let gas = Gas::new("air.json");
+        let engine = Engine::new("engine_Ryobi.json", &gas).unwrap();
+        let cyl = &engine.cylinders()[0];
+        let port = Port::new("intake".to_string(), PortEdge::Skirt, 290.0, 20e-3, 8e-3, 0.7, cyl).unwrap();
+        assert!((port.closing_angle() - 70.0).abs() < 1e-6);
+        assert!(port.connecting().is_empty());
+
+        assert_eq!(port.area_at(PI), 0.0);
+        assert_eq!(port.area_at(289f64.to_radians()), 0.0);
+        assert!(port.area_at(300f64.to_radians()) > 0.0);
+        let edge_position = cyl.crankshaft().kinematics(290f64.to_radians()).0;
+        assert!((port.area_at(0.0) - 20e-3 * edge_position.min(8e-3)).abs() < 1e-12);
+        assert!((port.area_at(10f64.to_radians()) - port.area_at(-10f64.to_radians())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_invalid_ports() {
+        let gas = Gas::new("air.json");
+        let engine = Engine::new("engine_Ryobi.json", &gas).unwrap();
+        let cyl = &engine.cylinders()[0];
+        assert!(Port::new("p".to_string(), PortEdge::Crown, 100.0, 0.0, 8e-3, 0.7, cyl).is_err());
+        assert!(Port::new("p".to_string(), PortEdge::Crown, 100.0, 20e-3, 8e-3, 1.2, cyl).is_err());
+    }
+}
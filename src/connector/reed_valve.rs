@@ -0,0 +1,225 @@
+use crate::connector::orifice::Orifice;
+use crate::core::traits::{Conn, Connector, SaveData};
+use crate::{BasicProperties, FlowRatio};
+use ndarray::*;
+
+// largest fraction of the petal natural period integrated in a single sub-step
+const MAX_STEP_FRACTION: f64 = 0.05;
+
+/// Spring-mass model of the petal of a [`ReedValve`](struct.ReedValve.html):
+/// `m*x'' + 2*ζ*m*ω*x' + k*x = A*(P_up - P_down)`, where `ω = sqrt(k/m)`
+#[derive(Debug, Clone)]
+pub struct ReedPetal {
+    area: f64,      // [m²] - area on which the pressure difference acts
+    mass: f64,      // [kg] - effective mass
+    stiffness: f64, // [N/m]
+    damping_ratio: f64,
+    max_lift: f64, // [m] - limited by the petal stop
+}
+
+impl ReedPetal {
+    /// `area` in m², `mass` in kg, `stiffness` in N/m and `max_lift` in m
+    pub fn new(area: f64, mass: f64, stiffness: f64, damping_ratio: f64, max_lift: f64) -> Result<ReedPetal, String> {
+        if area <= 0.0 || mass <= 0.0 || stiffness <= 0.0 || max_lift <= 0.0 {
+            return Err("petal `area`, `mass`, `stiffness` and `max_lift` must be greater than zero".to_string());
+        }
+        if damping_ratio < 0.0 {
+            return Err(format!("`damping_ratio` must be positive: {}", damping_ratio));
+        }
+        Ok(ReedPetal {
+            area,
+            mass,
+            stiffness,
+            damping_ratio,
+            max_lift,
+        })
+    }
+
+    /// Returns the natural frequency, in rad/s
+    pub fn natural_freq(&self) -> f64 {
+        (self.stiffness / self.mass).sqrt()
+    }
+}
+
+/// Connector struct representing a reed valve, i.e. the intake of a crankcase-scavenged two-stroke engine.
+/// The petal lifts when the pressure of the first connected object is higher than the second one and the flow area
+/// is the curtain area `perimeter*lift`, limited by the port area. The flow follows the orifice equation.
+pub struct ReedValve {
+    orifice: Orifice,
+    port_area: f64, // [m²]
+    perimeter: f64, // [m]
+    petal: ReedPetal,
+    lift: f64,     // [m]
+    velocity: f64, // [m/s]
+}
+
+impl ReedValve {
+    /// `port_area` in m² and `perimeter`, the length of the petal edge, in m
+    pub fn new(
+        name: &str,
+        port_area: f64,
+        perimeter: f64,
+        petal: ReedPetal,
+        discharge_coeff: f64,
+        connecting: Vec<String>,
+    ) -> Result<ReedValve, String> {
+        if port_area <= 0.0 || perimeter <= 0.0 {
+            return Err("`port_area` and `perimeter` must be greater than zero".to_string());
+        }
+        // closed petal: no flow area
+        let orifice = Orifice::new(name, 0.0, discharge_coeff, connecting)?;
+        Ok(ReedValve {
+            orifice,
+            port_area,
+            perimeter,
+            petal,
+            lift: 0.0,
+            velocity: 0.0,
+        })
+    }
+
+    /// Returns the petal lift, in m
+    pub fn lift(&self) -> f64 {
+        self.lift
+    }
+
+    /// Returns the flow area, in m²
+    pub fn area(&self) -> f64 {
+        self.orifice.area()
+    }
+
+    /// Integrates the petal motion during `dt` under the pressure difference `delta_press`, in Pa
+    fn move_petal(&mut self, delta_press: f64, dt: f64) {
+        let p = &self.petal;
+        let omega = p.natural_freq();
+        let num_steps = (dt * omega / (2.0 * std::f64::consts::PI * MAX_STEP_FRACTION)).ceil().max(1.0);
+        let h = dt / num_steps;
+        let force = p.area * delta_press;
+        let damping = 2.0 * p.damping_ratio * p.mass * omega;
+        for _ in 0..num_steps as usize {
+            // semi-implicit Euler
+            let accel = (force - damping * self.velocity - p.stiffness * self.lift) / p.mass;
+            self.velocity += h * accel;
+            self.lift += h * self.velocity;
+            // seat and stop
+            if self.lift <= 0.0 {
+                self.lift = 0.0;
+                self.velocity = self.velocity.max(0.0);
+            } else if self.lift >= p.max_lift {
+                self.lift = p.max_lift;
+                self.velocity = self.velocity.min(0.0);
+            }
+        }
+    }
+}
+
+impl Connector for ReedValve {
+    fn name(&self) -> &str {
+        self.orifice.name()
+    }
+    fn connecting(&self) -> &Vec<String> {
+        self.orifice.connecting()
+    }
+    fn connect_to(&mut self, elem_name: &str) -> Result<(), String> {
+        self.orifice.connect_to(elem_name)
+    }
+    fn update_flow_ratio(&mut self, prop: Vec<BasicProperties>, step: f64) {
+        let delta_press = prop[0].pressure - prop[1].pressure;
+        self.move_petal(delta_press, step);
+        self.orifice.set_area((self.perimeter * self.lift).min(self.port_area));
+        self.orifice.update_flow_ratio(prop, step)
+    }
+    fn get_flow_ratio(&self, elem_name: &str) -> Result<&FlowRatio, String> {
+        self.orifice.get_flow_ratio(elem_name)
+    }
+    fn discharge_coeff(&self) -> Option<f64> {
+        self.orifice.discharge_coeff()
+    }
+    fn set_discharge_coeff(&mut self, discharge_coeff: f64) -> Result<(), String> {
+        self.orifice.set_discharge_coeff(discharge_coeff)
+    }
+}
+
+impl SaveData for ReedValve {
+    fn get_headers(&self) -> String {
+        "mass flow [kg/s]\tenthalpy flow [J/s]\tlift [mm]\tarea [cm²]".to_string()
+    }
+    fn num_storable_variables(&self) -> usize {
+        4
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        let flow = self.orifice.get_storable_data();
+        array![flow[0], flow[1], self.lift * 1e3, self.orifice.area() * 1e4]
+    }
+}
+
+impl Conn for ReedValve {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reaction::gas::Gas;
+
+    fn state<'a>(name: &'a str, pressure: f64, gas: &Gas) -> BasicProperties<'a> {
+        BasicProperties {
+            name,
+            pressure,
+            temperature: gas.T(),
+            cp: gas.cp(),
+            cv: gas.cv(),
+            cp_cv: gas.k(),
+            gas_const: gas.R(),
+            crank_angle: None,
+            mass_frac: gas.mass_frac(),
+        }
+    }
+
+    // reed valve with a 4 cm² petal of 800 N/m and 6 mm of maximum lift, between "plenum" and "crankcase"
+    fn reed_valve(damping_ratio: f64) -> ReedValve {
+        let petal = ReedPetal::new(4e-4, 1.5e-3, 800.0, damping_ratio, 6e-3).unwrap();
+        let connecting = vec!["plenum".to_string(), "crankcase".to_string()];
+        ReedValve::new("reed", 3e-4, 0.1, petal, 0.7, connecting).unwrap()
+    }
+
+    // keeps the pressure difference for 0.1 s
+    fn hold(valve: &mut ReedValve, plenum_press: f64, crankcase_press: f64, gas: &Gas) {
+        for _ in 0..100 {
+            valve.update_flow_ratio(vec![state("plenum", plenum_press, gas), state("crankcase", crankcase_press, gas)], 1e-3);
+        }
+    }
+
+    #[test]
+    fn petal_settles_at_static_deflection() {
+        let gas = Gas::new("air.json");
+        let mut valve = reed_valve(1.0);
+        hold(&mut valve, 1.01e5, 1e5, &gas);
+        let lift = 4e-4 * 1e3 / 800.0;
+        assert!((valve.lift() - lift).abs() < 1e-6 * lift);
+        assert!((valve.area() - 0.1 * lift).abs() < 1e-6 * 0.1 * lift);
+        assert!(valve.get_flow_ratio("crankcase").unwrap().mass_flow > 0.0);
+    }
+
+    #[test]
+    fn petal_is_limited_by_seat_and_stop() {
+        let gas = Gas::new("air.json");
+        let mut valve = reed_valve(0.2);
+        // the flow area is limited by the port area
+        hold(&mut valve, 1.5e5, 1e5, &gas);
+        assert_eq!(valve.lift(), 6e-3);
+        assert_eq!(valve.area(), 3e-4);
+
+        // reversed pressure difference: the petal closes and there is no flow back
+        hold(&mut valve, 1e5, 1.5e5, &gas);
+        assert_eq!(valve.lift(), 0.0);
+        assert_eq!(valve.area(), 0.0);
+        assert_eq!(valve.get_flow_ratio("crankcase").unwrap().mass_flow, 0.0);
+    }
+
+    #[test]
+    fn petal_follows_natural_frequency() {
+        let petal = ReedPetal::new(4e-4, 2e-3, 800.0, 0.0, 6e-3).unwrap();
+        assert!((petal.natural_freq() - 632.4555320336759).abs() < 1e-9);
+        assert!(ReedPetal::new(4e-4, 0.0, 800.0, 0.2, 6e-3).is_err());
+        assert!(ReedPetal::new(4e-4, 2e-3, 800.0, -0.2, 6e-3).is_err());
+    }
+}
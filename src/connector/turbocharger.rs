@@ -0,0 +1,664 @@
+#![allow(non_snake_case)]
+
+use crate::core::traits::{Conn, Connector, SaveData};
+use crate::{BasicProperties, FlowRatio};
+use ndarray::*;
+use serde::Deserialize;
+use std::f64::consts::PI;
+
+/// Line of constant corrected speed from a compressor or turbine map
+#[derive(Debug, Clone)]
+pub struct SpeedLine {
+    speed: f64,            // corrected speed [RPM]
+    press_ratio: Vec<f64>, // increasing
+    mass_flow: Vec<f64>,   // corrected mass flow [kg/s]
+    efficiency: Vec<f64>,  // isentropic efficiency
+}
+
+impl SpeedLine {
+    /// `speed` is the corrected speed in RPM and `mass_flow` the corrected mass flow in kg/s.
+    /// The points may be given in any order but their pressure ratios must be different.
+    pub fn new(
+        speed: f64,
+        press_ratio: &[f64],
+        mass_flow: &[f64],
+        efficiency: &[f64],
+    ) -> Result<SpeedLine, String> {
+        if speed <= 0.0 {
+            return Err(format!("speed line must have positive speed: {}", speed));
+        }
+        if press_ratio.len() < 2 {
+            return Err(format!("speed line {} RPM must have at least two points", speed));
+        }
+        if press_ratio.len() != mass_flow.len() || press_ratio.len() != efficiency.len() {
+            return Err(format!(
+                "speed line {} RPM: pressure ratio, mass flow and efficiency must have the same length",
+                speed
+            ));
+        }
+        if efficiency.iter().any(|e| *e <= 0.0 || *e > 1.0) {
+            return Err(format!(
+                "speed line {} RPM: efficiency must be between 0.0 and 1.0",
+                speed
+            ));
+        }
+        if mass_flow.iter().any(|m| *m < 0.0) || press_ratio.iter().any(|p| *p <= 0.0) {
+            return Err(format!(
+                "speed line {} RPM: mass flow and pressure ratio must be positive",
+                speed
+            ));
+        }
+        let mut order: Vec<usize> = (0..press_ratio.len()).collect();
+        order.sort_by(|a, b| press_ratio[*a].partial_cmp(&press_ratio[*b]).unwrap());
+        if order
+            .windows(2)
+            .any(|w| press_ratio[w[0]] == press_ratio[w[1]])
+        {
+            return Err(format!(
+                "speed line {} RPM: pressure ratios must be different",
+                speed
+            ));
+        }
+        Ok(SpeedLine {
+            speed,
+            press_ratio: order.iter().map(|i| press_ratio[*i]).collect(),
+            mass_flow: order.iter().map(|i| mass_flow[*i]).collect(),
+            efficiency: order.iter().map(|i| efficiency[*i]).collect(),
+        })
+    }
+
+    /// Returns the corrected mass flow and the efficiency at `press_ratio`. Outside the line,
+    /// the compressor flow is constant at the choke side and decreases linearly, down to zero,
+    /// at the surge side. The turbine flow is constant once choked and goes to zero at
+    /// pressure ratio of one as through a nozzle.
+    fn at(&self, press_ratio: f64, kind: MapKind) -> (f64, f64) {
+        let pr = &self.press_ratio;
+        let m = &self.mass_flow;
+        let eff = &self.efficiency;
+        let last = pr.len() - 1;
+        if press_ratio < pr[0] {
+            let flow = match kind {
+                MapKind::Compressor => m[0],
+                MapKind::Turbine => {
+                    if press_ratio <= 1.0 {
+                        0.0
+                    } else if pr[0] <= 1.0 {
+                        m[0]
+                    } else {
+                        m[0] * ((press_ratio - 1.0) / (pr[0] - 1.0)).sqrt()
+                    }
+                }
+            };
+            return (flow, eff[0]);
+        }
+        if press_ratio > pr[last] {
+            let flow = match kind {
+                MapKind::Compressor => {
+                    let slope = (m[last] - m[last - 1]) / (pr[last] - pr[last - 1]);
+                    (m[last] + slope * (press_ratio - pr[last])).clamp(0.0, m[last])
+                }
+                MapKind::Turbine => m[last],
+            };
+            return (flow, eff[last]);
+        }
+        let i = match pr.iter().position(|p| *p >= press_ratio) {
+            Some(i) if i > 0 => i,
+            _ => 1,
+        };
+        let frac = (press_ratio - pr[i - 1]) / (pr[i] - pr[i - 1]);
+        (
+            m[i - 1] + frac * (m[i] - m[i - 1]),
+            eff[i - 1] + frac * (eff[i] - eff[i - 1]),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MapKind {
+    Compressor,
+    Turbine,
+}
+
+#[derive(Deserialize, Debug)]
+struct JsonTurboMap {
+    /// [K]
+    reference_temperature: f64,
+    /// [bar]
+    reference_pressure: f64,
+    speed_lines: Vec<JsonSpeedLine>,
+}
+
+#[derive(Deserialize, Debug)]
+struct JsonSpeedLine {
+    /// corrected speed [RPM]
+    speed: f64,
+    pressure_ratio: Vec<f64>,
+    /// corrected mass flow [kg/s]
+    mass_flow: Vec<f64>,
+    efficiency: Vec<f64>,
+}
+
+/// Compressor or turbine map from the supplier, given as lines of constant corrected speed.
+/// The corrected quantities are `N/sqrt(T/T_ref)` and `m_dot*sqrt(T/T_ref)/(P/P_ref)`, with `T` and `P`
+/// the stagnation state at the inlet.
+///
+/// The map is interpolated linearly between speed lines. Beyond the compressor speed lines the
+/// closest line is scaled by the similarity laws (mass flow ~ N, pressure ratio - 1 ~ N²); beyond
+/// the turbine speed lines the closest line is used.
+///
+/// The `.json` file has the form:
+/// ```json
+/// {
+///     "reference_temperature": 298.0,
+///     "reference_pressure": 1.0,
+///     "speed_lines": [
+///         {"speed": 100000, "pressure_ratio": [1.3, 1.25, 1.1], "mass_flow": [0.010, 0.016, 0.020], "efficiency": [0.65, 0.70, 0.60]},
+///         {"speed": 140000, "pressure_ratio": [1.7, 1.6, 1.3], "mass_flow": [0.015, 0.024, 0.030], "efficiency": [0.66, 0.72, 0.60]}
+///     ]
+/// }
+/// ```
+/// where `reference_temperature` is in K, `reference_pressure` in bar, `speed` in RPM and `mass_flow` in kg/s.
+#[derive(Debug, Clone)]
+pub struct TurboMap {
+    ref_temp: f64,  // [K]
+    ref_press: f64, // [Pa]
+    lines: Vec<SpeedLine>,
+}
+
+impl TurboMap {
+    /// `ref_temp` in K and `ref_press` in Pa
+    pub fn new(ref_temp: f64, ref_press: f64, mut lines: Vec<SpeedLine>) -> Result<TurboMap, String> {
+        if ref_temp <= 0.0 || ref_press <= 0.0 {
+            return Err("reference temperature and pressure must be greater than zero".to_string());
+        }
+        if lines.is_empty() {
+            return Err("the map must have at least one speed line".to_string());
+        }
+        lines.sort_by(|a, b| a.speed.partial_cmp(&b.speed).unwrap());
+        if lines.windows(2).any(|w| w[0].speed == w[1].speed) {
+            return Err("speed lines must have different speeds".to_string());
+        }
+        Ok(TurboMap {
+            ref_temp,
+            ref_press,
+            lines,
+        })
+    }
+
+    /// Reads the map from a `.json` file
+    pub fn from_file(file_name: &str) -> Result<TurboMap, String> {
+        let json_file = match std::fs::read_to_string(file_name) {
+            Ok(f) => f,
+            Err(err) => return Err(format!("unable to read '{}': {}", file_name, err)),
+        };
+        let json_map: JsonTurboMap = match serde_json::from_str(&json_file) {
+            Ok(m) => m,
+            Err(err) => return Err(format!("unable to parse '{}': {}", file_name, err)),
+        };
+        let mut lines = Vec::with_capacity(json_map.speed_lines.len());
+        for line in json_map.speed_lines.iter() {
+            lines.push(SpeedLine::new(
+                line.speed,
+                &line.pressure_ratio,
+                &line.mass_flow,
+                &line.efficiency,
+            )?);
+        }
+        TurboMap::new(
+            json_map.reference_temperature,
+            json_map.reference_pressure * 1e5,
+            lines,
+        )
+    }
+
+    /// Returns the corrected speed, in RPM, from the `speed` in RPM and inlet temperature in K
+    pub fn corrected_speed(&self, speed: f64, temperature: f64) -> f64 {
+        speed / (temperature / self.ref_temp).sqrt()
+    }
+
+    /// Returns the mass flow, in kg/s, from the corrected mass flow and the inlet state
+    pub fn mass_flow(&self, corrected_mass_flow: f64, pressure: f64, temperature: f64) -> f64 {
+        corrected_mass_flow * (pressure / self.ref_press) / (temperature / self.ref_temp).sqrt()
+    }
+
+    /// Returns the corrected mass flow and the efficiency at `press_ratio` and corrected speed, in RPM,
+    /// of a compressor
+    pub fn compressor_point(&self, press_ratio: f64, corrected_speed: f64) -> (f64, f64) {
+        self.point(press_ratio, corrected_speed, MapKind::Compressor)
+    }
+
+    /// Returns the corrected mass flow and the efficiency at expansion `press_ratio` and corrected
+    /// speed, in RPM, of a turbine
+    pub fn turbine_point(&self, press_ratio: f64, corrected_speed: f64) -> (f64, f64) {
+        self.point(press_ratio, corrected_speed, MapKind::Turbine)
+    }
+
+    fn point(&self, press_ratio: f64, speed: f64, kind: MapKind) -> (f64, f64) {
+        let speed = speed.max(0.0);
+        let first = &self.lines[0];
+        let last = &self.lines[self.lines.len() - 1];
+        let (mass_flow, efficiency) = if speed <= first.speed || speed >= last.speed {
+            let line = if speed <= first.speed { first } else { last };
+            match kind {
+                MapKind::Compressor => {
+                    // similarity laws
+                    let ratio = (speed / line.speed).max(1e-3);
+                    let (m, eff) = line.at(1.0 + (press_ratio - 1.0) / (ratio * ratio), kind);
+                    (m * ratio, eff)
+                }
+                MapKind::Turbine => line.at(press_ratio, kind),
+            }
+        } else {
+            let i = self.lines.iter().position(|l| l.speed >= speed).unwrap();
+            let (low, high) = (&self.lines[i - 1], &self.lines[i]);
+            let frac = (speed - low.speed) / (high.speed - low.speed);
+            let (m_low, eff_low) = low.at(press_ratio, kind);
+            let (m_high, eff_high) = high.at(press_ratio, kind);
+            (
+                m_low + frac * (m_high - m_low),
+                eff_low + frac * (eff_high - eff_low),
+            )
+        };
+        (mass_flow.max(0.0), efficiency.clamp(0.05, 1.0))
+    }
+}
+
+/// Connector struct representing a compressor driven by a [`Shaft`](struct.Shaft.html).
+/// The flow goes from the first to the second connected object, as given by the map.
+pub struct Compressor {
+    name: String,
+    map: TurboMap,
+    connecting: Vec<String>,
+    flow_ratio: Vec<FlowRatio>,
+    speed: f64, // [RPM]
+    press_ratio: f64,
+    corrected_speed: f64, // [RPM]
+    efficiency: f64,
+    outlet_temp: f64, // [K]
+    power: f64,       // [W] - absorbed from the shaft
+}
+
+impl Compressor {
+    pub fn new(name: &str, map: TurboMap, connecting: Vec<String>) -> Result<Compressor, String> {
+        if connecting.len() != 2 {
+            return Err(format!(
+                "Connector `Compressor` must connect only two elements, connecting: {}",
+                connecting.len()
+            ));
+        }
+        Ok(Compressor {
+            name: name.to_string(),
+            map,
+            connecting,
+            flow_ratio: vec![FlowRatio::new(), FlowRatio::new()],
+            speed: 0.0,
+            press_ratio: 1.0,
+            corrected_speed: 0.0,
+            efficiency: 0.0,
+            outlet_temp: 0.0,
+            power: 0.0,
+        })
+    }
+
+    /// Returns the power, in W, absorbed from the shaft
+    pub fn power(&self) -> f64 {
+        self.power
+    }
+}
+
+impl Connector for Compressor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn connecting(&self) -> &Vec<String> {
+        &self.connecting
+    }
+    fn connect_to(&mut self, elem_name: &str) -> Result<(), String> {
+        self.connecting.push(elem_name.to_string());
+        self.flow_ratio.push(FlowRatio::new());
+        if self.connecting.len() != 2 {
+            return Err("Wrong the number of connections. Compressor should connect only two elements".to_string());
+        }
+        Ok(())
+    }
+    fn update_flow_ratio(&mut self, prop: Vec<BasicProperties>, _step: f64) {
+        let inlet = &prop[0];
+        let P_in = inlet.pressure;
+        let T_in = inlet.temperature;
+        let k = inlet.cp_cv;
+        let cp = k * inlet.gas_const / (k - 1.0);
+
+        self.press_ratio = prop[1].pressure / P_in;
+        self.corrected_speed = self.map.corrected_speed(self.speed, T_in);
+        let (corr_flow, efficiency) = self
+            .map
+            .compressor_point(self.press_ratio, self.corrected_speed);
+        let m_dot = self.map.mass_flow(corr_flow, P_in, T_in);
+        self.efficiency = efficiency;
+
+        // when the pressure drops across the compressor it behaves as a restriction
+        let isentropic_rise = T_in * (self.press_ratio.powf((k - 1.0) / k) - 1.0);
+        self.outlet_temp = T_in + isentropic_rise.max(0.0) / efficiency;
+        self.power = m_dot * cp * (self.outlet_temp - T_in);
+
+        self.flow_ratio[0].mass_flow = -m_dot;
+        self.flow_ratio[0].enthalpy_flow = -m_dot * cp * T_in;
+        self.flow_ratio[0].species_flow = -m_dot * &inlet.mass_frac;
+        self.flow_ratio[1].mass_flow = m_dot;
+        self.flow_ratio[1].enthalpy_flow = m_dot * cp * self.outlet_temp;
+        self.flow_ratio[1].species_flow = m_dot * &inlet.mass_frac;
+    }
+    fn get_flow_ratio(&self, elem_name: &str) -> Result<&FlowRatio, String> {
+        match self.connecting.iter().position(|name| name == elem_name) {
+            Some(i) => Ok(&self.flow_ratio[i]),
+            None => Err(format!(
+                "object '{}' was not found in '{}'",
+                elem_name,
+                self.name()
+            )),
+        }
+    }
+    fn shaft_power(&self) -> Option<f64> {
+        Some(-self.power)
+    }
+    fn set_shaft_speed(&mut self, speed: f64) -> Result<(), String> {
+        self.speed = speed;
+        Ok(())
+    }
+}
+
+impl SaveData for Compressor {
+    fn get_headers(&self) -> String {
+        "mass flow [kg/s]\tpressure ratio [-]\tcorrected speed [RPM]\tefficiency [-]\toutlet temperature [K]\tpower [W]".to_string()
+    }
+    fn num_storable_variables(&self) -> usize {
+        6
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        array![
+            self.flow_ratio[1].mass_flow,
+            self.press_ratio,
+            self.corrected_speed,
+            self.efficiency,
+            self.outlet_temp,
+            self.power
+        ]
+    }
+}
+
+impl Conn for Compressor {}
+
+/// Connector struct representing a turbine driving a [`Shaft`](struct.Shaft.html).
+/// The flow goes from the first to the second connected object; there is no reverse flow.
+pub struct Turbine {
+    name: String,
+    map: TurboMap,
+    connecting: Vec<String>,
+    flow_ratio: Vec<FlowRatio>,
+    speed: f64, // [RPM]
+    press_ratio: f64,
+    corrected_speed: f64, // [RPM]
+    efficiency: f64,
+    outlet_temp: f64, // [K]
+    power: f64,       // [W] - delivered to the shaft
+}
+
+impl Turbine {
+    pub fn new(name: &str, map: TurboMap, connecting: Vec<String>) -> Result<Turbine, String> {
+        if connecting.len() != 2 {
+            return Err(format!(
+                "Connector `Turbine` must connect only two elements, connecting: {}",
+                connecting.len()
+            ));
+        }
+        Ok(Turbine {
+            name: name.to_string(),
+            map,
+            connecting,
+            flow_ratio: vec![FlowRatio::new(), FlowRatio::new()],
+            speed: 0.0,
+            press_ratio: 1.0,
+            corrected_speed: 0.0,
+            efficiency: 0.0,
+            outlet_temp: 0.0,
+            power: 0.0,
+        })
+    }
+
+    /// Returns the power, in W, delivered to the shaft
+    pub fn power(&self) -> f64 {
+        self.power
+    }
+}
+
+impl Connector for Turbine {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn connecting(&self) -> &Vec<String> {
+        &self.connecting
+    }
+    fn connect_to(&mut self, elem_name: &str) -> Result<(), String> {
+        self.connecting.push(elem_name.to_string());
+        self.flow_ratio.push(FlowRatio::new());
+        if self.connecting.len() != 2 {
+            return Err("Wrong the number of connections. Turbine should connect only two elements".to_string());
+        }
+        Ok(())
+    }
+    fn update_flow_ratio(&mut self, prop: Vec<BasicProperties>, _step: f64) {
+        let inlet = &prop[0];
+        let P_in = inlet.pressure;
+        let T_in = inlet.temperature;
+        let k = inlet.cp_cv;
+        let cp = k * inlet.gas_const / (k - 1.0);
+
+        self.press_ratio = P_in / prop[1].pressure;
+        self.corrected_speed = self.map.corrected_speed(self.speed, T_in);
+        let (corr_flow, efficiency) = self
+            .map
+            .turbine_point(self.press_ratio, self.corrected_speed);
+        let m_dot = self.map.mass_flow(corr_flow, P_in, T_in);
+        self.efficiency = efficiency;
+
+        let isentropic_drop = T_in * (1.0 - self.press_ratio.powf((1.0 - k) / k));
+        self.outlet_temp = T_in - efficiency * isentropic_drop.max(0.0);
+        self.power = m_dot * cp * (T_in - self.outlet_temp);
+
+        self.flow_ratio[0].mass_flow = -m_dot;
+        self.flow_ratio[0].enthalpy_flow = -m_dot * cp * T_in;
+        self.flow_ratio[0].species_flow = -m_dot * &inlet.mass_frac;
+        self.flow_ratio[1].mass_flow = m_dot;
+        self.flow_ratio[1].enthalpy_flow = m_dot * cp * self.outlet_temp;
+        self.flow_ratio[1].species_flow = m_dot * &inlet.mass_frac;
+    }
+    fn get_flow_ratio(&self, elem_name: &str) -> Result<&FlowRatio, String> {
+        match self.connecting.iter().position(|name| name == elem_name) {
+            Some(i) => Ok(&self.flow_ratio[i]),
+            None => Err(format!(
+                "object '{}' was not found in '{}'",
+                elem_name,
+                self.name()
+            )),
+        }
+    }
+    fn shaft_power(&self) -> Option<f64> {
+        Some(self.power)
+    }
+    fn set_shaft_speed(&mut self, speed: f64) -> Result<(), String> {
+        self.speed = speed;
+        Ok(())
+    }
+}
+
+impl SaveData for Turbine {
+    fn get_headers(&self) -> String {
+        "mass flow [kg/s]\tpressure ratio [-]\tcorrected speed [RPM]\tefficiency [-]\toutlet temperature [K]\tpower [W]".to_string()
+    }
+    fn num_storable_variables(&self) -> usize {
+        6
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        array![
+            self.flow_ratio[1].mass_flow,
+            self.press_ratio,
+            self.corrected_speed,
+            self.efficiency,
+            self.outlet_temp,
+            self.power
+        ]
+    }
+}
+
+impl Conn for Turbine {}
+
+/// Shaft coupling turbines and compressors. Its speed is integrated from the power balance
+/// `I*ω*dω/dt = P_turbine - P_compressor - c*ω²`, where `c*ω` is the bearing friction torque.
+pub struct Shaft {
+    name: String,
+    inertia: f64,  // [kg*m²]
+    friction: f64, // [N*m*s/rad]
+    speed: f64,    // [rad/s]
+    connecting: Vec<String>,
+    turbine_power: f64,    // [W]
+    compressor_power: f64, // [W]
+}
+
+impl Shaft {
+    /// `inertia` in kg*m², `friction` is the friction torque per angular speed, in N*m*s/rad,
+    /// and `speed` is the initial speed in RPM. `connecting` are the names of the compressors and turbines.
+    pub fn new(
+        name: &str,
+        inertia: f64,
+        friction: f64,
+        speed: f64,
+        connecting: Vec<String>,
+    ) -> Result<Shaft, String> {
+        if inertia <= 0.0 {
+            return Err(format!("`inertia` must be greater than zero: {}", inertia));
+        }
+        if friction < 0.0 {
+            return Err(format!("`friction` must be positive: {}", friction));
+        }
+        if speed < 0.0 {
+            return Err(format!("`speed` must be positive: {}", speed));
+        }
+        if connecting.is_empty() {
+            return Err("`Shaft` must be connected to at least one element".to_string());
+        }
+        Ok(Shaft {
+            name: name.to_string(),
+            inertia,
+            friction,
+            speed: speed * PI / 30.0,
+            connecting,
+            turbine_power: 0.0,
+            compressor_power: 0.0,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn connecting(&self) -> &Vec<String> {
+        &self.connecting
+    }
+
+    /// Returns the speed in RPM
+    pub fn speed(&self) -> f64 {
+        self.speed * 30.0 / PI
+    }
+
+    /// Returns the friction power in W
+    pub fn friction_power(&self) -> f64 {
+        self.friction * self.speed * self.speed
+    }
+
+    /// Advance the speed by `dt` with the `powers`, in W, delivered (positive) or absorbed (negative)
+    /// by the connected elements
+    pub fn advance(&mut self, powers: &[f64], dt: f64) {
+        self.turbine_power = powers.iter().filter(|p| **p > 0.0).sum();
+        self.compressor_power = -powers.iter().filter(|p| **p < 0.0).sum::<f64>();
+        let net_power = self.turbine_power - self.compressor_power - self.friction_power();
+        // avoiding the singularity at zero speed
+        let omega = self.speed.max(100.0);
+        self.speed = (self.speed + dt * net_power / (self.inertia * omega)).max(0.0);
+    }
+}
+
+impl SaveData for Shaft {
+    fn get_headers(&self) -> String {
+        "speed [RPM]\tturbine power [W]\tcompressor power [W]\tfriction power [W]".to_string()
+    }
+    fn num_storable_variables(&self) -> usize {
+        4
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        array![
+            self.speed(),
+            self.turbine_power,
+            self.compressor_power,
+            self.friction_power()
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> TurboMap {
+        let lines = vec![
+            SpeedLine::new(140000.0, &[1.7, 1.6, 1.3], &[0.015, 0.024, 0.030], &[0.66, 0.72, 0.60]).unwrap(),
+            SpeedLine::new(100000.0, &[1.3, 1.25, 1.1], &[0.010, 0.016, 0.020], &[0.65, 0.70, 0.60]).unwrap(),
+        ];
+        TurboMap::new(298.0, 1e5, lines).unwrap()
+    }
+
+    fn assert_point(point: (f64, f64), expected: (f64, f64)) {
+        assert!((point.0 - expected.0).abs() < 1e-12, "{:?} != {:?}", point, expected);
+        assert!((point.1 - expected.1).abs() < 1e-12, "{:?} != {:?}", point, expected);
+    }
+
+    #[test]
+    fn interpolates_along_speed_line() {
+        let map = map();
+        assert_point(map.compressor_point(1.25, 100000.0), (0.016, 0.70));
+        assert_point(map.compressor_point(1.175, 100000.0), (0.018, 0.65));
+        assert_point(map.compressor_point(1.45, 140000.0), (0.027, 0.66));
+    }
+
+    #[test]
+    fn interpolates_between_speed_lines() {
+        let map = map();
+        assert_point(map.compressor_point(1.3, 120000.0), (0.020, 0.625));
+        assert_point(map.turbine_point(1.3, 130000.0), (0.025, 0.6125));
+    }
+
+    #[test]
+    fn extrapolates_beyond_speed_lines() {
+        let map = map();
+        // compressor similarity laws at twice the speed of the highest line
+        assert_point(map.compressor_point(1.0 + 0.6 * 4.0, 280000.0), (0.048, 0.72));
+        // turbine flow goes to zero at a pressure ratio of one
+        assert_point(map.turbine_point(1.0, 100000.0), (0.0, 0.60));
+        assert_point(map.turbine_point(1.05, 100000.0), (0.020 * 0.5f64.sqrt(), 0.60));
+    }
+
+    #[test]
+    fn corrects_to_reference_state() {
+        let map = map();
+        assert!((map.corrected_speed(100000.0, 4.0 * 298.0) - 50000.0).abs() < 1e-9);
+        assert!((map.mass_flow(0.02, 2e5, 4.0 * 298.0) - 0.02).abs() < 1e-15);
+    }
+
+    #[test]
+    fn rejects_invalid_maps() {
+        assert!(SpeedLine::new(100000.0, &[1.1, 1.1], &[0.01, 0.02], &[0.6, 0.7]).is_err());
+        assert!(SpeedLine::new(100000.0, &[1.1, 1.2], &[0.01, 0.02], &[0.6, 1.1]).is_err());
+        let line = SpeedLine::new(100000.0, &[1.1, 1.2], &[0.02, 0.01], &[0.6, 0.7]).unwrap();
+        assert!(TurboMap::new(298.0, 1e5, vec![line.clone(), line]).is_err());
+        assert!(TurboMap::new(298.0, 1e5, Vec::new()).is_err());
+    }
+}
@@ -20,10 +20,18 @@ pub struct Valve {
     area: f64,
     max_lift: f64,
     discharge_coeff: Rc<dyn Fn(f64, f64, f64, &str) -> f64>,
+    cd_multiplier: f64,
     valve_lift: ValveLift,
+    lift_profile: Option<LiftProfile>,
+    base_opening: f64,  // [CA-deg] - events without actuation
+    base_closing: f64,  // [CA-deg]
+    base_max_lift: f64, // [m]
+    actuation: ValveActuation,
+    cycle_angle: f64, // [CA-deg] - 720 for four-stroke and 360 for two-stroke engines
     throat_area: f64,
     flow_ratio: Vec<(String, FlowRatio)>,
-    backflow_mass: f64,
+    backflow: BackflowGas,
+    intake: bool, // the gas leaving the cylinder flows against the normal direction
     connecting: Vec<String>,
 }
 
@@ -38,8 +46,9 @@ impl Valve {
     ) -> Result<Valve, &'static str> {
         let delta_angle = closing_angle - opening_angle;
         let max_lift_diam_ratio = max_lift / diameter;
+        let cycle_angle = cylinder.cycle_angle().to_degrees();
         let time_opened = if delta_angle < 0.0 {
-            delta_angle + 720.0
+            delta_angle + cycle_angle
         } else {
             delta_angle
         };
@@ -58,15 +67,43 @@ impl Valve {
             diameter,
             area: 0.25 * std::f64::consts::PI * diameter * diameter,
             discharge_coeff,
+            cd_multiplier: 1.0,
             max_lift,
             valve_lift,
+            lift_profile: None,
+            base_opening: opening_angle,
+            base_closing: closing_angle,
+            base_max_lift: max_lift,
+            actuation: ValveActuation::default(),
+            cycle_angle,
             throat_area: 0.0,
             flow_ratio,
-            backflow_mass: 0.0,
+            backflow: BackflowGas::new(),
+            intake: false,
             connecting,
         })
     }
 
+    /// Creates a valve whose lift follows a measured `profile`. The opening and closing angles and
+    /// the maximum lift are taken from the profile. `diameter` must be in meters.
+    pub fn with_lift_profile(
+        name: String,
+        diameter: f64,
+        profile: &LiftProfile,
+        cylinder: &Cylinder,
+    ) -> Result<Valve, &'static str> {
+        let mut valve = Valve::new(
+            name,
+            profile.opening_angle(),
+            profile.closing_angle(),
+            diameter,
+            profile.max_lift(),
+            cylinder,
+        )?;
+        valve.lift_profile = Some(profile.clone());
+        Ok(valve)
+    }
+
     fn default_discharge_coeff(
         lift_diam: f64,
         area: f64,
@@ -106,6 +143,62 @@ impl Valve {
         }
     }
 
+    /// Sets the valve as an intake valve: the gas it pushes back out of the cylinder is tracked and
+    /// returns to the cylinder when the flow reverses
+    pub(crate) fn set_intake(&mut self) {
+        self.intake = true;
+    }
+
+    /// Returns the opening angle in [CA-deg], including the actuation
+    pub fn opening_angle(&self) -> f64 {
+        self.opening_angle
+    }
+
+    /// Returns the closing angle in [CA-deg], including the actuation
+    pub fn closing_angle(&self) -> f64 {
+        self.closing_angle
+    }
+
+    /// Returns the variable valve actuation
+    pub fn actuation(&self) -> ValveActuation {
+        self.actuation
+    }
+
+    /// Set the variable valve actuation. It applies to the valve events given at construction: the event
+    /// is stretched by `duration_scale` around its centre, shifted by `phase` and its lift is multiplied by `lift_scale`
+    pub fn set_actuation(&mut self, actuation: ValveActuation) -> Result<(), String> {
+        if actuation == self.actuation {
+            return Ok(());
+        }
+        let base_duration = (self.base_closing - self.base_opening).rem_euclid(self.cycle_angle);
+        let duration = base_duration * actuation.duration_scale;
+        if duration >= self.cycle_angle {
+            return Err(format!(
+                "valve `{}`: the valve must be closed during part of the cycle, duration: {:.1} CA-deg",
+                self.name, duration
+            ));
+        }
+        let center = self.base_opening + 0.5 * base_duration + actuation.phase;
+        self.opening_angle = (center - 0.5 * duration).rem_euclid(self.cycle_angle);
+        self.closing_angle = (center + 0.5 * duration).rem_euclid(self.cycle_angle);
+        self.delta_angle = self.closing_angle - self.opening_angle;
+        self.max_lift = self.base_max_lift * actuation.lift_scale;
+        self.valve_lift = ValveLift::new(self.max_lift / self.diameter, duration);
+        self.actuation = actuation;
+        Ok(())
+    }
+
+    // `angle` must be relative angle in crank-angle degrees: `theta - opening_angle`
+    fn lift_diam(&self, angle: f64) -> f64 {
+        match &self.lift_profile {
+            Some(profile) => {
+                let act = &self.actuation;
+                profile.lift_at(angle / act.duration_scale) * act.lift_scale / self.diameter
+            }
+            None => self.valve_lift.calc_lift(angle),
+        }
+    }
+
     fn is_open(&self, chank_angle: f64) -> bool {
         // checking if valve is open
         if self.delta_angle < 0.0 {
@@ -135,16 +228,16 @@ impl Valve {
         let mut result: Vec<String> = Vec::new();
         result.push(format!("crank-angle [deg]\tarea[mm²]\tlift[mm]\n"));
 
-        for angle in 0..720 {
+        for angle in 0..(self.cycle_angle as usize) {
             let area: f64;
             let lift: f64;
             if self.is_open(angle as f64) {
                 let delta = angle as f64 - self.opening_angle;
-                let angle_cam = if delta >= 0.0 { delta } else { delta + 720.0 };
+                let angle_cam = if delta >= 0.0 { delta } else { delta + self.cycle_angle };
                 if angle == 340 {
                     let _a = 0;
                 }
-                let lift_diam = self.valve_lift.calc_lift(angle_cam);
+                let lift_diam = self.lift_diam(angle_cam);
                 area = self.calc_throat_area(lift_diam);
                 lift = lift_diam * self.diameter;
             } else {
@@ -206,17 +299,17 @@ impl Connector for Valve {
         let thoat_area: f64;
         let lift_diam: f64; // lift/diam
                             // checking if valve is open
-        if self.is_open(crank_angle) {
+        if !self.actuation.is_deactivated() && self.is_open(crank_angle) {
             let delta = crank_angle - self.opening_angle;
-            let angle = if delta >= 0.0 { delta } else { delta + 720.0 };
-            lift_diam = self.valve_lift.calc_lift(angle);
+            let angle = if delta >= 0.0 { delta } else { delta + self.cycle_angle };
+            lift_diam = self.lift_diam(angle);
             thoat_area = self.calc_throat_area(lift_diam);
             self.throat_area = thoat_area;
         } else {
             self.set_flow_to_zero();
             thoat_area = 0.0;
             self.throat_area = thoat_area;
-            self.backflow_mass = 0.0;
+            // the backflow stays in the intake port while the valve is closed and returns at the next opening
             return;
         }
 
@@ -235,19 +328,15 @@ impl Connector for Valve {
             return;
         }
 
-        // check if had backflow
+        // check if had backflow: the gas returning to the cylinder through an intake valve is the one previously pushed out of it
         let T_up: f64;
         let k: f64;
         let R: f64;
-        if self.backflow_mass > 0.0 && prop[i_down].name == self.flow_ratio[0].0 {
-            // T_up = self.backflow_gas.T();
-            // k = self.backflow_gas.k();
-            // R = self.backflow_gas.R();
-            let up_frac = 0.5;
-            let down_frac = 1.0 - up_frac;
-            T_up = up_frac * prop[i_up].temperature + down_frac * prop[i_down].temperature;
-            k = up_frac * prop[i_up].cp_cv + down_frac * prop[i_down].cp_cv;
-            R = up_frac * prop[i_up].gas_const + down_frac * prop[i_down].gas_const;
+        let is_returning = self.backflow.mass > 0.0 && prop[i_down].name == self.flow_ratio[0].0;
+        if is_returning {
+            T_up = self.backflow.temperature;
+            k = self.backflow.cp_cv;
+            R = self.backflow.gas_const;
         } else {
             T_up = prop[i_up].temperature;
             k = prop[i_up].cp_cv;
@@ -256,9 +345,6 @@ impl Connector for Valve {
 
         let P_up = prop[i_up].pressure;
         let P_down = prop[i_down].pressure;
-        // let T_up = prop[i_up].temperature;
-        // let k = prop[i_up].cp_cv;
-        // let R = prop[i_up].gas_const;
 
         let kp = k + 1.0;
         let km = k - 1.0;
@@ -272,7 +358,7 @@ impl Connector for Valve {
             "backward"
         };
 
-        let cd = (self.discharge_coeff)(lift_diam, self.area, thoat_area, direction);
+        let cd = self.cd_multiplier * (self.discharge_coeff)(lift_diam, self.area, thoat_area, direction);
 
         // estimating mass flow
         if P_du > (2.0 / kp).powf(k / km) {
@@ -302,8 +388,27 @@ impl Connector for Valve {
             }
         };
         let ii = i; // store the position to use in the downstream
+        let upstream_enthalpy = prop[i_up].cp_cv * prop[i_up].gas_const / (prop[i_up].cp_cv - 1.0)
+            * prop[i_up].temperature; // [J/kg]
+        let (enthalpy, mass_frac) = if is_returning {
+            // the backflow may be exhausted during the step, then the gas upstream follows
+            let returning_frac = (self.backflow.mass / (m_dot * dt)).min(1.0);
+            self.backflow.mass -= returning_frac * m_dot * dt;
+            (
+                returning_frac * k * R / km * T_up + (1.0 - returning_frac) * upstream_enthalpy,
+                returning_frac * &self.backflow.mass_frac
+                    + (1.0 - returning_frac) * &prop[i_up].mass_frac,
+            )
+        } else {
+            // only the gas leaving through intake valves flows back; through exhaust valves it is the normal flow
+            if self.intake && prop[i_up].name == self.flow_ratio[0].0 {
+                self.backflow.add(m_dot * dt, &prop[i_up]);
+            }
+            (upstream_enthalpy, prop[i_up].mass_frac.clone())
+        };
         self.flow_ratio[i].1.mass_flow = -m_dot;
-        self.flow_ratio[i].1.enthalpy_flow = -m_dot * k * R / km * T_up;
+        self.flow_ratio[i].1.enthalpy_flow = -m_dot * enthalpy;
+        self.flow_ratio[i].1.species_flow = -m_dot * mass_frac;
 
         // updating `flow_ratio` for downstream objects
         let i = match self
@@ -324,12 +429,8 @@ impl Connector for Valve {
         };
         self.flow_ratio[i].1.mass_flow = -self.flow_ratio[ii].1.mass_flow;
         self.flow_ratio[i].1.enthalpy_flow = -self.flow_ratio[ii].1.enthalpy_flow;
+        self.flow_ratio[i].1.species_flow = -&self.flow_ratio[ii].1.species_flow;
 
-        // Add back-flow if necessary: index 0 is always cylinder
-        self.backflow_mass += -self.flow_ratio[0].1.mass_flow * dt;
-        if self.backflow_mass < 0.0 {
-            self.backflow_mass = 0.0;
-        }
     }
 
     fn get_flow_ratio<'a>(&'a self, elem_name: &str) -> Result<&'a FlowRatio, String> {
@@ -346,15 +447,40 @@ impl Connector for Valve {
             )),
         }
     }
+    /// For valves, the discharge coefficient is a multiplier of the discharge coefficient curve
+    fn discharge_coeff(&self) -> Option<f64> {
+        Some(self.cd_multiplier)
+    }
+    fn set_discharge_coeff(&mut self, discharge_coeff: f64) -> Result<(), String> {
+        if discharge_coeff <= 0.0 {
+            return Err(format!(
+                "the discharge coefficient multiplier must be greater than zero: {}",
+                discharge_coeff
+            ));
+        }
+        self.cd_multiplier = discharge_coeff;
+        Ok(())
+    }
+    /// Replaces the default discharge coefficient curve, i.e. by flow-bench measurements
+    fn set_discharge_coeff_map(&mut self, map: &DischargeCoeffMap) -> Result<(), String> {
+        let map = map.clone();
+        self.discharge_coeff = Rc::new(move |lift_diam, area, throat_area, direction| {
+            map.discharge_coeff(lift_diam, area, throat_area, direction)
+        });
+        Ok(())
+    }
+    fn set_valve_actuation(&mut self, actuation: ValveActuation) -> Result<(), String> {
+        self.set_actuation(actuation)
+    }
 }
 
 impl SaveData for Valve {
     fn get_headers(&self) -> String {
-        "crank-angle [deg]\tmass flow [kg/s]\tenthalpy flow [J/s]\tthroat area [cm²]\tbackflow [mg]"
+        "crank-angle [deg]\tmass flow [kg/s]\tenthalpy flow [J/s]\tthroat area [cm²]\tbackflow [mg]\tbackflow temperature [K]"
             .to_string()
     }
     fn num_storable_variables(&self) -> usize {
-        5
+        6
     }
     fn get_storable_data(&self) -> Array1<f64> {
         array![
@@ -362,13 +488,131 @@ impl SaveData for Valve {
             self.flow_ratio[0].1.mass_flow,
             self.flow_ratio[0].1.enthalpy_flow,
             self.throat_area * 1e4,
-            self.backflow_mass * 1e6,
+            self.backflow.mass * 1e6,
+            self.backflow.temperature,
         ]
     }
 }
 
 impl Conn for Valve {}
 
+/// Cylinder gas pushed back through the valve. It is assumed to stay next to the valve, without mixing,
+/// and to return to the cylinder with its own state before any gas from the other side, even if the
+/// valve closes in between.
+#[derive(Debug, Clone)]
+struct BackflowGas {
+    mass: f64,            // [kg]
+    temperature: f64,     // [K]
+    cp_cv: f64,
+    gas_const: f64,       // [J/(kg.K)]
+    mass_frac: Array1<f64>,
+}
+
+impl BackflowGas {
+    fn new() -> BackflowGas {
+        BackflowGas {
+            mass: 0.0,
+            temperature: 0.0,
+            cp_cv: 0.0,
+            gas_const: 0.0,
+            mass_frac: Array1::zeros(0),
+        }
+    }
+
+    /// Adds `mass`, in kg, of cylinder gas with state `prop`. The mixture is adiabatic at constant pressure
+    fn add(&mut self, mass: f64, prop: &BasicProperties) {
+        if self.mass <= 0.0 || self.mass_frac.len() != prop.mass_frac.len() {
+            self.mass = mass;
+            self.temperature = prop.temperature;
+            self.cp_cv = prop.cp_cv;
+            self.gas_const = prop.gas_const;
+            self.mass_frac = prop.mass_frac.clone();
+            return;
+        }
+        let cp_old = self.cp_cv * self.gas_const / (self.cp_cv - 1.0);
+        let cp_add = prop.cp_cv * prop.gas_const / (prop.cp_cv - 1.0);
+        let total_mass = self.mass + mass;
+        let cp = (self.mass * cp_old + mass * cp_add) / total_mass;
+        self.temperature =
+            (self.mass * cp_old * self.temperature + mass * cp_add * prop.temperature) / (total_mass * cp);
+        self.gas_const = (self.mass * self.gas_const + mass * prop.gas_const) / total_mass;
+        self.cp_cv = cp / (cp - self.gas_const);
+        self.mass_frac = (self.mass * &self.mass_frac + mass * &prop.mass_frac) / total_mass;
+        self.mass = total_mass;
+    }
+}
+
+/// Variable valve actuation: cam phasing and, optionally, variable lift and duration, i.e. two-step or
+/// continuous variable valve lift. It is applied on top of the valve events of the `.json` file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValveActuation {
+    phase: f64,          // [CA-deg]
+    lift_scale: f64,     // [-]
+    duration_scale: f64, // [-]
+}
+
+impl ValveActuation {
+    /// `phase` in [CA-deg] shifts the valve events, positive values retard them. `lift_scale` multiplies the
+    /// maximum lift and `duration_scale` stretches the event around its centre
+    pub fn new(phase: f64, lift_scale: f64, duration_scale: f64) -> Result<ValveActuation, String> {
+        if lift_scale <= 0.0 || duration_scale <= 0.0 {
+            return Err(format!(
+                "`lift_scale` and `duration_scale` must be greater than zero, got {} and {}",
+                lift_scale, duration_scale
+            ));
+        }
+        Ok(ValveActuation {
+            phase,
+            lift_scale,
+            duration_scale,
+        })
+    }
+
+    /// Cam phasing only: shifts the valve events by `phase`, in [CA-deg], keeping lift and duration
+    pub fn phase_shift(phase: f64) -> ValveActuation {
+        ValveActuation {
+            phase,
+            lift_scale: 1.0,
+            duration_scale: 1.0,
+        }
+    }
+
+    /// Valve held shut, i.e. by a lost-motion mechanism of cylinder deactivation
+    pub fn deactivated() -> ValveActuation {
+        ValveActuation {
+            phase: 0.0,
+            lift_scale: 0.0,
+            duration_scale: 1.0,
+        }
+    }
+
+    /// Returns `true` if the valve is held shut
+    pub fn is_deactivated(&self) -> bool {
+        self.lift_scale == 0.0
+    }
+
+    /// Returns the cam phase in [CA-deg]
+    pub fn phase(&self) -> f64 {
+        self.phase
+    }
+
+    /// Returns the multiplier of the maximum lift
+    pub fn lift_scale(&self) -> f64 {
+        self.lift_scale
+    }
+
+    /// Returns the multiplier of the event duration
+    pub fn duration_scale(&self) -> f64 {
+        self.duration_scale
+    }
+}
+
+impl Default for ValveActuation {
+    fn default() -> ValveActuation {
+        ValveActuation::phase_shift(0.0)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct ValveLift {
     time_opened: f64, //in crank angle degree
@@ -411,3 +655,428 @@ impl ValveLift {
         }
     }
 }
+
+/// Measured valve lift profile, linearly interpolated between the points of the table
+#[derive(Debug, Clone)]
+pub struct LiftProfile {
+    angle: Vec<f64>, // [CA-deg] - relative to the opening angle
+    lift: Vec<f64>,  // [m]
+    opening_angle: f64, // [CA-deg]
+    closing_angle: f64, // [CA-deg]
+    max_lift: f64,      // [m]
+}
+
+impl LiftProfile {
+    /// Creates a lift profile from a table of crank-angle, `angle` in [CA-deg], vs. cam lift, `lift` in [m].
+    /// The angles must be increasing and may exceed `cycle_angle`, in [CA-deg], when the valve opens through the
+    /// end of the cycle: 720 CA-deg for four-stroke and 360 CA-deg for two-stroke engines.
+    ///
+    /// The valve lift is the cam lift minus the valve `lash`, in [m]. Therefore, the valve opens and closes when the
+    /// cam lift crosses the lash and the opening and closing ramps of the cam below it are ignored.
+    /// The valve lift is multiplied by `lift_scale` and the duration is stretched by `duration_scale`
+    /// around the angle of maximum lift.
+    pub fn new(
+        angle: &[f64],
+        lift: &[f64],
+        lash: f64,
+        duration_scale: f64,
+        lift_scale: f64,
+        cycle_angle: f64,
+    ) -> Result<LiftProfile, String> {
+        if angle.len() != lift.len() || angle.len() < 2 {
+            return Err(format!(
+                "lift profile: `angle` and `lift` must have the same length and at least 2 points, got {} and {}",
+                angle.len(),
+                lift.len()
+            ));
+        }
+        if angle.windows(2).any(|w| w[1] <= w[0]) {
+            return Err("lift profile: `angle` must be strictly increasing".to_string());
+        }
+        if lash < 0.0 || duration_scale <= 0.0 || lift_scale <= 0.0 {
+            return Err(format!(
+                "lift profile: `lash` must be positive and the scales greater than zero, got {}, {} and {}",
+                lash, duration_scale, lift_scale
+            ));
+        }
+
+        // valve lift without clipping: negative values are within the lash
+        let valve_lift: Vec<f64> = lift.iter().map(|l| (l - lash) * lift_scale).collect();
+        let i_first = match valve_lift.iter().position(|l| *l > 0.0) {
+            Some(i) => i,
+            None => return Err("lift profile: the lift never exceeds the lash".to_string()),
+        };
+        let i_last = valve_lift.iter().rposition(|l| *l > 0.0).unwrap();
+
+        // stretching the duration around the angle of maximum lift
+        let i_peak = (i_first..=i_last)
+            .max_by(|a, b| valve_lift[*a].partial_cmp(&valve_lift[*b]).unwrap())
+            .unwrap();
+        let peak = angle[i_peak];
+        let angle: Vec<f64> = angle
+            .iter()
+            .map(|a| peak + (a - peak) * duration_scale)
+            .collect();
+
+        // opening and closing: where the valve lift crosses zero
+        let crossing = |i: usize, j: usize| -> f64 {
+            angle[i] - valve_lift[i] * (angle[j] - angle[i]) / (valve_lift[j] - valve_lift[i])
+        };
+        let opening = if i_first == 0 { angle[0] } else { crossing(i_first - 1, i_first) };
+        let closing = if i_last == angle.len() - 1 {
+            angle[i_last]
+        } else {
+            crossing(i_last, i_last + 1)
+        };
+        if closing - opening >= cycle_angle {
+            return Err("lift profile: the valve must be closed during part of the cycle".to_string());
+        }
+
+        let mut table_angle = vec![0.0];
+        let mut table_lift = vec![0.0];
+        for i in i_first..=i_last {
+            table_angle.push(angle[i] - opening);
+            table_lift.push(valve_lift[i].max(0.0));
+        }
+        table_angle.push(closing - opening);
+        table_lift.push(0.0);
+
+        Ok(LiftProfile {
+            angle: table_angle,
+            lift: table_lift,
+            opening_angle: opening.rem_euclid(cycle_angle),
+            closing_angle: closing.rem_euclid(cycle_angle),
+            max_lift: valve_lift[i_peak],
+        })
+    }
+
+    /// Returns the opening angle in [CA-deg]
+    pub fn opening_angle(&self) -> f64 {
+        self.opening_angle
+    }
+
+    /// Returns the closing angle in [CA-deg]
+    pub fn closing_angle(&self) -> f64 {
+        self.closing_angle
+    }
+
+    /// Returns the maximum valve lift in [m]
+    pub fn max_lift(&self) -> f64 {
+        self.max_lift
+    }
+
+    /// Returns the valve lift in [m]. `angle` must be relative angle in crank-angle degrees: `theta - opening_angle`
+    pub fn lift_at(&self, angle: f64) -> f64 {
+        let last = self.angle.len() - 1;
+        if angle <= self.angle[0] || angle >= self.angle[last] {
+            return 0.0;
+        }
+        let i = self.angle.iter().position(|a| *a > angle).unwrap();
+        let frac = (angle - self.angle[i - 1]) / (self.angle[i] - self.angle[i - 1]);
+        self.lift[i - 1] + frac * (self.lift[i] - self.lift[i - 1])
+    }
+}
+
+/// Reference area of the discharge coefficients of a [`DischargeCoeffMap`](struct.DischargeCoeffMap.html)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceArea {
+    /// Curtain area, `π·D·L`
+    Curtain,
+    /// Inner seat area, `π·D²/4`. Flow coefficients of flow benches are usually referred to it
+    InnerSeat,
+    /// Port area, `π·d²/4`, with the port diameter `d` in [m]
+    Port(f64),
+    /// Minimum geometric flow area, the one used by `Valve` to calculate the mass flow
+    Throat,
+}
+
+/// Tabulated discharge coefficients vs. lift over diameter, `L/D`, for both flow directions.
+/// Values are linearly interpolated and held constant beyond the ends of the table.
+#[derive(Debug, Clone)]
+pub struct DischargeCoeffMap {
+    lift_diam: Vec<f64>,
+    forward: Vec<f64>,  // into the cylinder
+    backward: Vec<f64>, // out of the cylinder
+    reference: ReferenceArea,
+}
+
+impl DischargeCoeffMap {
+    /// Creates a map from increasing `lift_diam` (`L/D`) and the discharge coefficients of the `forward`
+    /// (into the cylinder) and `backward` (out of the cylinder) flow, referred to the area `reference`
+    pub fn new(
+        lift_diam: Vec<f64>,
+        forward: Vec<f64>,
+        backward: Vec<f64>,
+        reference: ReferenceArea,
+    ) -> Result<DischargeCoeffMap, String> {
+        if lift_diam.is_empty() || forward.len() != lift_diam.len() || backward.len() != lift_diam.len() {
+            return Err(format!(
+                "discharge coefficient map: `lift_diam`, `forward` and `backward` must have the same length, got {}, {} and {}",
+                lift_diam.len(),
+                forward.len(),
+                backward.len()
+            ));
+        }
+        if lift_diam.windows(2).any(|w| w[1] <= w[0]) {
+            return Err("discharge coefficient map: `lift_diam` must be strictly increasing".to_string());
+        }
+        if forward.iter().chain(backward.iter()).any(|cd| *cd < 0.0) {
+            return Err("discharge coefficient map: coefficients must be positive".to_string());
+        }
+        if let ReferenceArea::Port(diam) = reference {
+            if diam <= 0.0 {
+                return Err(format!("discharge coefficient map: invalid port diameter {}", diam));
+            }
+        }
+        Ok(DischargeCoeffMap {
+            lift_diam,
+            forward,
+            backward,
+            reference,
+        })
+    }
+
+    /// Returns the discharge coefficient referred to the throat area. `area` is the inner seat area
+    /// and `direction` is either "forward" or "backward"
+    pub fn discharge_coeff(&self, lift_diam: f64, area: f64, throat_area: f64, direction: &str) -> f64 {
+        if throat_area < 1e-10 {
+            return 0.0;
+        }
+        let table = if direction == "forward" {
+            &self.forward
+        } else {
+            &self.backward
+        };
+        let last = self.lift_diam.len() - 1;
+        let cd = if lift_diam <= self.lift_diam[0] {
+            table[0]
+        } else if lift_diam >= self.lift_diam[last] {
+            table[last]
+        } else {
+            let i = self.lift_diam.iter().position(|ld| *ld > lift_diam).unwrap();
+            let frac = (lift_diam - self.lift_diam[i - 1]) / (self.lift_diam[i] - self.lift_diam[i - 1]);
+            table[i - 1] + frac * (table[i] - table[i - 1])
+        };
+        let ref_area = match self.reference {
+            ReferenceArea::Curtain => {
+                // `area` = π·D²/4  =>  π·D·L = 4·area·L/D
+                4.0 * area * lift_diam
+            }
+            ReferenceArea::InnerSeat => area,
+            ReferenceArea::Port(diam) => 0.25 * std::f64::consts::PI * diam * diam,
+            ReferenceArea::Throat => throat_area,
+        };
+        cd * ref_area / throat_area
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::engine::Engine;
+    use crate::reaction::gas::Gas;
+
+    const ANGLE: [f64; 5] = [0.0, 10.0, 20.0, 30.0, 40.0];
+    const LIFT: [f64; 5] = [0.0, 2e-3, 4e-3, 2e-3, 0.0];
+
+    #[test]
+    fn lift_profile_removes_lash() {
+        let profile = LiftProfile::new(&ANGLE, &LIFT, 1e-3, 1.0, 1.0, 720.0).unwrap();
+        assert!((profile.opening_angle() - 5.0).abs() < 1e-12);
+        assert!((profile.closing_angle() - 35.0).abs() < 1e-12);
+        assert!((profile.max_lift() - 3e-3).abs() < 1e-15);
+        assert_eq!(profile.lift_at(0.0), 0.0);
+        assert!((profile.lift_at(5.0) - 1e-3).abs() < 1e-15);
+        assert!((profile.lift_at(15.0) - 3e-3).abs() < 1e-15);
+        assert!((profile.lift_at(27.5) - 0.5e-3).abs() < 1e-15);
+        assert_eq!(profile.lift_at(30.0), 0.0);
+    }
+
+    #[test]
+    fn lift_profile_scales_around_peak() {
+        let profile = LiftProfile::new(&ANGLE, &LIFT, 1e-3, 2.0, 0.5, 720.0).unwrap();
+        // the opening moves before the start of the cycle
+        assert!((profile.opening_angle() - 710.0).abs() < 1e-12);
+        assert!((profile.closing_angle() - 50.0).abs() < 1e-12);
+        assert!((profile.max_lift() - 1.5e-3).abs() < 1e-15);
+        assert!((profile.lift_at(30.0) - 1.5e-3).abs() < 1e-15);
+    }
+
+    #[test]
+    fn lift_profile_of_two_stroke_cycle() {
+        // exhaust opening before bottom-dead-center and closing after it, through the end of the cycle
+        let angle = [100.0, 150.0, 180.0, 210.0, 260.0];
+        let lift = [0.0, 4e-3, 6e-3, 4e-3, 0.0];
+        let profile = LiftProfile::new(&angle, &lift, 0.0, 1.0, 1.0, 360.0).unwrap();
+        assert!((profile.opening_angle() - 100.0).abs() < 1e-12);
+        assert!((profile.closing_angle() - 260.0).abs() < 1e-12);
+        let shifted: Vec<f64> = angle.iter().map(|a| a + 200.0).collect();
+        let profile = LiftProfile::new(&shifted, &lift, 0.0, 1.0, 1.0, 360.0).unwrap();
+        assert!((profile.opening_angle() - 300.0).abs() < 1e-12);
+        assert!((profile.closing_angle() - 100.0).abs() < 1e-12);
+        assert!((profile.lift_at(80.0) - 6e-3).abs() < 1e-15);
+        // a valve open during the whole cycle
+        let long: Vec<f64> = angle.iter().map(|a| 180.0 + (a - 180.0) * 2.5).collect();
+        assert!(LiftProfile::new(&long, &lift, 0.0, 1.0, 1.0, 360.0).is_err());
+        assert!(LiftProfile::new(&long, &lift, 0.0, 1.0, 1.0, 720.0).is_ok());
+    }
+
+    #[test]
+    fn lift_profile_rejects_invalid_tables() {
+        assert!(LiftProfile::new(&ANGLE, &LIFT[..4], 0.0, 1.0, 1.0, 720.0).is_err());
+        assert!(LiftProfile::new(&[0.0, 10.0, 10.0], &[0.0, 1e-3, 0.0], 0.0, 1.0, 1.0, 720.0).is_err());
+        assert!(LiftProfile::new(&ANGLE, &LIFT, 5e-3, 1.0, 1.0, 720.0).is_err());
+        assert!(LiftProfile::new(&ANGLE, &LIFT, 0.0, 0.0, 1.0, 720.0).is_err());
+    }
+
+    fn cd_map(reference: ReferenceArea) -> DischargeCoeffMap {
+        DischargeCoeffMap::new(vec![0.0, 0.1, 0.3], vec![0.2, 0.5, 0.7], vec![0.1, 0.4, 0.8], reference).unwrap()
+    }
+
+    #[test]
+    fn discharge_coeff_map_interpolates() {
+        let map = cd_map(ReferenceArea::Throat);
+        assert!((map.discharge_coeff(0.1, 1.0, 0.5, "forward") - 0.5).abs() < 1e-12);
+        assert!((map.discharge_coeff(0.2, 1.0, 0.5, "forward") - 0.6).abs() < 1e-12);
+        assert!((map.discharge_coeff(0.05, 1.0, 0.5, "backward") - 0.25).abs() < 1e-12);
+        // held constant beyond the ends of the table
+        assert!((map.discharge_coeff(0.5, 1.0, 0.5, "forward") - 0.7).abs() < 1e-12);
+        assert!((map.discharge_coeff(-0.1, 1.0, 0.5, "backward") - 0.1).abs() < 1e-12);
+        // closed valve
+        assert_eq!(map.discharge_coeff(0.1, 1.0, 0.0, "forward"), 0.0);
+    }
+
+    #[test]
+    fn discharge_coeff_map_converts_reference_area() {
+        let (area, throat_area) = (1e-3, 4e-4); // [m²]
+        let inner_seat = cd_map(ReferenceArea::InnerSeat).discharge_coeff(0.1, area, throat_area, "forward");
+        assert!((inner_seat - 0.5 * area / throat_area).abs() < 1e-12);
+        let curtain = cd_map(ReferenceArea::Curtain).discharge_coeff(0.1, area, throat_area, "forward");
+        assert!((curtain - 0.5 * 4.0 * area * 0.1 / throat_area).abs() < 1e-12);
+        let port = cd_map(ReferenceArea::Port(0.02)).discharge_coeff(0.1, area, throat_area, "forward");
+        assert!((port - 0.5 * 0.25 * std::f64::consts::PI * 0.02 * 0.02 / throat_area).abs() < 1e-12);
+    }
+
+    #[test]
+    fn discharge_coeff_map_rejects_invalid_tables() {
+        let reference = ReferenceArea::Throat;
+        assert!(DischargeCoeffMap::new(vec![0.0, 0.1], vec![0.5], vec![0.5, 0.6], reference.clone()).is_err());
+        assert!(DischargeCoeffMap::new(vec![0.1, 0.0], vec![0.5, 0.6], vec![0.5, 0.6], reference.clone()).is_err());
+        assert!(DischargeCoeffMap::new(vec![0.0, 0.1], vec![0.5, -0.6], vec![0.5, 0.6], reference).is_err());
+        assert!(DischargeCoeffMap::new(vec![0.0, 0.1], vec![0.5, 0.6], vec![0.5, 0.6], ReferenceArea::Port(0.0)).is_err());
+    }
+
+    fn state<'a>(name: &'a str, pressure: f64, temperature: f64, crank_angle: Option<f64>, gas: &Gas) -> BasicProperties<'a> {
+        BasicProperties {
+            name,
+            pressure,
+            temperature,
+            cp: gas.cp(),
+            cv: gas.cv(),
+            cp_cv: gas.k(),
+            gas_const: gas.R(),
+            crank_angle,
+            mass_frac: gas.mass_frac(),
+        }
+    }
+
+    // intake valve of `engine.json`, open from 340 to 570 CA-deg, connected to an intake port
+    fn intake_valve(gas: &Gas) -> Valve {
+        let engine = Engine::new("engine.json", gas).unwrap();
+        let mut valve = engine.valves().iter().find(|v| v.name() == "valve_int").unwrap().clone();
+        valve.connect_to("port").unwrap();
+        valve
+    }
+
+    #[test]
+    fn actuation_wraps_events_across_the_cycle_end() {
+        let gas = Gas::new("air.json");
+        let mut valve = intake_valve(&gas);
+        // retarding the 340-570 CA-deg event moves its closing into the next cycle
+        valve.set_actuation(ValveActuation::phase_shift(200.0)).unwrap();
+        assert!((valve.opening_angle() - 540.0).abs() < 1e-9);
+        assert!((valve.closing_angle() - 50.0).abs() < 1e-9);
+        assert!(valve.is_open(700.0) && valve.is_open(30.0));
+        assert!(!valve.is_open(100.0) && !valve.is_open(530.0));
+        // advancing it moves the opening into the previous cycle
+        valve.set_actuation(ValveActuation::phase_shift(-360.0)).unwrap();
+        assert!((valve.opening_angle() - 700.0).abs() < 1e-9);
+        assert!((valve.closing_angle() - 210.0).abs() < 1e-9);
+        assert!(valve.is_open(710.0) && valve.is_open(100.0));
+        assert!(!valve.is_open(300.0));
+        // the valve must be closed during part of the cycle
+        assert!(valve.set_actuation(ValveActuation::new(0.0, 1.0, 720.0 / 230.0).unwrap()).is_err());
+    }
+
+    #[test]
+    fn actuation_of_two_stroke_cycle() {
+        let gas = Gas::new("air.json");
+        let engine = Engine::new("engine_Ryobi.json", &gas).unwrap();
+        let cylinder = &engine.cylinders()[0];
+        let mut valve = Valve::new("valve_exh".to_string(), 100.0, 260.0, 0.02, 0.006, cylinder).unwrap();
+        assert_eq!(valve.cycle_angle, 360.0);
+        valve.set_actuation(ValveActuation::phase_shift(120.0)).unwrap();
+        assert!((valve.opening_angle() - 220.0).abs() < 1e-9);
+        assert!((valve.closing_angle() - 20.0).abs() < 1e-9);
+        assert!(valve.is_open(350.0) && valve.is_open(10.0));
+        assert!(!valve.is_open(100.0));
+        // a duration that fits a four-stroke cycle does not fit a two-stroke one
+        assert!(valve.set_actuation(ValveActuation::new(0.0, 1.0, 2.5).unwrap()).is_err());
+    }
+
+    #[test]
+    fn backflow_is_kept_while_the_valve_is_closed() {
+        let gas = Gas::new("air.json");
+        let mut valve = intake_valve(&gas);
+        let dt = 1e-5;
+
+        // cylinder gas pushed back into the port
+        valve.update_flow_ratio(vec![state("cyl_1", 2e5, 900.0, Some(400f64.to_radians()), &gas), state("port", 1e5, 300.0, None, &gas)], dt);
+        let pushed_back = -valve.get_flow_ratio("cyl_1").unwrap().mass_flow * dt;
+        assert!(pushed_back > 0.0);
+        assert!((valve.backflow.mass - pushed_back).abs() < 1e-15);
+        assert!((valve.backflow.temperature - 900.0).abs() < 1e-12);
+
+        // a second outflow mixes with the one already in the port
+        valve.update_flow_ratio(vec![state("cyl_1", 2e5, 700.0, Some(401f64.to_radians()), &gas), state("port", 1e5, 300.0, None, &gas)], dt);
+        let total_pushed = pushed_back - valve.get_flow_ratio("cyl_1").unwrap().mass_flow * dt;
+        assert!((valve.backflow.mass - total_pushed).abs() < 1e-15);
+        assert!(valve.backflow.temperature > 700.0 && valve.backflow.temperature < 900.0);
+
+        // the valve closes without returning the gas
+        let backflow = valve.backflow.clone();
+        valve.update_flow_ratio(vec![state("cyl_1", 2e5, 900.0, Some(600f64.to_radians()), &gas), state("port", 1e5, 300.0, None, &gas)], dt);
+        assert_eq!(valve.get_flow_ratio("cyl_1").unwrap().mass_flow, 0.0);
+        assert_eq!(valve.backflow.mass, backflow.mass);
+
+        // it returns at the next opening, hotter than the gas in the port
+        valve.update_flow_ratio(vec![state("cyl_1", 1e5, 900.0, Some(400f64.to_radians()), &gas), state("port", 1.001e5, 300.0, None, &gas)], 1e-9);
+        let flow = valve.get_flow_ratio("cyl_1").unwrap();
+        assert!(flow.mass_flow > 0.0);
+        let enthalpy = flow.enthalpy_flow / flow.mass_flow;
+        let backflow_enthalpy = backflow.cp_cv * backflow.gas_const / (backflow.cp_cv - 1.0) * backflow.temperature;
+        assert!((enthalpy - backflow_enthalpy).abs() < 1e-9 * backflow_enthalpy);
+        assert!(valve.backflow.mass < backflow.mass);
+    }
+
+    #[test]
+    fn backflow_returns_before_the_port_gas() {
+        let gas = Gas::new("air.json");
+        let mut valve = intake_valve(&gas);
+        let dt = 1e-5;
+        valve.update_flow_ratio(vec![state("cyl_1", 2e5, 900.0, Some(400f64.to_radians()), &gas), state("port", 1e5, 300.0, None, &gas)], dt);
+        let pushed_back = valve.backflow.mass;
+
+        // a long step returns all the backflow and then some port gas
+        let dt = 1e-3;
+        valve.update_flow_ratio(vec![state("cyl_1", 1e5, 900.0, Some(400f64.to_radians()), &gas), state("port", 2e5, 300.0, None, &gas)], dt);
+        let flow = valve.get_flow_ratio("cyl_1").unwrap();
+        assert!(flow.mass_flow * dt > pushed_back);
+        assert!(valve.backflow.mass.abs() < 1e-15);
+        let cp = gas.cp();
+        let returning_frac = pushed_back / (flow.mass_flow * dt);
+        let enthalpy = flow.enthalpy_flow / flow.mass_flow;
+        let expected = returning_frac * cp * 900.0 + (1.0 - returning_frac) * cp * 300.0;
+        assert!((enthalpy - expected).abs() < 1e-9 * expected);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::base::constants::MAX_ARRAY_LEN;
+use crate::connector::turbocharger::Shaft;
 use crate::engine::engine::Engine;
 use crate::{BasicProperties, FlowRatio};
 use crate::{ObjectInfo, ObjectType};
@@ -7,7 +8,7 @@ use std::f64::consts::PI;
 use std::time::Instant;
 
 // Core Traits
-// use crate::core::traits::Connector;
+use crate::core::traits::Connector;
 // use crate::core::traits::OneDim;
 use crate::core::traits::SaveData;
 use crate::core::traits::ZeroDim;
@@ -26,10 +27,15 @@ pub struct System {
     zero_dim: Vec<Box<dyn ZeroD>>,
     one_dim: Vec<Box<dyn OneD>>,
     connector: Vec<Box<dyn Conn>>,
+    shafts: Vec<Shaft>,
     engine_connectors_index: Vec<Vec<(ObjectType, usize)>>,
     zero_dim_connectors_index: Vec<Vec<(ObjectType, usize)>>,
     one_dim_connectors_index: Vec<Vec<(ObjectType, usize)>>,
     connector_objects_index: Vec<Vec<(ObjectType, usize)>>,
+    shaft_connectors_index: Vec<Vec<usize>>,
+    crank_driven_index: Vec<usize>,
+    crank_angle_index: Vec<(usize, usize)>, // (zero_dim, cylinder)
+    valve_index: Vec<(usize, usize)>,       // (engine valve, connector)
     cycle_start: usize,
     iterations_counter: usize,
     time: Array2<f64>,
@@ -42,6 +48,7 @@ impl System {
         zero_dim: Vec<Box<dyn ZeroD>>,
         one_dim: Vec<Box<dyn OneD>>,
         connector: Vec<Box<dyn Conn>>,
+        shafts: Vec<Shaft>,
     ) -> Result<System, String> {
         let mut system = System {
             objs_info,
@@ -49,10 +56,15 @@ impl System {
             zero_dim,
             one_dim,
             connector,
+            shafts,
             engine_connectors_index: Vec::new(),
             zero_dim_connectors_index: Vec::new(),
             one_dim_connectors_index: Vec::new(),
             connector_objects_index: Vec::new(),
+            shaft_connectors_index: Vec::new(),
+            crank_driven_index: Vec::new(),
+            crank_angle_index: Vec::new(),
+            valve_index: Vec::new(),
             cycle_start: 0,
             iterations_counter: 0,
             time: Array::from_elem((MAX_ARRAY_LEN, 1), 0.),
@@ -65,18 +77,48 @@ impl System {
 
     /// Advance all objects in System by `dt`. The state of the objects is stored.
     pub fn advance<'a>(&'a mut self, dt: f64) -> &'a mut Self {
+        // Setting the crank-angle of ZeroDim objects driven by the crankshaft
+        if let Some(eng) = &self.engine {
+            for (i, j) in self.crank_angle_index.iter() {
+                let angle = eng.cylinders()[*j].crank_angle();
+                self.zero_dim[*i].set_crank_angle(angle, eng.sec_to_rad());
+            }
+            // valve actuation changed through `Engine`
+            for (i, j) in self.valve_index.iter() {
+                if let Err(err) = self.connector[*j].set_valve_actuation(eng.valves()[*i].actuation()) {
+                    println!("Error at 'System::advance()': \n {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+
         // Advancing ZeroDim objects
         self.zero_dim.iter_mut().for_each(|zd| zd.advance(dt));
 
         // Advancing OneDim objects
 
-        // Advacing Engine
+        // Advacing Engine: the blow-by leaks to the crankcase under the piston, if any
         if let Some(eng) = &mut self.engine {
+            for (i, j) in self.crank_angle_index.iter() {
+                eng.set_crankcase_pressure_of(*j, self.zero_dim[*i].get_state().pressure);
+            }
             eng.advance(dt);
         }
 
         // check if masses less than zero
 
+        // Setting the speed of connectors driven by the crankshaft
+        if let Some(eng) = &self.engine {
+            let engine_speed = eng.sec_to_rad() * 30.0 / PI; // [RPM]
+            for i in self.crank_driven_index.iter() {
+                let ratio = self.connector[*i].drive_ratio().unwrap_or(0.0);
+                if let Err(err) = self.connector[*i].set_shaft_speed(ratio * engine_speed) {
+                    println!("Error at 'System::advance()': \n {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+
         // Update connectors `flow_ratio`: require `basic_properties` of DimElements
         for (connector, obj_index_list) in self
             .connector
@@ -87,15 +129,37 @@ impl System {
                 Vec::with_capacity(connector.connecting().len());
             for (obj_type, i) in obj_index_list.iter() {
                 match obj_type {
-                    ObjectType::ZeroDim => basic_properties.push(self.zero_dim[*i].get_state()),
+                    ObjectType::ZeroDim => {
+                        basic_properties.push(self.zero_dim[*i].get_state_to(connector.name()))
+                    }
                     ObjectType::Cylinder => basic_properties
-                        .push(self.engine.as_ref().unwrap().cylinders()[*i].get_state()),
+                        .push(self.engine.as_ref().unwrap().cylinders()[*i].get_state_to(connector.name())),
                     _ => panic!("Error at `System::advance()`\n Object of unknown type!"),
                 }
             }
             connector.update_flow_ratio(basic_properties, dt);
         }
 
+        // Advancing shafts: require the power of their connectors
+        let connector = &mut self.connector;
+        for (shaft, conn_index_list) in self
+            .shafts
+            .iter_mut()
+            .zip(self.shaft_connectors_index.iter())
+        {
+            let powers: Vec<f64> = conn_index_list
+                .iter()
+                .map(|i| connector[*i].shaft_power().unwrap_or(0.0))
+                .collect();
+            shaft.advance(&powers, dt);
+            for i in conn_index_list.iter() {
+                if let Err(err) = connector[*i].set_shaft_speed(shaft.speed()) {
+                    println!("Error at 'System::advance()': \n {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+
         // Update ZeroDim objects: require `flow_ratio` and `name `from connectors
         for (zero_dim, conn_index_list) in self
             .zero_dim
@@ -166,6 +230,10 @@ impl System {
                             .add_data(eng.cylinders()[data.index].get_storable_data());
                     }
                 }
+                ObjectType::Shaft => {
+                    data.stored_data
+                        .add_data(self.shafts[data.index].get_storable_data());
+                }
             }
         }
 
@@ -220,8 +288,8 @@ impl System {
             match &self.engine {
                 Some(e) => {
                     total_angle += step * e.sec_to_rad();
-                    if total_angle > 4.0 * PI {
-                        total_angle -= 4.0 * PI;
+                    if total_angle > e.cycle_angle() {
+                        total_angle -= e.cycle_angle();
                         cycle += 1;
                         if cycle > max_cycles {
                             break;
@@ -247,24 +315,39 @@ impl System {
         if let Some(engine) = &mut self.engine {
             let mut pressure: Vec<ArrayView1<f64>> = Vec::new();
             let mut volume: Vec<ArrayView1<f64>> = Vec::new();
+            let mut angle: Vec<ArrayView1<f64>> = Vec::new();
             let mut data: Vec<Array2<f64>> = Vec::new();
             let range = (self.cycle_start, self.iterations_counter);
             let p_index: usize = 1;
             let v_index: usize = 3;
+            let a_index: usize = 0;
             for info in &self.objs_info {
                 match info.obj_type {
                     ObjectType::Cylinder => {
-                        data.push(info.stored_data.get_data(range, vec![p_index, v_index]));
+                        data.push(info.stored_data.get_data(range, vec![p_index, v_index, a_index]));
                     }
                     _ => {}
                 }
             }
             data.iter_mut().for_each(|d| {
                 pressure.push(d.column(0));
-                volume.push(d.column(1))
+                volume.push(d.column(1));
+                angle.push(d.column(2))
             });
 
-            engine.calc_operational_param(pressure, volume);
+            // power drawn by the connectors driven by the crankshaft, averaged over the last cycle
+            let mut parasitic_power = 0.0;
+            for i in self.crank_driven_index.iter() {
+                let name = self.connector[*i].name();
+                let info = self.objs_info.iter().find(|info| info.name == name).unwrap();
+                if let Some(col) = info.stored_data.column_index("power [W]") {
+                    if let Some(mean) = info.stored_data.column(range, col).mean() {
+                        parasitic_power += mean;
+                    }
+                }
+            }
+
+            engine.calc_operational_param(pressure, volume, angle, parasitic_power);
 
             println!("Engine performance:{}", engine.operat_param());
         }
@@ -296,6 +379,82 @@ impl System {
         }
     }
 
+    /// Returns a view of the stored variable `var_name` from object `obj_name`.
+    /// If `Engine` has been added, only the data stored in the last cycle is returned.
+    /// `var_name` must be one of the names returned by `stored_variables()`, i.e. "pressure [bar]".
+    pub fn trace<'a>(&'a self, obj_name: &str, var_name: &str) -> Result<ArrayView1<'a, f64>, String> {
+        self.trace_in_range(obj_name, var_name, self.last_cycle_range())
+    }
+
+    /// Returns a view of the stored variable `var_name` from object `obj_name` limited by the index `range`.
+    /// The variable "time [s]" is accepted for every object.
+    pub fn trace_in_range<'a>(
+        &'a self,
+        obj_name: &str,
+        var_name: &str,
+        range: (usize, usize),
+    ) -> Result<ArrayView1<'a, f64>, String> {
+        if range.0 > range.1 || range.1 > self.iterations_counter {
+            return Err(format!(
+                "range ({}, {}) is out of the stored data: (0, {})",
+                range.0, range.1, self.iterations_counter
+            ));
+        }
+        let obj_info = match self.objs_info.iter().find(|info| info.name == obj_name) {
+            Some(info) => info,
+            None => return Err(format!("object '{}' was not found", obj_name)),
+        };
+        if var_name == "time [s]" {
+            return Ok(self.time.slice(s![range.0..range.1, 0]));
+        }
+        match obj_info.stored_data.column_index(var_name) {
+            Some(col) => Ok(obj_info.stored_data.column(range, col)),
+            None => Err(format!(
+                "variable '{}' is not stored by '{}'. Stored variables are: {:?}",
+                var_name,
+                obj_name,
+                obj_info.stored_data.columns()
+            )),
+        }
+    }
+
+    /// Returns the names of the variables stored by object `obj_name`
+    pub fn stored_variables<'a>(&'a self, obj_name: &str) -> Result<Vec<&'a str>, String> {
+        match self.objs_info.iter().find(|info| info.name == obj_name) {
+            Some(info) => Ok(info.stored_data.columns()),
+            None => Err(format!("object '{}' was not found", obj_name)),
+        }
+    }
+
+    /// Returns the first and last index of the data stored in the last cycle.
+    /// If no `Engine` has been added, the range covers all stored data.
+    pub fn last_cycle_range(&self) -> (usize, usize) {
+        (self.cycle_start, self.iterations_counter)
+    }
+
+    /// Returns the discharge coefficient of connector `conn_name`. For valves, it is the multiplier of
+    /// the discharge coefficient curve.
+    pub fn discharge_coeff_of(&self, conn_name: &str) -> Result<f64, String> {
+        let i = self.connector_index(conn_name)?;
+        match self.connector[i].discharge_coeff() {
+            Some(cd) => Ok(cd),
+            None => Err(format!("`{}` has no discharge coefficient", conn_name)),
+        }
+    }
+
+    /// Set the discharge coefficient of connector `conn_name`. For valves, it is the multiplier of
+    /// the discharge coefficient curve.
+    pub fn set_discharge_coeff_of(&mut self, conn_name: &str, discharge_coeff: f64) -> Result<(), String> {
+        let i = self.connector_index(conn_name)?;
+        self.connector[i].set_discharge_coeff(discharge_coeff)
+    }
+
+    /// Set the opening, between 0 (closed) and 1 (fully open), of connector `conn_name`, i.e. an EGR valve
+    pub fn set_opening_of(&mut self, conn_name: &str, opening: f64) -> Result<(), String> {
+        let i = self.connector_index(conn_name)?;
+        self.connector[i].set_opening(opening)
+    }
+
     pub fn engine<'a>(&'a self) -> Option<&'a Engine> {
         match &self.engine {
             Some(eng) => Some(eng),
@@ -362,12 +521,87 @@ impl System {
             self.engine_connectors_index = engine_connectors_index;
         }
 
+        // finding which connectors are coupled to the shafts and setting their initial speed
+        let mut shaft_connectors_index: Vec<Vec<usize>> = Vec::new();
+        for shaft in self.shafts.iter() {
+            let mut indexes: Vec<usize> = Vec::new();
+            for conn_name in shaft.connecting().iter() {
+                let i = self.connector_index(conn_name)?;
+                if self.connector[i].shaft_power().is_none() || self.connector[i].drive_ratio().is_some() {
+                    return Err(format!("'{}' cannot be coupled to shaft '{}'", conn_name, shaft.name()));
+                }
+                indexes.push(i);
+            }
+            shaft_connectors_index.push(indexes);
+        }
+        for (shaft, conn_index_list) in self.shafts.iter().zip(shaft_connectors_index.iter()) {
+            for i in conn_index_list.iter() {
+                self.connector[*i].set_shaft_speed(shaft.speed())?;
+            }
+        }
+        self.shaft_connectors_index = shaft_connectors_index;
+
+        // finding which connectors are driven by the crankshaft
+        let crank_driven_index: Vec<usize> = (0..self.connector.len())
+            .filter(|i| self.connector[*i].drive_ratio().is_some())
+            .collect();
+        if !crank_driven_index.is_empty() && self.engine.is_none() {
+            return Err(format!(
+                "'{}' is driven by the crankshaft but no engine was added",
+                self.connector[crank_driven_index[0]].name()
+            ));
+        }
+        self.crank_driven_index = crank_driven_index;
+
+        // finding which ZeroDim objects are driven by the cylinders' crankshaft
+        let mut crank_angle_index: Vec<(usize, usize)> = Vec::new();
+        for (i, zero) in self.zero_dim.iter().enumerate() {
+            if let Some(cyl_name) = zero.driving_cylinder() {
+                let j = self
+                    .engine
+                    .as_ref()
+                    .and_then(|eng| eng.cylinders().iter().position(|c| c.name() == cyl_name));
+                match j {
+                    Some(j) => crank_angle_index.push((i, j)),
+                    None => {
+                        return Err(format!(
+                            "'{}' is driven by cylinder '{}', which was not found",
+                            zero.name(),
+                            cyl_name
+                        ))
+                    }
+                }
+            }
+        }
+        self.crank_angle_index = crank_angle_index;
+
+        // engine valves are copied into the connectors: their actuation is set through `Engine`
+        let mut valve_index: Vec<(usize, usize)> = Vec::new();
+        if let Some(eng) = &self.engine {
+            for (i, valve) in eng.valves().iter().enumerate() {
+                valve_index.push((i, self.connector_index(valve.name())?));
+            }
+        }
+        self.valve_index = valve_index;
+
         self.zero_dim_connectors_index = zero_dim_connectors_index;
         self.one_dim_connectors_index = one_dim_connectors_index;
         self.connector_objects_index = connector_objects_index;
         Ok(self)
     }
 
+    fn connector_index(&self, conn_name: &str) -> Result<usize, String> {
+        match self.objs_info.iter().find(|info| info.name == conn_name) {
+            Some(ObjectInfo {
+                obj_type: ObjectType::Connector,
+                index,
+                ..
+            }) => Ok(*index),
+            Some(_) => Err(format!("'{}' is not a connector", conn_name)),
+            None => Err(format!("connector '{}' was not found", conn_name)),
+        }
+    }
+
     fn get_connectors_index(&self, obj_name: String) -> IndexOutput {
         let mut conn_indexes: Vec<(ObjectType, usize)> = Vec::new();
         // searching connector vector
@@ -431,3 +665,56 @@ impl System {
         // }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Gas, SystemBuilder};
+
+    fn system() -> super::System {
+        let mut gas_ambient = Gas::new("air.json");
+        gas_ambient.TPX(293.0, 2.0 * 101325.0, "O2:0.21, N2:0.79");
+        let mut gas_chamber = Gas::new("air.json");
+        gas_chamber.TPX(293.0, 101325.0, "O2:0.21, N2:0.79");
+        let mut builder = SystemBuilder::new();
+        builder
+            .add_environment("ambient", &gas_ambient)
+            .add_reservoir("chamber", 500.0, &gas_chamber)
+            .add_orifice("orifice", 50.0, 0.9, vec!["ambient", "chamber"]);
+        let mut system = builder.build_system();
+        system.advance_to_steady_state();
+        system
+    }
+
+    #[test]
+    fn traces_of_stored_variables() {
+        let system = system();
+        let names = system.stored_variables("chamber").unwrap();
+        assert!(names.contains(&"pressure [bar]"));
+        // without engine, the whole simulation is returned
+        let (first, last) = system.last_cycle_range();
+        assert_eq!(first, 0);
+
+        let pressure = system.trace("chamber", "pressure [bar]").unwrap();
+        assert_eq!(pressure.len(), last);
+        // the chamber is filled from the ambient
+        assert!(pressure[0] < 1.1 * 1.01325);
+        assert!((pressure[last - 1] / (2.0 * 1.01325) - 1.0).abs() < 0.02);
+
+        let time = system.trace("chamber", "time [s]").unwrap();
+        assert_eq!(time.len(), last);
+        assert!(time.windows(2).into_iter().all(|t| t[1] > t[0]));
+        let part = system.trace_in_range("chamber", "pressure [bar]", (2, 5)).unwrap();
+        assert_eq!(part.to_vec(), pressure.slice(ndarray::s![2..5]).to_vec());
+    }
+
+    #[test]
+    fn trace_errors() {
+        let system = system();
+        let (_, last) = system.last_cycle_range();
+        assert!(system.trace("plenum", "pressure [bar]").is_err());
+        assert!(system.trace("chamber", "pressure [Pa]").is_err());
+        assert!(system.trace_in_range("chamber", "pressure [bar]", (5, 2)).is_err());
+        assert!(system.trace_in_range("chamber", "pressure [bar]", (0, last + 1)).is_err());
+        assert!(system.stored_variables("plenum").is_err());
+    }
+}
@@ -3,6 +3,12 @@
 use super::system::System;
 use crate::zero_dim;
 use crate::connector;
+use crate::connector::egr_cooler::HeatExchange;
+use crate::connector::blower::BlowerMap;
+use crate::connector::reed_valve::ReedPetal;
+use crate::connector::turbocharger::{Shaft, TurboMap};
+use crate::connector::valve::DischargeCoeffMap;
+use crate::zero_dim::reservoir::ReservoirWall;
 // use crate::one_dim;
 use crate::engine::engine::Engine;
 use crate::reaction::gas::Gas;
@@ -25,6 +31,7 @@ pub struct SystemBuilder {
     zero_dim: Vec<Box<dyn ZeroD>>,
     one_dim: Vec<Box<dyn OneD>>,
     connector: Vec<Box<dyn Conn>>,
+    shafts: Vec<Shaft>,
 }
 
 impl SystemBuilder {
@@ -39,6 +46,7 @@ impl SystemBuilder {
             zero_dim: Vec::new(),
             one_dim: Vec::new(),
             connector: Vec::new(),
+            shafts: Vec::new(),
         }
     }
 
@@ -50,6 +58,7 @@ impl SystemBuilder {
             self.zero_dim,
             self.one_dim,
             self.connector,
+            self.shafts,
         ) {
             Ok(s) => s,
             Err(err) => {
@@ -100,6 +109,22 @@ impl SystemBuilder {
             self.connector.push(Box::new(val.clone()));
         }
 
+        for port in engine.ports() {
+            if self.does_it_exist(port.name()) {
+                println!("Error at `add_engine`:");
+                println!(" Object with the same name already exists: {}", port.name());
+                std::process::exit(1);
+            }
+            let i = self.connector.len();
+            self.objs_info.push(ObjectInfo::new(
+                port.name().to_string(),
+                ObjectType::Connector,
+                i,
+                StoreData::new(&port.get_headers(), port.num_storable_variables()),
+            ));
+            self.connector.push(Box::new(port.clone()));
+        }
+
         // Pushing ´engine´
         self.engine = Some(engine);
         self
@@ -174,6 +199,139 @@ impl SystemBuilder {
         self
     }
 
+    /// Add a `zero_dim::Reservoir` with heat transfer to the lumped `wall`, see
+    /// [`ReservoirWall`](../../zero_dim/reservoir/struct.ReservoirWall.html). The input `volume` must be in cubic centimeters [cm³].
+    pub fn add_reservoir_with_wall<'a>(
+        &'a mut self,
+        elem_name: &str,
+        volume: f64,
+        gas: &Gas,
+        wall: ReservoirWall,
+    ) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_reservoir_with_wall':");
+            println!("Object with the same name already exists");
+            std::process::exit(1)
+        }
+
+        // pushing reservoir
+        let res = match zero_dim::reservoir::Reservoir::with_wall(elem_name.to_string(), gas, volume*1e-6, wall) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Error at 'add_reservoir_with_wall':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.zero_dim.len();
+        self.objs_info.push(ObjectInfo::new(
+            elem_name.to_string(),
+            ObjectType::ZeroDim,
+            i,
+            StoreData::new(&res.get_headers(), res.num_storable_variables()),
+        ));
+
+        self.zero_dim.push(Box::new(res));
+
+        self
+    }
+
+    /// Add a `zero_dim::Intercooler`: a heat exchanger of `volume`, in cm³, with a pressure drop `press_drop_coeff*m_dot²`,
+    /// where `press_drop_coeff` is in Pa/(kg/s)², which cools the incoming gas towards `coolant_temp`, in K, according to `heat_exchange`.
+    pub fn add_intercooler<'a>(
+        &'a mut self,
+        elem_name: &str,
+        volume: f64,
+        press_drop_coeff: f64,
+        heat_exchange: HeatExchange,
+        coolant_temp: f64,
+        gas: &Gas,
+    ) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_intercooler':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        // pushing intercooler
+        let intercooler = match zero_dim::intercooler::Intercooler::new(
+            elem_name.to_string(),
+            gas,
+            volume * 1e-6,
+            press_drop_coeff,
+            heat_exchange,
+            coolant_temp,
+        ) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Error at 'add_intercooler':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.zero_dim.len();
+        self.objs_info.push(ObjectInfo::new(
+            elem_name.to_string(),
+            ObjectType::ZeroDim,
+            i,
+            StoreData::new(&intercooler.get_headers(), intercooler.num_storable_variables()),
+        ));
+
+        self.zero_dim.push(Box::new(intercooler));
+        self
+    }
+
+    /// Add a `zero_dim::Crankcase` of the two-stroke engine cylinder `cylinder`, which must have a `crankcase_compression_ratio`.
+    /// Its volume follows the piston of `cylinder`. Must be called after `add_engine()`.
+    pub fn add_crankcase<'a>(&'a mut self, elem_name: &str, cylinder: &str, gas: &Gas) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_crankcase':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        // finding the cylinder
+        let cyl = match self.engine.as_ref().map(|eng| eng.cylinders().iter().find(|c| c.name() == cylinder)) {
+            Some(Some(cyl)) => cyl,
+            Some(None) => {
+                println!("Error at 'add_crankcase':");
+                println!(" cylinder `{}` was not found", cylinder);
+                std::process::exit(1)
+            }
+            None => {
+                println!("Error at 'add_crankcase':");
+                println!(" an engine must be added before the crankcase");
+                std::process::exit(1)
+            }
+        };
+
+        // pushing crankcase
+        let crankcase = match zero_dim::crankcase::Crankcase::new(elem_name.to_string(), gas, cyl) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Error at 'add_crankcase':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.zero_dim.len();
+        self.objs_info.push(ObjectInfo::new(
+            elem_name.to_string(),
+            ObjectType::ZeroDim,
+            i,
+            StoreData::new(&crankcase.get_headers(), crankcase.num_storable_variables()),
+        ));
+
+        self.zero_dim.push(Box::new(crankcase));
+        self
+    }
+
     /// Add a `connector::Orifice` connector. It connects two `ZeroDim` through a hole of diameter `diam` in mm.
     /// The discharge coefficient must be between 0 and 1.
     pub fn add_orifice<'a>(&'a mut self, elem_name:&str, diam: f64, discharge_coeff: f64, conn: Vec<&str>) -> &'a mut Self {
@@ -207,6 +365,291 @@ impl SystemBuilder {
         self
     }
 
+    /// Add a `connector::ControlledOrifice` connector as an EGR valve. It connects two `ZeroDim` through a hole of diameter `diam`, in mm,
+    /// when fully open. The `opening` must be between 0 (closed) and 1 (fully open) and can be changed with
+    /// [`System::set_opening_of()`](../system/struct.System.html#method.set_opening_of).
+    pub fn add_egr_valve<'a>(&'a mut self, elem_name: &str, diam: f64, discharge_coeff: f64, opening: f64, conn: Vec<&str>) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_egr_valve':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        // pushing connector
+        let connecting: Vec<String> = conn.iter().map(|c| c.to_string()).collect();
+        let egr_valve = match connector::controlled_orifice::ControlledOrifice::new(elem_name, diam*1e-3, discharge_coeff, opening, connecting) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Error at 'add_egr_valve':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.connector.len();
+        self.objs_info.push(ObjectInfo::new(
+            egr_valve.name().to_string(),
+            ObjectType::Connector,
+            i,
+            StoreData::new(&egr_valve.get_headers(), egr_valve.num_storable_variables()),
+        ));
+        self.connector.push(Box::new(egr_valve));
+        self
+    }
+
+    /// Add a `connector::EgrCooler` connector. The flow between the two `ZeroDim` objects is restricted as through an orifice of
+    /// diameter `diam`, in mm, and the gas is cooled towards `coolant_temp`, in K, according to `heat_exchange`.
+    pub fn add_egr_cooler<'a>(
+        &'a mut self,
+        elem_name: &str,
+        diam: f64,
+        discharge_coeff: f64,
+        heat_exchange: HeatExchange,
+        coolant_temp: f64,
+        conn: Vec<&str>,
+    ) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_egr_cooler':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        // pushing connector
+        let connecting: Vec<String> = conn.iter().map(|c| c.to_string()).collect();
+        let cooler = match connector::egr_cooler::EgrCooler::new(elem_name, diam*1e-3, discharge_coeff, heat_exchange, coolant_temp, connecting) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Error at 'add_egr_cooler':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.connector.len();
+        self.objs_info.push(ObjectInfo::new(
+            cooler.name().to_string(),
+            ObjectType::Connector,
+            i,
+            StoreData::new(&cooler.get_headers(), cooler.num_storable_variables()),
+        ));
+        self.connector.push(Box::new(cooler));
+        self
+    }
+
+    /// Add a `connector::ControlledOrifice` connector as a wastegate. It connects two `ZeroDim`, usually bypassing a turbine,
+    /// through a hole of diameter `diam`, in mm, when fully open. The `opening` must be between 0 (closed) and 1 (fully open)
+    /// and can be changed with [`System::set_opening_of()`](../system/struct.System.html#method.set_opening_of).
+    pub fn add_wastegate<'a>(&'a mut self, elem_name: &str, diam: f64, discharge_coeff: f64, opening: f64, conn: Vec<&str>) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_wastegate':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        // pushing connector
+        let connecting: Vec<String> = conn.iter().map(|c| c.to_string()).collect();
+        let wastegate = match connector::controlled_orifice::ControlledOrifice::new(elem_name, diam*1e-3, discharge_coeff, opening, connecting) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Error at 'add_wastegate':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.connector.len();
+        self.objs_info.push(ObjectInfo::new(
+            wastegate.name().to_string(),
+            ObjectType::Connector,
+            i,
+            StoreData::new(&wastegate.get_headers(), wastegate.num_storable_variables()),
+        ));
+        self.connector.push(Box::new(wastegate));
+        self
+    }
+
+    /// Add a `connector::ReedValve` connector. `port_area` is in mm², `perimeter`, the length of the petal edge, is in mm and
+    /// `petal` is the [`ReedPetal`](../../connector/reed_valve/struct.ReedPetal.html) spring-mass model.
+    /// The petal opens, and the flow goes, from the first to the second object of `conn`.
+    pub fn add_reed_valve<'a>(
+        &'a mut self,
+        elem_name: &str,
+        port_area: f64,
+        perimeter: f64,
+        petal: ReedPetal,
+        discharge_coeff: f64,
+        conn: Vec<&str>,
+    ) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_reed_valve':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        // pushing connector
+        let connecting: Vec<String> = conn.iter().map(|c| c.to_string()).collect();
+        let reed_valve = match connector::reed_valve::ReedValve::new(elem_name, port_area*1e-6, perimeter*1e-3, petal, discharge_coeff, connecting) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Error at 'add_reed_valve':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.connector.len();
+        self.objs_info.push(ObjectInfo::new(
+            reed_valve.name().to_string(),
+            ObjectType::Connector,
+            i,
+            StoreData::new(&reed_valve.get_headers(), reed_valve.num_storable_variables()),
+        ));
+        self.connector.push(Box::new(reed_valve));
+        self
+    }
+
+    /// Add a `connector::Compressor` connector with the map read from the `.json` file `map_file`, see
+    /// [`TurboMap`](../../connector/turbocharger/struct.TurboMap.html). The flow goes from the first to the second
+    /// object of `conn`. The compressor must be coupled to a shaft with `add_turbo_shaft()`.
+    pub fn add_compressor<'a>(&'a mut self, elem_name: &str, map_file: &str, conn: Vec<&str>) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_compressor':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        // pushing connector
+        let connecting: Vec<String> = conn.iter().map(|c| c.to_string()).collect();
+        let compressor = match TurboMap::from_file(map_file)
+            .and_then(|map| connector::turbocharger::Compressor::new(elem_name, map, connecting))
+        {
+            Ok(c) => c,
+            Err(err) => {
+                println!("Error at 'add_compressor':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.connector.len();
+        self.objs_info.push(ObjectInfo::new(
+            compressor.name().to_string(),
+            ObjectType::Connector,
+            i,
+            StoreData::new(&compressor.get_headers(), compressor.num_storable_variables()),
+        ));
+        self.connector.push(Box::new(compressor));
+        self
+    }
+
+    /// Add a `connector::Turbine` connector with the map read from the `.json` file `map_file`, see
+    /// [`TurboMap`](../../connector/turbocharger/struct.TurboMap.html). The flow goes from the first to the second
+    /// object of `conn`. The turbine must be coupled to a shaft with `add_turbo_shaft()`.
+    pub fn add_turbine<'a>(&'a mut self, elem_name: &str, map_file: &str, conn: Vec<&str>) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_turbine':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        // pushing connector
+        let connecting: Vec<String> = conn.iter().map(|c| c.to_string()).collect();
+        let turbine = match TurboMap::from_file(map_file)
+            .and_then(|map| connector::turbocharger::Turbine::new(elem_name, map, connecting))
+        {
+            Ok(t) => t,
+            Err(err) => {
+                println!("Error at 'add_turbine':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.connector.len();
+        self.objs_info.push(ObjectInfo::new(
+            turbine.name().to_string(),
+            ObjectType::Connector,
+            i,
+            StoreData::new(&turbine.get_headers(), turbine.num_storable_variables()),
+        ));
+        self.connector.push(Box::new(turbine));
+        self
+    }
+
+    /// Add a `connector::Blower` connector: a positive-displacement blower or supercharger driven by the crankshaft.
+    /// `displacement` is the volume, in cm³, displaced per blower revolution, `drive_ratio` is the ratio between blower
+    /// and engine speed and `map_file` is the `.json` file of the [`BlowerMap`](../../connector/blower/struct.BlowerMap.html).
+    /// The flow goes from the first to the second object of `conn` and the power drawn is subtracted from the engine power.
+    pub fn add_blower<'a>(&'a mut self, elem_name: &str, displacement: f64, drive_ratio: f64, map_file: &str, conn: Vec<&str>) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_blower':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        // pushing connector
+        let connecting: Vec<String> = conn.iter().map(|c| c.to_string()).collect();
+        let blower = match BlowerMap::from_file(map_file).and_then(|map| {
+            connector::blower::Blower::new(elem_name, displacement * 1e-6, drive_ratio, map, connecting)
+        }) {
+            Ok(b) => b,
+            Err(err) => {
+                println!("Error at 'add_blower':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.connector.len();
+        self.objs_info.push(ObjectInfo::new(
+            blower.name().to_string(),
+            ObjectType::Connector,
+            i,
+            StoreData::new(&blower.get_headers(), blower.num_storable_variables()),
+        ));
+        self.connector.push(Box::new(blower));
+        self
+    }
+
+    /// Add a `connector::turbocharger::Shaft` coupling the compressors and turbines in `conn`. `inertia` is in kg*m², `friction` is the
+    /// bearing friction torque per angular speed, in N*m*s/rad, and `speed` is the initial speed in RPM.
+    /// The shaft speed is integrated in [`System::advance()`](../system/struct.System.html#method.advance).
+    pub fn add_turbo_shaft<'a>(&'a mut self, elem_name: &str, inertia: f64, friction: f64, speed: f64, conn: Vec<&str>) -> &'a mut Self {
+        // checking if 'elem_name' already exists
+        if self.does_it_exist(elem_name) {
+            println!("Error at 'add_turbo_shaft':");
+            println!("Object with the same name already exists: `{}`", elem_name);
+            std::process::exit(1)
+        }
+
+        let connecting: Vec<String> = conn.iter().map(|c| c.to_string()).collect();
+        let shaft = match Shaft::new(elem_name, inertia, friction, speed, connecting) {
+            Ok(s) => s,
+            Err(err) => {
+                println!("Error at 'add_turbo_shaft':\n {}", err);
+                std::process::exit(1)
+            }
+        };
+
+        // adding to list of objects
+        let i = self.shafts.len();
+        self.objs_info.push(ObjectInfo::new(
+            shaft.name().to_string(),
+            ObjectType::Shaft,
+            i,
+            StoreData::new(&shaft.get_headers(), shaft.num_storable_variables()),
+        ));
+        self.shafts.push(shaft);
+        self
+    }
+
     /// Connect a `connector` object to an element object. The inputs must be the name of the connector and element as `&str`
     pub fn connect_from_to<'a>(&'a mut self, connector: &str, elem_name: &str) -> &'a mut Self {
         //checking if 'elem_name' already exists
@@ -240,6 +683,45 @@ impl SystemBuilder {
         self
     }
 
+    /// Replaces the discharge coefficient curve of the valve `valve_name` by the tabulated values of `map`,
+    /// i.e. from flow-bench measurements
+    pub fn set_discharge_coeff_map<'a>(&'a mut self, valve_name: &str, map: &DischargeCoeffMap) -> &'a mut Self {
+        let obj_info = match self.objs_info.iter().find(|info| info.name == valve_name) {
+            Some(info) if matches!(info.obj_type, ObjectType::Connector) => info,
+            _ => {
+                println!("Error at 'SystemBuilder::set_discharge_coeff_map()':");
+                println!(" Connector with name '{}' does not exist", valve_name);
+                std::process::exit(1);
+            }
+        };
+        if let Err(err) = self.connector[obj_info.index].set_discharge_coeff_map(map) {
+            println!("Error at 'SystemBuilder::set_discharge_coeff_map()':");
+            println!(" {}", err);
+            std::process::exit(1);
+        }
+        self
+    }
+
+    /// Adds the mole fraction of every species to the stored data of the reservoir `elem_name`
+    pub fn store_species_of<'a>(&'a mut self, elem_name: &str) -> &'a mut Self {
+        let obj_info = match self.objs_info.iter_mut().find(|info| info.name == elem_name) {
+            Some(info) if matches!(info.obj_type, ObjectType::ZeroDim) => info,
+            _ => {
+                println!("Error at 'SystemBuilder::store_species_of()':");
+                println!(" Zero-dimensional object with name '{}' does not exist", elem_name);
+                std::process::exit(1);
+            }
+        };
+        let obj = &mut self.zero_dim[obj_info.index];
+        if let Err(err) = obj.set_store_species(true) {
+            println!("Error at 'SystemBuilder::store_species_of()':");
+            println!(" {}", err);
+            std::process::exit(1);
+        }
+        obj_info.stored_data = StoreData::new(&obj.get_headers(), obj.num_storable_variables());
+        self
+    }
+
     fn does_it_exist(&self, obj_name: &str) -> bool {
         self.objs_info.iter().any(|info| info.name == obj_name)
     }
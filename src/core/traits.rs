@@ -1,4 +1,5 @@
 use ndarray::*;
+use crate::connector::valve::{DischargeCoeffMap, ValveActuation};
 use crate::{BasicProperties, FlowRatio};
 
 // Super Traits
@@ -9,8 +10,23 @@ pub trait Conn: Connector + SaveData {}
 pub trait ZeroDim {
     fn name<'a>(&'a self) -> &'a str;
     fn get_state(&self) -> BasicProperties;
+    /// Returns the state seen by the connector `conn_name`. It differs from `get_state()` only for objects
+    /// with pressure losses between their connections
+    fn get_state_to(&self, _conn_name: &str) -> BasicProperties<'_> {
+        self.get_state()
+    }
     fn advance(&mut self, dt: f64);
     fn update_flow_ratio(&mut self, total_flow_ratio: Vec<(&str, &FlowRatio)>);
+    /// Returns the name of the engine cylinder whose crankshaft drives the object, if any
+    fn driving_cylinder(&self) -> Option<&str> {
+        None
+    }
+    /// Set the crank-angle, in CA radian, and the engine speed, in CA radian per second, of objects driven by the crankshaft
+    fn set_crank_angle(&mut self, _angle: f64, _sec_to_rad: f64) {}
+    /// Store the mole fraction of every species along with the other variables
+    fn set_store_species(&mut self, _state: bool) -> Result<(), String> {
+        Err(format!("`{}` does not support storing species", self.name()))
+    }
 }
 
 pub trait OneDim { 
@@ -39,6 +55,38 @@ pub trait Connector {
         println!("updating {} with information {:?}", self.name(), info);
     }
     fn get_flow_ratio<'a>(&'a self, elem_name: &str) -> Result<&'a FlowRatio, String>;
+    /// Returns the discharge coefficient, if the connector has one
+    fn discharge_coeff(&self) -> Option<f64> {
+        None
+    }
+    fn set_discharge_coeff(&mut self, _discharge_coeff: f64) -> Result<(), String> {
+        Err(format!("`{}` has no discharge coefficient", self.name()))
+    }
+    /// Set the opening of connectors with variable area, between 0 (closed) and 1 (fully open)
+    fn set_opening(&mut self, _opening: f64) -> Result<(), String> {
+        Err(format!("`{}` has no variable opening", self.name()))
+    }
+    /// Replaces the discharge coefficient curve by `map`
+    fn set_discharge_coeff_map(&mut self, _map: &DischargeCoeffMap) -> Result<(), String> {
+        Err(format!("`{}` does not accept a discharge coefficient map", self.name()))
+    }
+    /// Set the variable valve actuation of engine valves
+    fn set_valve_actuation(&mut self, _actuation: ValveActuation) -> Result<(), String> {
+        Err(format!("`{}` has no valve actuation", self.name()))
+    }
+    /// Returns the power, in W, delivered to (positive) or absorbed from (negative) a shaft,
+    /// if the connector can be coupled to one
+    fn shaft_power(&self) -> Option<f64> {
+        None
+    }
+    /// Set the speed, in RPM, of the shaft driving the connector
+    fn set_shaft_speed(&mut self, _speed: f64) -> Result<(), String> {
+        Err(format!("`{}` cannot be coupled to a shaft", self.name()))
+    }
+    /// Returns the ratio between the connector and the engine speed, if the connector is driven by the crankshaft
+    fn drive_ratio(&self) -> Option<f64> {
+        None
+    }
 }
 
 pub trait SaveData {
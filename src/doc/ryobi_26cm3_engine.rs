@@ -10,9 +10,15 @@
 //!     "displacement": 26.2,
 //!     "bore": 32.0,
 //!     "firing_order": "1",
+//!     "strokes": 2,
+//!     "scavenging": {
+//!         "model": "Benson-Brandham",
+//!         "short_circuit": 0.15,
+//!         "displacement": 0.3
+//!     },
 //!     "combustion": {
 //!         "model": "Two-zone model",        
-//!         "comb_ini": 710.0,
+//!         "comb_ini": 335.0,
 //!         "wiebe": {
 //!             "m": 1.535,
 //!             "a": 6.60,
@@ -34,22 +40,22 @@
 //!             "name": "cyl_1",
 //!             "compression_ratio": 8.5,
 //!             "wall_temperature": 470.0,
-//!             "intake_valves": [
+//!             "crankcase_compression_ratio": 1.4,
+//!             "transfer_ports": [
 //!                 {
-//!                     "name": "valve_int",
-//!                     "opening_angle": 285.0,
-//!                     "closing_angle": 630.0,
-//!                     "diameter": 9.3,
-//!                     "max_lift": 3.4
+//!                     "name": "transfer",
+//!                     "opening_angle": 122.0,
+//!                     "width": 9.0,
+//!                     "height": 5.0,
+//!                     "number": 2
 //!                 }
 //!             ],
-//!             "exhaust_valves": [
-//!                 {   
-//!                     "name": "valve_exh",
-//!                     "opening_angle": 90.0,
-//!                     "closing_angle": 435.0,
-//!                     "diameter": 9.3,
-//!                     "max_lift": 3.4
+//!             "exhaust_ports": [
+//!                 {
+//!                     "name": "exhaust",
+//!                     "opening_angle": 100.0,
+//!                     "width": 16.0,
+//!                     "height": 9.0
 //!                 }
 //!             ]
 //!         }
@@ -57,9 +63,13 @@
 //! }
 //! ```
 //!
+//! The crankcase is compressed by the descending piston and delivers the charge to the cylinder through the
+//! transfer ports, while the reed valve lets the fresh charge in when the crankcase pressure drops below the intake plenum.
+//!
 //! ```
 //! use lmb::Gas;
 //! use lmb_engine_simulator as lmb;
+//! use lmb::connector::reed_valve::ReedPetal;
 //!
 //! fn main() {
 //!
@@ -68,22 +78,25 @@
 //!     let mut gas_exhaust = Gas::new("air.json");
 //!     gas_exhaust.TPX(500.0, 101325.0, "N2:0.662586, H2O:0.202449, CO2:0.134965");
 //!     
+//!     // reed valve petal: 4 cm² loaded area, 1.5 g, 800 N/m, 20% of critical damping and 6 mm of maximum lift
+//!     let petal = ReedPetal::new(4.0e-4, 1.5e-3, 800.0, 0.2, 6.0e-3).unwrap();
+//!
 //!     let mut builder = lmb::SystemBuilder::new();
 //!     builder.add_engine("engine_Ryobi.json", &gas)
 //!         .add_environment("ambient", &gas)
 //!         .add_reservoir("int_plenum", 250.0, &gas_intake)
-//!         .add_reservoir("int_port", 2.04, &gas_intake)
+//!         .add_crankcase("crankcase", "cyl_1", &gas_intake)
 //!         .add_reservoir("exh_port", 2.04, &gas_exhaust)
 //!         .add_reservoir("exh_plenum_1", 95.0, &gas_exhaust)
 //!         .add_reservoir("exh_plenum_2", 158.0, &gas_exhaust)
 //!         .add_orifice("int_plenum -> amb 1", 9.5, 0.92, vec!["ambient", "int_plenum"])
 //!         .add_orifice("int_plenum -> amb 2", 9.5, 0.92, vec!["ambient", "int_plenum"])
-//!         .add_orifice("int_plenum -> int_port", 6.5, 0.78, vec!["int_plenum", "int_port"])
+//!         .add_reed_valve("reed_valve", 120.0, 40.0, petal, 0.7, vec!["int_plenum", "crankcase"])
 //!         .add_orifice("exh_plenum_2 -> amb", 8.9, 0.78, vec!["exh_plenum_2", "ambient"])
 //!         .add_orifice("exh_plenum_1 -> exh_plenum_2", 12.7, 0.78, vec!["exh_plenum_1", "exh_plenum_2"])
 //!         .add_orifice("exh_port -> exh_plenum_1", 10.8, 0.78, vec!["exh_port", "exh_plenum_1"])
-//!         .connect_from_to("valve_int", "int_port")
-//!         .connect_from_to("valve_exh", "exh_port");
+//!         .connect_from_to("transfer", "crankcase")
+//!         .connect_from_to("exhaust", "exh_port");
 //!     
 //!     let mut system = builder.build_system();
 //!
@@ -96,12 +109,12 @@
 //!         let folder_name = format!("./Ryobi_26_results/{:.0}_", speed);
 //!         system.write_to_file( &(folder_name.clone() + "cylinder.txt"), "cyl_1", None);
 //!         system.write_to_file(&(folder_name.clone() + "int_plenum.txt"), "int_plenum", None);
-//!         system.write_to_file(&(folder_name.clone() + "int_port.txt"), "int_port", None);
+//!         system.write_to_file(&(folder_name.clone() + "crankcase.txt"), "crankcase", None);
 //!         system.write_to_file(&(folder_name.clone() + "exh_port.txt"), "exh_port", None);
 //!         system.write_to_file(&(folder_name.clone() + "exh_plenum_1.txt"), "exh_plenum_1", None);
 //!         system.write_to_file(&(folder_name.clone() + "exh_plenum_2.txt"), "exh_plenum_2", None);
-//!         system.write_to_file(&(folder_name.clone() + "int_valve.txt"), "valve_int", None);
-//!         system.write_to_file(&(folder_name.clone() + "exh_valve.txt"), "valve_exh", None);
+//!         system.write_to_file(&(folder_name.clone() + "reed_valve.txt"), "reed_valve", None);
+//!         system.write_to_file(&(folder_name.clone() + "exh_port_flow.txt"), "exhaust", None);
 //!     }
 //!
 //!     system.engine().unwrap().write_performance_to("./Ryobi_26_results/engine_performance.txt");
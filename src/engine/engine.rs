@@ -1,11 +1,12 @@
 use crate::base::constants::{_P_REF, _T_REF};
-use crate::connector::valve::Valve;
-use crate::core::traits::ZeroDim;
+use crate::connector::port::{Port, PortEdge};
+use crate::connector::valve::{DischargeCoeffMap, LiftProfile, ReferenceArea, Valve, ValveActuation};
+use crate::core::traits::{Connector, ZeroDim};
 use crate::reaction::combustion;
 use crate::reaction::combustion::{Combustion, WiebeFunction};
 use crate::reaction::gas::Gas;
-use crate::zero_dim::cylinder::Cylinder;
-use super::json_reader::{JsonEngine, JsonFuel};
+use crate::zero_dim::cylinder::{BlowBy, Crevice, Cylinder, Scavenging};
+use super::json_reader::{JsonEngine, JsonFriction, JsonFuel, JsonFuelComponent, JsonInjectionPulse};
 use crate::FlowRatio;
 use ndarray::*;
 use serde_json;
@@ -18,10 +19,17 @@ pub struct Engine {
     bore: f64,
     eccentricity: f64,
     firing_order: String,
+    tdc_offsets: Vec<f64>,
+    strokes: u8,
     combustion: Box<dyn Combustion>,
     injector: Option<Injector>,
+    friction: Option<Friction>,
     cylinders: Vec<Cylinder>,
     valves: Vec<Valve>,
+    intake_valves: Vec<usize>, // indexes in `valves`
+    intake_actuation: ValveActuation,
+    exhaust_actuation: ValveActuation,
+    ports: Vec<Port>,
     operat_param: OperationalParameters,
     sec_to_rad: f64,
 }
@@ -30,7 +38,7 @@ type EngineOutput = Result<Engine, String>;
 
 impl Engine {
     pub fn new(file_name: &str, gas: &Gas) -> EngineOutput {
-        let json_engine = match Engine::reading_json(file_name) {
+        let mut json_engine = match Engine::reading_json(file_name) {
             Ok(eng) => eng,
             Err(err) => {
                 let msg = format!(
@@ -41,34 +49,12 @@ impl Engine {
             }
         };
 
-        let firing_order: Vec<f64> = json_engine
-            .firing_order
-            .split('-')
-            .map(|s| -> f64 {
-                match s.parse() {
-                    Ok(s) => s,
-                    Err(err) => {
-                        eprintln!("Error at Engine:new \n unable to parse {} \n{}", s, err);
-                        std::process::exit(1);
-                    }
-                }
-            })
-            .collect();
-
-        if firing_order.len() != json_engine.cylinders.len() {
-            let msg = format!(
-                "Error at Engine::new 
-            `firing_order` and `cylinders` must have the same length.
-            `firing_order`: {}
-            `cylinder`: {}",
-                firing_order.len(),
-                json_engine.cylinders.len()
-            );
-            return Err(msg);
+        let strokes = json_engine.strokes.unwrap_or(4);
+        if strokes != 2 && strokes != 4 {
+            return Err(format!("Error at Engine::new \n engine must have 2 or 4 strokes: {}", strokes));
         }
-
-        let num_divisions = firing_order.iter().cloned().fold(0. / 0., f64::max); // gets highest value
-        let division = 720.0 / num_divisions;
+        let cycle_deg = 180.0 * f64::from(strokes);
+        let tdc_offsets = Engine::read_tdc_offsets(&json_engine, cycle_deg)?;
 
         // checking condition
         if json_engine.combustion.is_some() && json_engine.injector.is_none() {
@@ -89,7 +75,7 @@ impl Engine {
         let combustion: Box<dyn Combustion>;
         if let Some(inj_json) = &json_engine.injector {
             // creating Fuel obj
-            let fuel = Fuel::new(&inj_json.fuel, gas);
+            let fuel = Fuel::new(&inj_json.fuel, gas)?;
             // creating Injector obj
             injector = Some(Injector::new(
                 inj_json.inj_type.clone(),
@@ -97,13 +83,13 @@ impl Engine {
                 &fuel,
                 &air_gas,
             ));
-            if let Some(comb) = &json_engine.combustion {
-                if !gas.contains_specie(&inj_json.fuel.name) {
-                    return Err(format!(
-                        "the gas added to the cylinder does not contain specie \"{}\"",
-                        inj_json.fuel.name
-                    ));
+            if let Some(inj) = &mut injector {
+                inj.set_pulses(Engine::injection_pulses(&inj_json.pulses)?)?;
+                if let Some(film) = &inj_json.wall_film {
+                    inj.set_wall_film(Some(WallFilm::new(film.deposit_fraction, film.time_constant)?))?;
                 }
+            }
+            if let Some(comb) = &json_engine.combustion {
                 // Combustion models
                 if comb.model == "Two-zone model" {
                     let wiebe =
@@ -128,14 +114,49 @@ impl Engine {
             combustion = Box::new(combustion::NoCombustion::new());
         }
 
+        let friction = match &json_engine.friction {
+            Some(json) => Some(Friction::from_json(json)?),
+            None => None,
+        };
+
+        // measured lift profiles: they set the timing and maximum lift of their valves
+        let mut lift_profiles: Vec<(String, LiftProfile)> = Vec::new();
+        for cylinder in json_engine.cylinders.iter_mut() {
+            for valve in cylinder
+                .intake_valves
+                .iter_mut()
+                .chain(cylinder.exhaust_valves.iter_mut())
+            {
+                if let Some(json_profile) = &valve.lift_profile {
+                    let lift: Vec<f64> = json_profile.lift.iter().map(|l| l * 1e-3).collect();
+                    let profile = match LiftProfile::new(
+                        &json_profile.angle,
+                        &lift,
+                        json_profile.lash.unwrap_or(0.0) * 1e-3,
+                        json_profile.duration_scale.unwrap_or(1.0),
+                        json_profile.lift_scale.unwrap_or(1.0),
+                        cycle_deg,
+                    ) {
+                        Ok(profile) => profile,
+                        Err(err) => return Err(format!("valve `{}`: {}", valve.name, err)),
+                    };
+                    valve.opening_angle = profile.opening_angle();
+                    valve.closing_angle = profile.closing_angle();
+                    valve.max_lift = profile.max_lift() * 1e3;
+                    lift_profiles.push((valve.name.clone(), profile));
+                }
+            }
+        }
+
         // instantianting cylinders
         let mut cyl_names: Vec<String> = Vec::new();
         let mut cylinders: Vec<Cylinder> = Vec::new();
-        for (cylinder, order) in json_engine.cylinders.iter().zip(firing_order.iter()) {
+        for (cylinder, offset) in json_engine.cylinders.iter().zip(tdc_offsets.iter()) {
             cyl_names.push(cylinder.name.clone());
+            // the engine starts with the first cylinder at bottom-dead-center
             cylinders.push(Cylinder::new(
                 cylinder.name.clone(),
-                180.0 + (order - 1.0) * division,
+                (180.0 - offset).rem_euclid(cycle_deg),
                 &json_engine,
                 &cylinder,
                 &cylinder.intake_valves,
@@ -149,10 +170,12 @@ impl Engine {
         // instantianting valves
         let mut valve_names: Vec<String> = Vec::new();
         let mut valves: Vec<Valve> = Vec::new();
+        let mut intake_valves: Vec<usize> = Vec::new();
         for cylinder in json_engine.cylinders.iter() {
             // intake valves
             for valve in cylinder.intake_valves.iter() {
                 valve_names.push(valve.name.clone());
+                intake_valves.push(valves.len());
                 let cyl = match cylinders.iter().find(|c| c.name() == cylinder.name) {
                     Some(c) => c,
                     None => {
@@ -160,14 +183,24 @@ impl Engine {
                         return Err(msg);
                     }
                 };
-                valves.push(Valve::new(
-                    valve.name.clone(),
-                    valve.opening_angle,
-                    valve.closing_angle,
-                    valve.diameter * 1e-3,
-                    valve.max_lift * 1e-3,
-                    cyl,
-                )?);
+                let mut intake_valve = match lift_profiles.iter().find(|(name, _)| *name == valve.name) {
+                    Some((_, profile)) => Valve::with_lift_profile(
+                        valve.name.clone(),
+                        valve.diameter * 1e-3,
+                        profile,
+                        cyl,
+                    )?,
+                    None => Valve::new(
+                        valve.name.clone(),
+                        valve.opening_angle,
+                        valve.closing_angle,
+                        valve.diameter * 1e-3,
+                        valve.max_lift * 1e-3,
+                        cyl,
+                    )?,
+                };
+                intake_valve.set_intake();
+                valves.push(intake_valve);
             }
 
             // exhaust valves
@@ -180,28 +213,105 @@ impl Engine {
                         return Err(msg);
                     }
                 };
-                valves.push(Valve::new(
-                    valve.name.clone(),
-                    valve.opening_angle,
-                    valve.closing_angle,
-                    valve.diameter * 1e-3,
-                    valve.max_lift * 1e-3,
+                valves.push(match lift_profiles.iter().find(|(name, _)| *name == valve.name) {
+                    Some((_, profile)) => Valve::with_lift_profile(
+                        valve.name.clone(),
+                        valve.diameter * 1e-3,
+                        profile,
+                        cyl,
+                    )?,
+                    None => Valve::new(
+                        valve.name.clone(),
+                        valve.opening_angle,
+                        valve.closing_angle,
+                        valve.diameter * 1e-3,
+                        valve.max_lift * 1e-3,
+                        cyl,
+                    )?,
+                });
+            }
+        }
+
+        // instantiating ports
+        let mut ports: Vec<Port> = Vec::new();
+        for (cylinder, cyl) in json_engine.cylinders.iter().zip(cylinders.iter()) {
+            let json_ports = cylinder
+                .intake_ports
+                .iter()
+                .map(|p| (p, PortEdge::Skirt))
+                .chain(cylinder.transfer_ports.iter().map(|p| (p, PortEdge::Crown)))
+                .chain(cylinder.exhaust_ports.iter().map(|p| (p, PortEdge::Crown)));
+            for (port, edge) in json_ports {
+                ports.push(Port::new(
+                    port.name.clone(),
+                    edge,
+                    port.opening_angle,
+                    port.width * 1e-3 * f64::from(port.number.unwrap_or(1)),
+                    port.height * 1e-3,
+                    port.discharge_coeff.unwrap_or(0.7),
                     cyl,
                 )?);
             }
         }
 
+        // measured discharge coefficients
+        for json_valve in json_engine
+            .cylinders
+            .iter()
+            .flat_map(|c| c.intake_valves.iter().chain(c.exhaust_valves.iter()))
+        {
+            if let Some(json_cd) = &json_valve.discharge_coeff {
+                let reference = match (json_cd.reference_area.as_str(), json_cd.port_diameter) {
+                    ("curtain", _) => ReferenceArea::Curtain,
+                    ("inner seat", _) => ReferenceArea::InnerSeat,
+                    ("port", Some(diam)) => ReferenceArea::Port(diam * 1e-3),
+                    ("throat", _) => ReferenceArea::Throat,
+                    ("port", None) => {
+                        return Err(format!(
+                            "valve `{}`: \"port_diameter\" is required for reference area \"port\"",
+                            json_valve.name
+                        ))
+                    }
+                    (reference, _) => {
+                        return Err(format!(
+                            "valve `{}`: unknown reference area \"{}\". Options: \"curtain\", \"inner seat\", \"port\" or \"throat\"",
+                            json_valve.name, reference
+                        ))
+                    }
+                };
+                let map = DischargeCoeffMap::new(
+                    json_cd.lift_diam.clone(),
+                    json_cd.forward.clone(),
+                    json_cd.backward.clone(),
+                    reference,
+                )
+                .map_err(|err| format!("valve `{}`: {}", json_valve.name, err))?;
+                let valve = valves
+                    .iter_mut()
+                    .find(|v| v.name() == json_valve.name)
+                    .ok_or_else(|| format!("valve `{}` was not found", json_valve.name))?;
+                valve.set_discharge_coeff_map(&map)?;
+            }
+        }
+
         let engine = Engine {
             speed: json_engine.speed,
             displacement: json_engine.displacement,
             bore: json_engine.bore,
             eccentricity: json_engine.eccentricity,
             firing_order: json_engine.firing_order.clone(),
+            tdc_offsets,
+            strokes,
             sec_to_rad: 2.0 * PI * json_engine.speed / 60.0,
             cylinders,
             valves,
+            intake_valves,
+            intake_actuation: ValveActuation::default(),
+            exhaust_actuation: ValveActuation::default(),
+            ports,
             combustion,
             injector: injector,
+            friction,
             operat_param: OperationalParameters::new(),
         };
         Ok(engine)
@@ -211,14 +321,23 @@ impl Engine {
         self.cylinders.iter_mut().for_each(|cyl| cyl.advance(dt));
     }
 
+    /// Calculates the operational parameters from the pressure, in bar, volume, in cm³, and crank-angle, in CA-deg,
+    /// of each cylinder over the last cycle. `parasitic_power`, in W, is the power drawn by accessories driven by the crankshaft
     pub fn calc_operational_param(
         &mut self,
         press: Vec<ArrayView1<f64>>, // bar
         vol: Vec<ArrayView1<f64>>,   // cm^3
+        angle: Vec<ArrayView1<f64>>, // CA-deg
+        parasitic_power: f64,        // W
     ) {
         let mut power = 0.0f64;
         let mut total_work = 0.0f64;
-        for (p, v) in press.iter().zip(vol) {
+        let mut pumping_work = 0.0f64;
+        let mut peak_press = 0.0f64;
+        let mut cyl_work: Vec<f64> = Vec::new();
+        let mut cyl_peak: Vec<(f64, f64)> = Vec::new(); // pressure and crank-angle
+        let mut trapping_press: Vec<f64> = Vec::new();
+        for (((p, v), a), cyl) in press.iter().zip(vol).zip(angle).zip(self.cylinders.iter()) {
             let mut dv: Array1<f64> = Array1::zeros(v.len() - 1);
             let mut p_mean: Array1<f64> = Array1::zeros(p.len() - 1);
             Zip::from(&mut dv)
@@ -229,10 +348,44 @@ impl Engine {
                 .and(p.slice(s![1..]))
                 .and(p.slice(s![0..p.len() - 1]))
                 .apply(|pm, p2, p1| *pm = 0.5 * (p2 + p1) * 1e5);
-            let work = dv.dot(&(p_mean - _P_REF));
+            let p_rel = p_mean - _P_REF;
+            let work = dv.dot(&p_rel);
+            cyl_work.push(work);
             total_work += work;
-            power += work * self.speed / 120.0; // W
+            power += work * self.cycles_per_second(); // W
+            // gas exchange loop of four-stroke engines: exhaust and intake strokes
+            if self.strokes == 4 {
+                Zip::from(&dv)
+                    .and(&p_rel)
+                    .and(a.slice(s![0..a.len() - 1]))
+                    .apply(|dv, p, a| {
+                        if *a >= 180.0 && *a < 540.0 {
+                            pumping_work += dv * p
+                        }
+                    });
+            }
+            let i_peak = p
+                .iter()
+                .enumerate()
+                .fold(0, |i_max, (i, pi)| if *pi > p[i_max] { i } else { i_max });
+            if !cyl.is_deactivated() {
+                peak_press += p[i_peak];
+            }
+            cyl_peak.push((p[i_peak], a[i_peak]));
+            // pressure at the sample closest to the start of the closed phase, across the end of the cycle
+            let cycle_deg = self.cycle_angle().to_degrees();
+            let trapping = cyl.trapping_angle();
+            let distance = |angle: f64| {
+                let d = (angle - trapping).rem_euclid(cycle_deg);
+                d.min(cycle_deg - d)
+            };
+            let i_trap = a
+                .iter()
+                .enumerate()
+                .fold(0, |i_min, (i, ai)| if distance(*ai) < distance(a[i_min]) { i } else { i_min });
+            trapping_press.push(p[i_trap]);
         }
+        let gross_work = total_work - pumping_work;
 
         let mut total_fuel_mass = 0.0f64;
         self.cylinders()
@@ -240,28 +393,102 @@ impl Engine {
             .for_each(|c| total_fuel_mass += c.fuel_mass());
         // println!("fuel mass: {:.4} [mg]", total_fuel_mass*1e6);
 
+        // deactivated cylinders take no charge
+        let active: Vec<&Cylinder> = self.cylinders().iter().filter(|c| !c.is_deactivated()).collect();
+        let num_active = active.len();
+        // no active cylinder leaves the averages at zero
+        peak_press /= num_active.max(1) as f64;
+
         let mut mass: f64 = 0.0;
-        self.cylinders()
-            .iter()
-            .for_each(|c| mass += c.closed_phase_mass());
+        active.iter().for_each(|c| mass += c.closed_phase_mass());
         let total_disp = self.displacement * (self.cylinders().len() as f64) * 1e-6;
         let r_ref = 287.0;
         let vol_effic = 100.0 * mass / (_P_REF * total_disp / (r_ref * _T_REF));
 
         let mut residual_mass: f64 = 0.0;
-        self.cylinders()
+        active.iter().for_each(|c| residual_mass += c.residual_mass_frac());
+        residual_mass = 100.0 * residual_mass / num_active.max(1) as f64;
+
+        let mut egr_rate: f64 = 0.0;
+        active.iter().for_each(|c| egr_rate += c.egr_rate());
+        egr_rate = 100.0 * egr_rate / num_active.max(1) as f64;
+
+        // fresh air per cycle: trapped mass without residual gas, recirculated exhaust gas and fuel
+        let mut air_mass: f64 = 0.0;
+        active.iter().for_each(|c| air_mass += c.trapped_air() * (1.0 - c.egr_rate()));
+        let air_flow = air_mass * self.cycles_per_second() * 3600.0; // kg/h
+
+        let mut trapping_effic: f64 = 0.0;
+        active.iter().for_each(|c| trapping_effic += c.trapping_effic());
+        trapping_effic = 100.0 * trapping_effic / num_active.max(1) as f64;
+
+        // leakage past the piston rings
+        let mut blow_by: f64 = 0.0;
+        self.cylinders().iter().for_each(|c| blow_by += c.blow_by_mass());
+        let blow_by = blow_by * self.cycles_per_second() * 3600.0; // kg/h
+
+        // cylinder-to-cylinder breakdown
+        let cyl_perform: Vec<CylinderPerformance> = self
+            .cylinders()
             .iter()
-            .for_each(|c| residual_mass += c.residual_mass_frac());
-        residual_mass = 100.0 * residual_mass / (self.cylinders().len() as f64);
+            .zip(cyl_work.iter().zip(cyl_peak.iter()).zip(trapping_press.iter()))
+            .map(|(c, ((work, (peak, peak_angle)), trap_press))| CylinderPerformance {
+                name: c.name().to_string(),
+                imep: work / c.displacement() * 1e-5,
+                trapped_mass: c.closed_phase_mass() * 1e6,
+                vol_effic: 100.0 * c.closed_phase_mass() / (_P_REF * c.displacement() / (r_ref * _T_REF)),
+                residual_mass: 100.0 * c.residual_mass_frac(),
+                peak_pressure: *peak,
+                peak_pressure_angle: *peak_angle,
+                eff_compression_ratio: c.effective_compression_ratio(),
+                eff_expansion_ratio: c.effective_expansion_ratio(),
+                eff_displacement: c.effective_displacement() * 1e6,
+                trapping_pressure: *trap_press,
+                blow_by: c.blow_by_mass() * 1e6,
+            })
+            .collect();
+        let is_active: Vec<bool> = self.cylinders().iter().map(|c| !c.is_deactivated()).collect();
+        let active_perform = cyl_perform.iter().zip(is_active.iter()).filter(|(_, a)| **a).map(|(c, _)| c);
+        let cyl_imep: Vec<f64> = active_perform.clone().map(|c| c.imep).collect();
+        let cyl_mass: Vec<f64> = active_perform.map(|c| c.trapped_mass).collect();
+        let imep_cov = 100.0 * relative(std_deviation(&cyl_imep), mean(&cyl_imep).abs());
+        let imep_spread = spread(&cyl_imep);
+        let trapped_mass_spread = 100.0 * relative(spread(&cyl_mass), mean(&cyl_mass));
 
         let torque = power / (self.speed * PI / 30.0);
-        let imep = total_work / self.displacement * 10.0; // displ is in cm³
-        let thermal_effic: f64;
-        if let Some(injector) = &self.injector {
-            thermal_effic = 100.0 * total_work / (total_fuel_mass * injector.fuel().lhv());
+        // engine mean effective pressures: the work of all cylinders over the displacement of all cylinders,
+        // so deactivated cylinders lower them with their displacement and pumping work
+        let imep = total_work / total_disp * 1e-5;
+        let gross_imep = gross_work / total_disp * 1e-5;
+        let pmep = -pumping_work / total_disp * 1e-5;
+        // all cylinders may be deactivated
+        let thermal_effic = match &self.injector {
+            Some(injector) if total_fuel_mass > 0.0 => 100.0 * total_work / (total_fuel_mass * injector.fuel().lhv()),
+            _ => 0.0,
+        };
+
+        // brake output: indicated less mechanical friction and the power drawn by accessories driven by
+        // the crankshaft, i.e. superchargers
+        let fmep = match &self.friction {
+            Some(friction) => friction.fmep(self.speed, peak_press, self.mean_piston_speed()),
+            None => 0.0,
+        };
+        let parasitic_mep = parasitic_power / self.cycles_per_second() / total_disp * 1e-5;
+        let bmep = imep - fmep - parasitic_mep;
+        let brake_power = power - fmep * 1e5 * total_disp * self.cycles_per_second() - parasitic_power;
+        let brake_torque = brake_power / (self.speed * PI / 30.0);
+        let fuel_flow = total_fuel_mass * self.cycles_per_second() * 3.6e6; // g/h
+        let bsfc = if self.injector.is_some() && brake_power > 0.0 {
+            fuel_flow / (brake_power * 1e-3) // g/kWh
         } else {
-            thermal_effic = 0.0
-        }
+            0.0
+        };
+        let brake_effic = match &self.injector {
+            Some(injector) if total_fuel_mass > 0.0 => {
+                100.0 * brake_power / (self.cycles_per_second() * total_fuel_mass * injector.fuel().lhv())
+            }
+            _ => 0.0,
+        };
 
         self.operat_param.speed.push(self.speed);
         self.operat_param.power.push(power);
@@ -270,6 +497,25 @@ impl Engine {
         self.operat_param.thermal_effic.push(thermal_effic);
         self.operat_param.vol_effic.push(vol_effic);
         self.operat_param.residual_mass.push(residual_mass);
+        self.operat_param.air_flow.push(air_flow);
+        self.operat_param.egr_rate.push(egr_rate);
+        self.operat_param.parasitic_power.push(parasitic_power);
+        self.operat_param.trapping_effic.push(trapping_effic);
+        self.operat_param.gross_imep.push(gross_imep);
+        self.operat_param.pmep.push(pmep);
+        self.operat_param.peak_pressure.push(peak_press);
+        self.operat_param.fmep.push(fmep);
+        self.operat_param.bmep.push(bmep);
+        self.operat_param.brake_power.push(brake_power);
+        self.operat_param.brake_torque.push(brake_torque);
+        self.operat_param.bsfc.push(bsfc);
+        self.operat_param.imep_cov.push(imep_cov);
+        self.operat_param.imep_spread.push(imep_spread);
+        self.operat_param.trapped_mass_spread.push(trapped_mass_spread);
+        self.operat_param.cylinders.push(cyl_perform);
+        self.operat_param.brake_effic.push(brake_effic);
+        self.operat_param.active_cylinders.push(num_active as f64);
+        self.operat_param.blow_by.push(blow_by);
     }
 
     pub fn write_performance_to(&self, file_name: &str) {
@@ -277,7 +523,7 @@ impl Engine {
         let mut data: Vec<String> = Vec::new();
         for i in 0..self.operat_param.speed.len() {
             data.push(format!(
-                "{:.1}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\n",
+                "{:.1}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.4}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.3}\t{:.2}\t{:.3}\t{:.2}\t{:.2}\t{:.2}\t{:.1}\t{:.2}\t{:.3}\t{:.2}\t{:.2}\t{:.0}\t{:.4}\n",
                 op.speed[i],
                 op.power[i],
                 op.torque[i],
@@ -285,14 +531,62 @@ impl Engine {
                 op.thermal_effic[i],
                 op.vol_effic[i],
                 op.residual_mass[i],
+                op.air_flow[i],
+                op.egr_rate[i],
+                op.parasitic_power[i],
+                op.trapping_effic[i],
+                op.gross_imep[i],
+                op.pmep[i],
+                op.peak_pressure[i],
+                op.fmep[i],
+                op.bmep[i],
+                op.brake_power[i],
+                op.brake_torque[i],
+                op.bsfc[i],
+                op.imep_cov[i],
+                op.imep_spread[i],
+                op.trapped_mass_spread[i],
+                op.brake_effic[i],
+                op.active_cylinders[i],
+                op.blow_by[i],
             ));
         }
-        let header = "Speed [RPM]\tPower [W]\tTorque [Nm]\tIMEP [bar]\tEfficiency [%]\tVolumetric effic [%]\tResidual mass [%]".to_string();
+        let header = "Speed [RPM]\tPower [W]\tTorque [Nm]\tIMEP [bar]\tEfficiency [%]\tVolumetric effic [%]\tResidual mass [%]\tAir flow [kg/h]\tEGR rate [%]\tParasitic power [W]\tTrapping effic [%]\tGross IMEP [bar]\tPMEP [bar]\tPeak pressure [bar]\tFMEP [bar]\tBMEP [bar]\tBrake power [W]\tBrake torque [Nm]\tBSFC [g/kWh]\tIMEP cyl-to-cyl COV [%]\tIMEP spread [bar]\tTrapped mass spread [%]\tBrake effic [%]\tActive cylinders\tBlow-by [kg/h]".to_string();
         let mut file = std::fs::File::create(file_name).expect("Error opening writing file");
         write!(file, "{}\n", header).expect("Unable to write data");
         write!(file, "{}", data.join("")).expect("Unable to write data");
     }
 
+    /// Writes the performance of each cylinder, one line per cylinder and simulated speed
+    pub fn write_cylinders_performance_to(&self, file_name: &str) {
+        let op = &self.operat_param;
+        let mut data: Vec<String> = Vec::new();
+        for (speed, cylinders) in op.speed.iter().zip(op.cylinders.iter()) {
+            for c in cylinders.iter() {
+                data.push(format!(
+                    "{:.1}\t{}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.1}\t{:.2}\t{:.2}\t{:.2}\t{:.3}\t{:.3}\n",
+                    speed,
+                    c.name,
+                    c.imep,
+                    c.trapped_mass,
+                    c.vol_effic,
+                    c.residual_mass,
+                    c.peak_pressure,
+                    c.peak_pressure_angle,
+                    c.eff_compression_ratio,
+                    c.eff_expansion_ratio,
+                    c.eff_displacement,
+                    c.trapping_pressure,
+                    c.blow_by,
+                ));
+            }
+        }
+        let header = "Speed [RPM]\tCylinder\tIMEP [bar]\tTrapped mass [mg]\tVolumetric effic [%]\tResidual mass [%]\tPeak pressure [bar]\tPeak pressure angle [CA-deg]\tEffective compression ratio\tEffective expansion ratio\tEffective displacement [cm³]\tTrapping pressure [bar]\tBlow-by [mg]".to_string();
+        let mut file = std::fs::File::create(file_name).expect("Error opening writing file");
+        writeln!(file, "{}", header).expect("Unable to write data");
+        write!(file, "{}", data.join("")).expect("Unable to write data");
+    }
+
     pub fn update_cylinders_flow_ratio(&mut self, flow_ratio: Vec<Vec<(&str, &FlowRatio)>>) {
         for (cylinder, flow) in self.cylinders.iter_mut().zip(flow_ratio) {
             cylinder.update_flow_ratio(flow);
@@ -341,16 +635,227 @@ impl Engine {
         self.combustion = comb.clone();
     }
 
+    /// Set the initial of combustion, `ign_angle` in [CA-deg], and the Wiebe function of the combustion model of all cylinders
+    pub fn set_wiebe_function(&mut self, ign_angle: f64, wiebe: WiebeFunction) -> Result<(), String> {
+        self.combustion.set_wiebe_function(ign_angle, wiebe)?;
+        let comb = self.combustion.clone();
+        self.cylinders
+            .iter_mut()
+            .for_each(|c| c.set_combustion_model(comb.clone()));
+        Ok(())
+    }
+
+    /// Returns the mechanical friction model, if any
+    pub fn friction(&self) -> Option<&Friction> {
+        self.friction.as_ref()
+    }
+
+    /// Set the mechanical friction model. Without it, brake and indicated quantities are equal
+    pub fn set_friction(&mut self, friction: Option<Friction>) {
+        self.friction = friction;
+    }
+
+    /// Returns the mean piston speed in m/s
+    pub fn mean_piston_speed(&self) -> f64 {
+        let area = 0.25 * PI * (self.bore * 1e-3).powi(2);
+        let stroke = self.displacement * 1e-6 / area;
+        2.0 * stroke * self.speed / 60.0
+    }
+
+    /// Returns the variable valve actuation of the intake valves
+    pub fn intake_actuation(&self) -> ValveActuation {
+        self.intake_actuation
+    }
+
+    /// Returns the variable valve actuation of the exhaust valves
+    pub fn exhaust_actuation(&self) -> ValveActuation {
+        self.exhaust_actuation
+    }
+
+    /// Set the variable valve actuation, i.e. the cam phase, of the intake valves of all cylinders
+    pub fn set_intake_actuation(&mut self, actuation: ValveActuation) {
+        match self.set_actuation_of_valves(true, actuation) {
+            Ok(()) => self.intake_actuation = actuation,
+            Err(err) => {
+                println!("Error at Engine::set_intake_actuation():\n {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Set the variable valve actuation, i.e. the cam phase, of the exhaust valves of all cylinders
+    pub fn set_exhaust_actuation(&mut self, actuation: ValveActuation) {
+        match self.set_actuation_of_valves(false, actuation) {
+            Ok(()) => self.exhaust_actuation = actuation,
+            Err(err) => {
+                println!("Error at Engine::set_exhaust_actuation():\n {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // applies `actuation` to the intake or exhaust valves and updates the valve events of their cylinders.
+    // The valves of deactivated cylinders are kept shut
+    fn set_actuation_of_valves(&mut self, intake: bool, actuation: ValveActuation) -> Result<(), String> {
+        let mut found = false;
+        for i in 0..self.valves.len() {
+            if self.intake_valves.contains(&i) != intake {
+                continue;
+            }
+            found = true;
+            let cyl_name = self.valves[i].connecting()[0].clone();
+            if self.cylinders.iter().any(|c| c.name() == cyl_name && c.is_deactivated()) {
+                continue;
+            }
+            self.set_actuation_of_valve(i, actuation)?;
+        }
+        if !found {
+            let kind = if intake { "intake" } else { "exhaust" };
+            return Err(format!("engine has no {} valves", kind));
+        }
+        Ok(())
+    }
+
+    // applies `actuation` to the valve `i` and updates the valve events of its cylinder
+    fn set_actuation_of_valve(&mut self, i: usize, actuation: ValveActuation) -> Result<(), String> {
+        let valve = &mut self.valves[i];
+        valve.set_actuation(actuation)?;
+        let cyl_name = &valve.connecting()[0];
+        if let Some(cyl) = self.cylinders.iter_mut().find(|c| c.name() == cyl_name) {
+            cyl.set_valve_events(valve.name(), valve.opening_angle(), valve.closing_angle())?;
+        }
+        Ok(())
+    }
+
+    /// Deactivates or reactivates cylinder `cyl`. The valves of a deactivated cylinder are held shut,
+    /// it has no injection or combustion and its trapped gas works as a spring
+    pub fn set_deactivated_of(&mut self, cyl: &str, deactivated: bool) {
+        let cylinder = match self.cylinders.iter_mut().find(|c| c.name() == cyl) {
+            Some(c) => c,
+            None => {
+                println!("Error at Engine::set_deactivated_of()");
+                println!(" \"{}\" was not found", cyl);
+                std::process::exit(1);
+            }
+        };
+        cylinder.set_deactivated(deactivated);
+        for i in 0..self.valves.len() {
+            if self.valves[i].connecting()[0] != cyl {
+                continue;
+            }
+            let actuation = if deactivated {
+                ValveActuation::deactivated()
+            } else if self.intake_valves.contains(&i) {
+                self.intake_actuation
+            } else {
+                self.exhaust_actuation
+            };
+            if let Err(err) = self.set_actuation_of_valve(i, actuation) {
+                println!("Error at Engine::set_deactivated_of():\n {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Returns the number of active cylinders
+    pub fn active_cylinders(&self) -> usize {
+        self.cylinders.iter().filter(|c| !c.is_deactivated()).count()
+    }
+
+    /// Set the scavenging model of all cylinders
+    pub fn set_scavenging(&mut self, scavenging: Scavenging) {
+        self.cylinders
+            .iter_mut()
+            .for_each(|c| c.set_scavenging(scavenging));
+    }
+
+    /// Set the wall temperature, in K, of cylinder `cyl`
+    pub fn set_wall_temperature_of(&mut self, cyl: &str, temp: f64) {
+        if let Some(cylinder) = self.cylinders.iter_mut().find(|c| c.name() == cyl) {
+            cylinder.set_wall_temperature(temp);
+        } else {
+            println!("Error at Engine::set_wall_temperature_of()");
+            println!(" \"{}\" was not found", cyl);
+            std::process::exit(1);
+        }
+    }
+
+    /// Set the multiplier of the heat transfer coefficient of cylinder `cyl`
+    pub fn set_heat_transfer_multiplier_of(&mut self, cyl: &str, multiplier: f64) {
+        if let Some(cylinder) = self.cylinders.iter_mut().find(|c| c.name() == cyl) {
+            cylinder.set_heat_transfer_multiplier(multiplier);
+        } else {
+            println!("Error at Engine::set_heat_transfer_multiplier_of()");
+            println!(" \"{}\" was not found", cyl);
+            std::process::exit(1);
+        }
+    }
+
+    /// Set the blow-by model of cylinder `cyl`, see [`BlowBy`](../../zero_dim/cylinder/struct.BlowBy.html). `None` seals it
+    pub fn set_blow_by_of(&mut self, cyl: &str, blow_by: Option<BlowBy>) {
+        if let Some(cylinder) = self.cylinders.iter_mut().find(|c| c.name() == cyl) {
+            cylinder.set_blow_by(blow_by);
+        } else {
+            println!("Error at Engine::set_blow_by_of()");
+            println!(" \"{}\" was not found", cyl);
+            std::process::exit(1);
+        }
+    }
+
+    /// Set the crevice model of cylinder `cyl`, see [`Crevice`](../../zero_dim/cylinder/struct.Crevice.html). `None` removes it
+    pub fn set_crevice_of(&mut self, cyl: &str, crevice: Option<Crevice>) {
+        if let Some(cylinder) = self.cylinders.iter_mut().find(|c| c.name() == cyl) {
+            cylinder.set_crevice(crevice);
+        } else {
+            println!("Error at Engine::set_crevice_of()");
+            println!(" \"{}\" was not found", cyl);
+            std::process::exit(1);
+        }
+    }
+
     /// Set injectors relative air-fuel ratio, input between 0 and 1
     pub fn set_air_fuel_ratio(&mut self, afr: f64) {
         if let Some(inj) = &mut self.injector {
             inj.set_air_fuel_ratio(afr);
+            self.cylinders.iter_mut().for_each(|c| c.set_air_fuel_ratio(afr));
         } else {
             println!("Error at Engine:: set_air_fuel_ratio(): Injector does not exist");
             std::process::exit(1);
         }
     }
 
+    /// Set the intake port wall film of the port injectors, see [`WallFilm`](struct.WallFilm.html). `None` removes it
+    pub fn set_wall_film(&mut self, wall_film: Option<WallFilm>) {
+        let result = match &mut self.injector {
+            Some(inj) => inj.set_wall_film(wall_film.clone()).and_then(|_| {
+                self.cylinders
+                    .iter_mut()
+                    .try_for_each(|c| c.set_wall_film(wall_film.clone()))
+            }),
+            None => Err("Injector does not exist".to_string()),
+        };
+        if let Err(err) = result {
+            println!("Error at Engine::set_wall_film():\n {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    /// Set the injection pulses of the direct injectors, see [`InjectionPulse`](struct.InjectionPulse.html)
+    pub fn set_injection_pulses(&mut self, pulses: Vec<InjectionPulse>) {
+        let result = match &mut self.injector {
+            Some(inj) => inj.set_pulses(pulses.clone()).and_then(|_| {
+                self.cylinders
+                    .iter_mut()
+                    .try_for_each(|c| c.set_injection_pulses(pulses.clone()))
+            }),
+            None => Err("Injector does not exist".to_string()),
+        };
+        if let Err(err) = result {
+            println!("Error at Engine::set_injection_pulses():\n {}", err);
+            std::process::exit(1);
+        }
+    }
+
     /// Set if the species inside the cylinder should be storable
     pub fn store_species(&mut self, state: bool) {
         self.cylinders
@@ -368,6 +873,99 @@ impl Engine {
         &self.valves
     }
 
+    /// Returns a reference to the ports in the engine
+    pub fn ports(&self) -> &Vec<Port> {
+        &self.ports
+    }
+
+    /// Returns the number of strokes per cycle: 2 or 4
+    pub fn strokes(&self) -> u8 {
+        self.strokes
+    }
+
+    /// Returns the crank-angle, in radians, of a full cycle: `4*PI` for four-stroke and `2*PI` for two-stroke engines
+    pub fn cycle_angle(&self) -> f64 {
+        PI * f64::from(self.strokes)
+    }
+
+    // crank-angle of the firing TDC of each cylinder: given by `tdc_offset` or evenly spaced by `firing_order`
+    fn read_tdc_offsets(json_engine: &JsonEngine, cycle_deg: f64) -> Result<Vec<f64>, String> {
+        let cylinders = &json_engine.cylinders;
+        let mut offsets: Vec<Option<f64>> = cylinders.iter().map(|c| c.tdc_offset).collect();
+        if offsets.iter().any(|o| o.is_none()) {
+            let firing_order: Vec<f64> = json_engine
+                .firing_order
+                .split('-')
+                .map(|s| {
+                    s.trim().parse().map_err(|err| {
+                        format!("Error at Engine::new \n unable to parse `firing_order` \"{}\" \n{}", s, err)
+                    })
+                })
+                .collect::<Result<_, String>>()?;
+            if firing_order.len() != cylinders.len() {
+                let msg = format!(
+                    "Error at Engine::new 
+            `firing_order` and `cylinders` must have the same length.
+            `firing_order`: {}
+            `cylinder`: {}",
+                    firing_order.len(),
+                    cylinders.len()
+                );
+                return Err(msg);
+            }
+            let num_divisions = firing_order.iter().cloned().fold(0. / 0., f64::max); // gets highest value
+            let division = cycle_deg / num_divisions;
+            // the shorthand keeps its original placement: the cylinder in position `order` starts the simulation at
+            // `180 + (order - 1)*division` CA-deg, i.e. it reaches its firing TDC `(order - 1)*division` before the first cylinder
+            for (offset, order) in offsets.iter_mut().zip(firing_order.iter()) {
+                if offset.is_none() {
+                    *offset = Some((-(order - 1.0) * division).rem_euclid(cycle_deg));
+                }
+            }
+        }
+        let mut tdc_offsets = Vec::new();
+        for (cylinder, offset) in cylinders.iter().zip(offsets) {
+            let offset = offset.unwrap();
+            if !(0.0..=cycle_deg).contains(&offset) {
+                return Err(format!(
+                    "Error at Engine::new \n `tdc_offset` of cylinder `{}` must be between 0 and {} CA-deg: {}",
+                    cylinder.name, cycle_deg, offset
+                ));
+            }
+            tdc_offsets.push(offset);
+        }
+        Ok(tdc_offsets)
+    }
+
+    // engine cycles per second
+    fn cycles_per_second(&self) -> f64 {
+        self.speed / (30.0 * f64::from(self.strokes))
+    }
+
+    // pulses of the .json file: the fuel not assigned to any pulse is split equally between the others
+    fn injection_pulses(json_pulses: &[JsonInjectionPulse]) -> Result<Vec<InjectionPulse>, String> {
+        let assigned: f64 = json_pulses.iter().filter_map(|p| p.fraction).sum();
+        let unassigned = json_pulses.iter().filter(|p| p.fraction.is_none()).count();
+        let mut pulses = Vec::new();
+        for p in json_pulses {
+            let fraction = p.fraction.unwrap_or((1.0 - assigned) / unassigned as f64);
+            pulses.push(match (&p.rate_shape, p.duration) {
+                (Some(shape), _) => {
+                    let shape: Vec<(f64, f64)> = shape.iter().map(|[angle, rate]| (*angle, *rate)).collect();
+                    InjectionPulse::new(p.start, fraction, &shape)?
+                }
+                (None, Some(duration)) => InjectionPulse::rectangular(p.start, duration, fraction)?,
+                (None, None) => {
+                    return Err(format!(
+                        "injection pulse starting at {} CA-deg requires a `duration` or a `rate_shape`",
+                        p.start
+                    ))
+                }
+            });
+        }
+        Ok(pulses)
+    }
+
     /// Returns cylinders bore in mm
     pub fn bore(&self) -> f64 {
         self.bore
@@ -378,6 +976,11 @@ impl Engine {
         self.firing_order.clone()
     }
 
+    /// Returns the crank-angle, in CA-deg, at which each cylinder reaches its firing TDC, in the order of `cylinders()`
+    pub fn tdc_offsets(&self) -> &Vec<f64> {
+        &self.tdc_offsets
+    }
+
     /// Returns cylinders eccentricity in mm
     pub fn eccentricity(&self) -> f64 {
         self.eccentricity
@@ -388,6 +991,11 @@ impl Engine {
         self.combustion.model_name().to_string()
     }
 
+    /// Returns the combustion model
+    pub fn combustion(&self) -> &dyn Combustion {
+        self.combustion.as_ref()
+    }
+
     pub fn injector(&self) -> Option<&Injector> {
         if let Some(inj) = &self.injector {
             Some(inj)
@@ -396,6 +1004,11 @@ impl Engine {
         }
     }
 
+    // pressure, in Pa, of the crankcase element under cylinder `i`, where its blow-by leaks
+    pub(crate) fn set_crankcase_pressure_of(&mut self, i: usize, press: f64) {
+        self.cylinders[i].set_crankcase_pressure(press);
+    }
+
     /// Returns the constant multiplier to change from seconds to crank-angle degrees
     pub fn sec_to_rad(&self) -> f64 {
         self.sec_to_rad
@@ -405,6 +1018,11 @@ impl Engine {
         &self.operat_param
     }
 
+    /// Erases the operational parameters of all simulated speeds
+    pub fn clear_operat_param(&mut self) {
+        self.operat_param = OperationalParameters::new();
+    }
+
     fn reading_json(file_name: &str) -> serde_json::Result<JsonEngine> {
         let json_file = std::fs::read_to_string(file_name).expect("Unable to read file");
         let data: JsonEngine = serde_json::from_str(&json_file)?;
@@ -420,6 +1038,25 @@ pub struct OperationalParameters {
     thermal_effic: Vec<f64>,
     vol_effic: Vec<f64>,
     residual_mass: Vec<f64>,
+    air_flow: Vec<f64>,
+    egr_rate: Vec<f64>,
+    parasitic_power: Vec<f64>,
+    trapping_effic: Vec<f64>,
+    gross_imep: Vec<f64>,
+    pmep: Vec<f64>,
+    peak_pressure: Vec<f64>,
+    fmep: Vec<f64>,
+    bmep: Vec<f64>,
+    brake_power: Vec<f64>,
+    brake_torque: Vec<f64>,
+    bsfc: Vec<f64>,
+    imep_cov: Vec<f64>,
+    imep_spread: Vec<f64>,
+    trapped_mass_spread: Vec<f64>,
+    cylinders: Vec<Vec<CylinderPerformance>>,
+    brake_effic: Vec<f64>,
+    active_cylinders: Vec<f64>,
+    blow_by: Vec<f64>,
 }
 
 impl OperationalParameters {
@@ -432,6 +1069,167 @@ impl OperationalParameters {
             thermal_effic: Vec::new(),
             vol_effic: Vec::new(),
             residual_mass: Vec::new(),
+            air_flow: Vec::new(),
+            egr_rate: Vec::new(),
+            parasitic_power: Vec::new(),
+            trapping_effic: Vec::new(),
+            gross_imep: Vec::new(),
+            pmep: Vec::new(),
+            peak_pressure: Vec::new(),
+            fmep: Vec::new(),
+            bmep: Vec::new(),
+            brake_power: Vec::new(),
+            brake_torque: Vec::new(),
+            bsfc: Vec::new(),
+            imep_cov: Vec::new(),
+            imep_spread: Vec::new(),
+            trapped_mass_spread: Vec::new(),
+            cylinders: Vec::new(),
+            brake_effic: Vec::new(),
+            active_cylinders: Vec::new(),
+            blow_by: Vec::new(),
+        }
+    }
+
+    /// Returns the simulated speeds in RPM
+    pub fn speed(&self) -> &Vec<f64> {
+        &self.speed
+    }
+    /// Returns the indicated power in W of each simulated speed
+    pub fn power(&self) -> &Vec<f64> {
+        &self.power
+    }
+    /// Returns the indicated torque in Nm of each simulated speed
+    pub fn torque(&self) -> &Vec<f64> {
+        &self.torque
+    }
+    /// Returns the IMEP in bar, the indicated work of all cylinders over the total displacement, of each simulated speed
+    pub fn imep(&self) -> &Vec<f64> {
+        &self.imep
+    }
+    /// Returns the indicated thermal efficiency in % of each simulated speed
+    pub fn thermal_effic(&self) -> &Vec<f64> {
+        &self.thermal_effic
+    }
+    /// Returns the volumetric efficiency in % of each simulated speed
+    pub fn vol_effic(&self) -> &Vec<f64> {
+        &self.vol_effic
+    }
+    /// Returns the residual mass fraction in % of each simulated speed
+    pub fn residual_mass(&self) -> &Vec<f64> {
+        &self.residual_mass
+    }
+    /// Returns the fresh air flow in kg/h of each simulated speed
+    pub fn air_flow(&self) -> &Vec<f64> {
+        &self.air_flow
+    }
+    /// Returns the EGR rate in % of each simulated speed
+    pub fn egr_rate(&self) -> &Vec<f64> {
+        &self.egr_rate
+    }
+    /// Returns the power in W drawn by accessories driven by the crankshaft, i.e. superchargers,
+    /// of each simulated speed. It is already subtracted from `power()` and `torque()`
+    pub fn parasitic_power(&self) -> &Vec<f64> {
+        &self.parasitic_power
+    }
+    /// Returns the trapping efficiency in %, the fraction of the delivered charge retained in the cylinders, of each simulated speed
+    pub fn trapping_effic(&self) -> &Vec<f64> {
+        &self.trapping_effic
+    }
+    /// Returns the gross IMEP in bar, from the compression and expansion strokes, of each simulated speed.
+    /// The IMEP is `gross_imep() - pmep()`
+    pub fn gross_imep(&self) -> &Vec<f64> {
+        &self.gross_imep
+    }
+    /// Returns the pumping mean effective pressure in bar, the work spent in the exhaust and intake strokes, of each simulated speed.
+    /// It is positive when the gas exchange absorbs work and zero for two-stroke engines. As the IMEP, it is referred to
+    /// the total displacement
+    pub fn pmep(&self) -> &Vec<f64> {
+        &self.pmep
+    }
+    /// Returns the peak cylinder pressure in bar, averaged over the cylinders, of each simulated speed
+    pub fn peak_pressure(&self) -> &Vec<f64> {
+        &self.peak_pressure
+    }
+    /// Returns the friction mean effective pressure in bar of each simulated speed
+    pub fn fmep(&self) -> &Vec<f64> {
+        &self.fmep
+    }
+    /// Returns the BMEP in bar, `imep() - fmep()` less the mean effective pressure of the `parasitic_power()`, of each simulated speed
+    pub fn bmep(&self) -> &Vec<f64> {
+        &self.bmep
+    }
+    /// Returns the brake power in W, the indicated power less friction and `parasitic_power()`, of each simulated speed
+    pub fn brake_power(&self) -> &Vec<f64> {
+        &self.brake_power
+    }
+    /// Returns the brake torque in Nm of each simulated speed
+    pub fn brake_torque(&self) -> &Vec<f64> {
+        &self.brake_torque
+    }
+    /// Returns the brake specific fuel consumption in g/kWh of each simulated speed. It is zero without injector or brake power
+    pub fn bsfc(&self) -> &Vec<f64> {
+        &self.bsfc
+    }
+    /// Returns the cylinder-to-cylinder coefficient of variation in % of the IMEP, i.e. the variation among the active
+    /// cylinders in the simulated cycle and not the cycle-to-cycle variation, of each simulated speed
+    pub fn imep_cov(&self) -> &Vec<f64> {
+        &self.imep_cov
+    }
+    /// Returns the difference in bar between the highest and lowest IMEP of the cylinders of each simulated speed
+    pub fn imep_spread(&self) -> &Vec<f64> {
+        &self.imep_spread
+    }
+    /// Returns the difference between the highest and lowest trapped mass of the cylinders, in % of their mean, of each simulated speed
+    pub fn trapped_mass_spread(&self) -> &Vec<f64> {
+        &self.trapped_mass_spread
+    }
+    /// Returns the brake thermal efficiency in % of each simulated speed
+    pub fn brake_effic(&self) -> &Vec<f64> {
+        &self.brake_effic
+    }
+    /// Returns the number of active, not deactivated, cylinders of each simulated speed
+    pub fn active_cylinders(&self) -> &Vec<f64> {
+        &self.active_cylinders
+    }
+    /// Returns the blow-by, the mass flow leaking to the crankcase, in kg/h of each simulated speed
+    pub fn blow_by(&self) -> &Vec<f64> {
+        &self.blow_by
+    }
+    /// Returns the performance of each cylinder, in the order of `Engine::cylinders()`, of each simulated speed
+    pub fn cylinders(&self) -> &Vec<Vec<CylinderPerformance>> {
+        &self.cylinders
+    }
+
+    /// Returns the values of the column `header`, named as in the file written by `Engine::write_performance_to()`
+    pub fn column(&self, header: &str) -> Option<&Vec<f64>> {
+        match header {
+            "Speed [RPM]" => Some(&self.speed),
+            "Power [W]" => Some(&self.power),
+            "Torque [Nm]" => Some(&self.torque),
+            "IMEP [bar]" => Some(&self.imep),
+            "Efficiency [%]" => Some(&self.thermal_effic),
+            "Volumetric effic [%]" => Some(&self.vol_effic),
+            "Residual mass [%]" => Some(&self.residual_mass),
+            "Air flow [kg/h]" => Some(&self.air_flow),
+            "EGR rate [%]" => Some(&self.egr_rate),
+            "Parasitic power [W]" => Some(&self.parasitic_power),
+            "Trapping effic [%]" => Some(&self.trapping_effic),
+            "Gross IMEP [bar]" => Some(&self.gross_imep),
+            "PMEP [bar]" => Some(&self.pmep),
+            "Peak pressure [bar]" => Some(&self.peak_pressure),
+            "FMEP [bar]" => Some(&self.fmep),
+            "BMEP [bar]" => Some(&self.bmep),
+            "Brake power [W]" => Some(&self.brake_power),
+            "Brake torque [Nm]" => Some(&self.brake_torque),
+            "BSFC [g/kWh]" => Some(&self.bsfc),
+            "IMEP cyl-to-cyl COV [%]" => Some(&self.imep_cov),
+            "IMEP spread [bar]" => Some(&self.imep_spread),
+            "Trapped mass spread [%]" => Some(&self.trapped_mass_spread),
+            "Brake effic [%]" => Some(&self.brake_effic),
+            "Active cylinders" => Some(&self.active_cylinders),
+            "Blow-by [kg/h]" => Some(&self.blow_by),
+            _ => None,
         }
     }
 }
@@ -448,6 +1246,24 @@ impl std::fmt::Display for OperationalParameters {
             effic [%]:\t {:.2?}
             vol_effic [%]: {:.2?}
             residual_mass [%]: {:.2?}
+            air_flow [kg/h]: {:.3?}
+            egr_rate [%]: {:.2?}
+            parasitic_power [W]: {:.2?}
+            trapping_effic [%]: {:.2?}
+            gross_imep [bar]: {:.2?}
+            pmep [bar]: {:.3?}
+            peak_pressure [bar]: {:.2?}
+            fmep [bar]: {:.3?}
+            bmep [bar]: {:.2?}
+            brake_power [W]: {:.2?}
+            brake_torque [Nm]: {:.2?}
+            bsfc [g/kWh]: {:.1?}
+            imep_cyl_to_cyl_cov [%]: {:.2?}
+            imep_spread [bar]: {:.3?}
+            trapped_mass_spread [%]: {:.2?}
+            brake_effic [%]: {:.2?}
+            active_cylinders: {:.0?}
+            blow_by [kg/h]: {:.4?}
             ",
             self.speed,
             self.power,
@@ -456,12 +1272,245 @@ impl std::fmt::Display for OperationalParameters {
             self.thermal_effic,
             self.vol_effic,
             self.residual_mass,
+            self.air_flow,
+            self.egr_rate,
+            self.parasitic_power,
+            self.trapping_effic,
+            self.gross_imep,
+            self.pmep,
+            self.peak_pressure,
+            self.fmep,
+            self.bmep,
+            self.brake_power,
+            self.brake_torque,
+            self.bsfc,
+            self.imep_cov,
+            self.imep_spread,
+            self.trapped_mass_spread,
+            self.brake_effic,
+            self.active_cylinders,
+            self.blow_by,
         )
     }
 }
+
+/// Performance of a single cylinder in one simulated speed
+#[derive(Debug, Clone)]
+pub struct CylinderPerformance {
+    name: String,
+    imep: f64,                // [bar]
+    trapped_mass: f64,        // [mg]
+    vol_effic: f64,           // [%]
+    residual_mass: f64,       // [%]
+    peak_pressure: f64,       // [bar]
+    peak_pressure_angle: f64, // [CA-deg]
+    eff_compression_ratio: f64,
+    eff_expansion_ratio: f64,
+    eff_displacement: f64,  // [cm³]
+    trapping_pressure: f64, // [bar]
+    blow_by: f64,           // [mg]
+}
+
+impl CylinderPerformance {
+    /// Returns the cylinder name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Returns the IMEP in bar
+    pub fn imep(&self) -> f64 {
+        self.imep
+    }
+    /// Returns the mass trapped in the closed phase in mg
+    pub fn trapped_mass(&self) -> f64 {
+        self.trapped_mass
+    }
+    /// Returns the volumetric efficiency in %
+    pub fn vol_effic(&self) -> f64 {
+        self.vol_effic
+    }
+    /// Returns the residual mass fraction in %
+    pub fn residual_mass(&self) -> f64 {
+        self.residual_mass
+    }
+    /// Returns the peak pressure in bar
+    pub fn peak_pressure(&self) -> f64 {
+        self.peak_pressure
+    }
+    /// Returns the crank-angle of the peak pressure in CA-deg, referred to the firing TDC of the cylinder
+    pub fn peak_pressure_angle(&self) -> f64 {
+        self.peak_pressure_angle
+    }
+    /// Returns the effective compression ratio, from the cylinder volume at the start of the closed phase,
+    /// i.e. the intake valve closing
+    pub fn eff_compression_ratio(&self) -> f64 {
+        self.eff_compression_ratio
+    }
+    /// Returns the effective expansion ratio, from the cylinder volume at the exhaust opening
+    pub fn eff_expansion_ratio(&self) -> f64 {
+        self.eff_expansion_ratio
+    }
+    /// Returns the swept volume, in cm³, from top-dead-center to the start of the closed phase
+    pub fn eff_displacement(&self) -> f64 {
+        self.eff_displacement
+    }
+    /// Returns the pressure in bar at the start of the closed phase, i.e. the intake valve closing
+    pub fn trapping_pressure(&self) -> f64 {
+        self.trapping_pressure
+    }
+    /// Returns the mass, in mg, leaked to the crankcase in one cycle
+    pub fn blow_by(&self) -> f64 {
+        self.blow_by
+    }
+}
+
+// the statistics of an empty slice are zero
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn std_deviation(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mean = mean(values);
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt()
+}
+
+fn spread(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let max = values.iter().cloned().fold(f64::MIN, f64::max);
+    let min = values.iter().cloned().fold(f64::MAX, f64::min);
+    max - min
+}
+
+// `value` over `reference`, or zero if `reference` is zero
+fn relative(value: f64, reference: f64) -> f64 {
+    if reference == 0.0 {
+        0.0
+    } else {
+        value / reference
+    }
+}
+
+/// Mechanical friction model of the engine, giving the friction mean effective pressure (FMEP)
+#[derive(Debug, Clone)]
+pub enum Friction {
+    /// Chen-Flynn correlation: `FMEP = a + b*p_max + c*u_p + d*u_p²`, where FMEP and the peak cylinder pressure
+    /// `p_max` are in bar and the mean piston speed `u_p` is in m/s
+    ChenFlynn { a: f64, b: f64, c: f64, d: f64 },
+    /// Measured FMEP against engine speed and peak cylinder pressure
+    Map(FrictionMap),
+}
+
+impl Friction {
+    fn from_json(json: &JsonFriction) -> Result<Friction, String> {
+        match json.model.as_str() {
+            "Chen-Flynn" => match (json.a, json.b, json.c, json.d) {
+                (Some(a), Some(b), Some(c), Some(d)) => Ok(Friction::ChenFlynn { a, b, c, d }),
+                _ => Err("friction model \"Chen-Flynn\" requires `a`, `b`, `c` and `d`".to_string()),
+            },
+            "map" => match (&json.speed, &json.peak_pressure, &json.fmep) {
+                (Some(speed), Some(peak_press), Some(fmep)) => {
+                    if fmep.len() != speed.len() || fmep.iter().any(|r| r.len() != peak_press.len()) {
+                        return Err(format!(
+                            "friction map `fmep` must have {} rows (speeds) and {} columns (peak pressures)",
+                            speed.len(),
+                            peak_press.len()
+                        ));
+                    }
+                    let flat: Vec<f64> = fmep.iter().flatten().copied().collect();
+                    let fmep = Array2::from_shape_vec((speed.len(), peak_press.len()), flat).unwrap();
+                    Ok(Friction::Map(FrictionMap::new(speed, peak_press, fmep)?))
+                }
+                _ => Err("friction model \"map\" requires `speed`, `peak_pressure` and `fmep`".to_string()),
+            },
+            model => Err(format!(
+                "unknown friction model \"{}\". Options: \"Chen-Flynn\" or \"map\"",
+                model
+            )),
+        }
+    }
+
+    /// Returns the FMEP in bar at `speed`, in RPM, peak cylinder pressure `peak_press`, in bar, and mean piston speed `piston_speed`, in m/s
+    pub fn fmep(&self, speed: f64, peak_press: f64, piston_speed: f64) -> f64 {
+        match self {
+            Friction::ChenFlynn { a, b, c, d } => a + b * peak_press + c * piston_speed + d * piston_speed.powi(2),
+            Friction::Map(map) => map.fmep(speed, peak_press),
+        }
+    }
+}
+
+/// FMEP tabulated against engine speed and peak cylinder pressure.
+/// The map is interpolated bilinearly and is constant beyond its limits.
+#[derive(Debug, Clone)]
+pub struct FrictionMap {
+    speed: Vec<f64>,      // [RPM]
+    peak_press: Vec<f64>, // [bar]
+    fmep: Array2<f64>,    // [bar]
+}
+
+impl FrictionMap {
+    /// `speed` in RPM and `peak_press` in bar must be increasing. `fmep`, in bar, has one row per speed
+    /// and one column per peak pressure.
+    pub fn new(speed: &[f64], peak_press: &[f64], fmep: Array2<f64>) -> Result<FrictionMap, String> {
+        if speed.is_empty() || peak_press.is_empty() {
+            return Err("friction map must have at least one speed and one peak pressure".to_string());
+        }
+        if speed.windows(2).any(|w| w[1] <= w[0]) || peak_press.windows(2).any(|w| w[1] <= w[0]) {
+            return Err("friction map speeds and peak pressures must be increasing".to_string());
+        }
+        if fmep.dim() != (speed.len(), peak_press.len()) {
+            return Err(format!(
+                "friction map `fmep` must have {} rows (speeds) and {} columns (peak pressures)",
+                speed.len(),
+                peak_press.len()
+            ));
+        }
+        if fmep.iter().any(|f| *f < 0.0) {
+            return Err("friction map `fmep` cannot be negative".to_string());
+        }
+        Ok(FrictionMap {
+            speed: speed.to_vec(),
+            peak_press: peak_press.to_vec(),
+            fmep,
+        })
+    }
+
+    /// Returns the FMEP in bar at `speed`, in RPM, and peak cylinder pressure `peak_press`, in bar
+    pub fn fmep(&self, speed: f64, peak_press: f64) -> f64 {
+        let (i, fi) = FrictionMap::bracket(&self.speed, speed);
+        let (j, fj) = FrictionMap::bracket(&self.peak_press, peak_press);
+        let i1 = (i + 1).min(self.speed.len() - 1);
+        let j1 = (j + 1).min(self.peak_press.len() - 1);
+        let f = &self.fmep;
+        let low = f[[i, j]] + fj * (f[[i, j1]] - f[[i, j]]);
+        let high = f[[i1, j]] + fj * (f[[i1, j1]] - f[[i1, j]]);
+        low + fi * (high - low)
+    }
+
+    // index of the lower point and the interpolation fraction, clamped to the table
+    fn bracket(points: &[f64], value: f64) -> (usize, f64) {
+        let last = points.len() - 1;
+        if last == 0 || value <= points[0] {
+            return (0, 0.0);
+        }
+        if value >= points[last] {
+            return (last, 0.0);
+        }
+        let i = points.iter().position(|p| *p > value).unwrap() - 1;
+        (i, (value - points[i]) / (points[i + 1] - points[i]))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Fuel {
     name: String,
+    species: Vec<(String, f64)>, // species and their mole fractions
     mole_weight: f64,
     air_moles: f64,
     lhv: f64,
@@ -474,42 +1523,108 @@ pub struct Fuel {
 }
 
 impl Fuel {
-    fn new(fuel: &JsonFuel, gas: &Gas) -> Fuel {
-        let mole_weight = gas.mole_weight_of(&fuel.name);
-        let atoms = gas.atoms_of(&fuel.name);
-        let lhv = match fuel.lhv {
-            Some(lhv) => lhv,
-            None => Fuel::calc_low_heat_value(&fuel.name),
+    fn new(fuel: &JsonFuel, gas: &Gas) -> Result<Fuel, String> {
+        // a single specie is a blend of one component
+        let single = [JsonFuelComponent {
+            specie: fuel.name.clone(),
+            fraction: 1.0,
+            lhv: None,
+            heat_vap: None,
+        }];
+        let components: &[JsonFuelComponent] = if fuel.components.is_empty() {
+            &single
+        } else {
+            &fuel.components
         };
+        for c in components.iter() {
+            if !gas.contains_specie(&c.specie) {
+                return Err(format!(
+                    "the gas added to the cylinder does not contain specie \"{}\"",
+                    c.specie
+                ));
+            }
+            if c.fraction < 0.0 {
+                return Err(format!("fraction of fuel component \"{}\" cannot be negative", c.specie));
+            }
+        }
+        let total: f64 = components.iter().map(|c| c.fraction).sum();
+        if (total - 1.0).abs() > 1e-3 {
+            return Err(format!("the fractions of fuel \"{}\" must add up to 1.0: {}", fuel.name, total));
+        }
 
-        let heat_vap = match fuel.heat_vap {
-            Some(h_vap) => h_vap,
-            None => 0.0,
+        // mole and mass fractions of the components
+        let weights: Vec<f64> = components.iter().map(|c| gas.mole_weight_of(&c.specie)).collect();
+        let (mole_frac, mass_frac): (Vec<f64>, Vec<f64>) = match fuel.basis.as_deref() {
+            None | Some("mole") => {
+                let mole_weight: f64 = components.iter().zip(weights.iter()).map(|(c, w)| c.fraction * w).sum();
+                components
+                    .iter()
+                    .zip(weights.iter())
+                    .map(|(c, w)| (c.fraction / total, c.fraction * w / mole_weight))
+                    .unzip()
+            }
+            Some("mass") => {
+                let moles: f64 = components.iter().zip(weights.iter()).map(|(c, w)| c.fraction / w).sum();
+                components
+                    .iter()
+                    .zip(weights.iter())
+                    .map(|(c, w)| (c.fraction / w / moles, c.fraction / total))
+                    .unzip()
+            }
+            Some(basis) => return Err(format!("fuel basis must be \"mole\" or \"mass\": \"{}\"", basis)),
         };
 
-        let c = match atoms.get("C") {
-            Some(x) => *x,
-            None => 0.0,
-        };
-        let h = match atoms.get("H") {
-            Some(x) => *x,
-            None => 0.0,
-        };
-        let o = match atoms.get("O") {
-            Some(x) => *x,
-            None => 0.0,
+        // properties per mole of blend
+        let mole_weight: f64 = mole_frac.iter().zip(weights.iter()).map(|(x, w)| x * w).sum();
+        let atom = |c: &JsonFuelComponent, name: &str| -> f64 { *gas.atoms_of(&c.specie).get(name).unwrap_or(&0.0) };
+        let count = |name: &str| -> f64 {
+            components.iter().zip(mole_frac.iter()).map(|(c, x)| x * atom(c, name)).sum()
         };
+        let c = count("C");
+        let h = count("H");
+        let o = count("O");
+        let n = count("N");
 
-        let n = match atoms.get("N") {
-            Some(x) => *x,
-            None => 0.0,
+        // properties per kg of blend
+        let lhv = match fuel.lhv {
+            Some(lhv) => lhv,
+            None => {
+                let mut lhv = 0.0;
+                for (comp, y) in components.iter().zip(mass_frac.iter()) {
+                    let comp_lhv = match comp.lhv.or_else(|| Fuel::known_low_heat_value(&comp.specie)) {
+                        Some(lhv) => lhv,
+                        None => Fuel::calc_low_heat_value(&comp.specie, gas)?,
+                    };
+                    lhv += y * comp_lhv;
+                }
+                lhv
+            }
+        };
+        let heat_vap = match fuel.heat_vap {
+            Some(h_vap) => h_vap,
+            None if fuel.state == "gas" => 0.0,
+            None => components
+                .iter()
+                .zip(mass_frac.iter())
+                .map(|(comp, y)| y * comp.heat_vap.or_else(|| Fuel::known_heat_vap(&comp.specie)).unwrap_or(0.0))
+                .sum(),
         };
 
         let air_moles = c + 0.25 * h - 0.5 * o;
-        let comp = format!("{}:1.0", fuel.name);
+        let species: Vec<(String, f64)> = components
+            .iter()
+            .zip(mole_frac.iter())
+            .map(|(comp, x)| (comp.specie.clone(), *x))
+            .collect();
+        let comp = species
+            .iter()
+            .map(|(s, x)| format!("{}:{}", s, x))
+            .collect::<Vec<String>>()
+            .join(", ");
 
-        Fuel {
+        Ok(Fuel {
             name: fuel.name.to_string(),
+            species,
             mole_weight,
             air_moles,
             lhv,
@@ -519,37 +1634,86 @@ impl Fuel {
             hydrogen: h,
             oxigen: o,
             nitrogen: n,
+        })
+    }
+
+    // low heat value, in J/kg, of known fuels
+    fn known_low_heat_value(name: &str) -> Option<f64> {
+        match name {
+            "CH4" => Some(50e06),
+            "C2H5OH" => Some(25.858e06),
+            "C8H18" => Some(44.651e06),
+            _ => None,
         }
     }
 
-    fn calc_low_heat_value(name: &str) -> f64 {
-        if name == "CH4" {
-            50e06
-        } else if name == "C2H5OH" {
-            25.858e06
-        } else if name == "C8H18" {
-            44.651e06
-        } else {
-            println!("Error! Fuel `{}` has no low heat value stored", name);
-            std::process::exit(1);
+    // heat of vaporization, in J/kg, of known liquid fuels at 25 °C
+    fn known_heat_vap(name: &str) -> Option<f64> {
+        match name {
+            "C8H18" => Some(307e3),
+            "C7H16" => Some(365e3),
+            "C7H8" => Some(412e3),
+            "C6H14" => Some(366e3),
+            "C2H5OH" => Some(918e3),
+            "CH3OH" => Some(1168e3),
+            _ => None,
         }
     }
 
+    // low heat value, in J/kg, from the enthalpies of formation of the gas file:
+    // CcHhOoNn + (c + h/4 - o/2) O2 -> c CO2 + h/2 H2O + n/2 N2
+    fn calc_low_heat_value(name: &str, gas: &Gas) -> Result<f64, String> {
+        for s in ["O2", "CO2", "H2O", "N2"].iter() {
+            if !gas.contains_specie(s) {
+                return Err(format!(
+                    "low heat value of \"{}\" cannot be calculated: specie \"{}\" not found in `gas`",
+                    name, s
+                ));
+            }
+        }
+        let enthalpy = |specie: &str| -> f64 {
+            let mut g = gas.clone();
+            g.TPX(298.15, _P_REF, &format!("{}:1.0", specie));
+            g.h() * g.M() // [J/kmol]
+        };
+        let atoms = gas.atoms_of(name);
+        let atom = |a: &str| -> f64 { *atoms.get(a).unwrap_or(&0.0) };
+        let o2 = atom("C") + 0.25 * atom("H") - 0.5 * atom("O");
+        let reactants = enthalpy(name) + o2 * enthalpy("O2");
+        let products = atom("C") * enthalpy("CO2") + 0.5 * atom("H") * enthalpy("H2O") + 0.5 * atom("N") * enthalpy("N2");
+        Ok((reactants - products) / gas.mole_weight_of(name))
+    }
+
     pub fn name<'a>(&'a self) -> &'a str {
         &self.name
     }
+    /// Returns the species of the fuel and their mole fractions
+    pub fn species(&self) -> &Vec<(String, f64)> {
+        &self.species
+    }
+    /// Returns the mass fractions of the fuel with the species of `gas`
+    pub fn mass_frac_in(&self, gas: &Gas) -> Array1<f64> {
+        let masses = gas.break_str_into_X_array(&self.comp) * gas.mole_weight();
+        let total = masses.sum();
+        masses / total
+    }
+    /// Returns the molar mass of the fuel in kg/kmol
     pub fn mole_weight(&self) -> f64 {
         self.mole_weight
     }
+    /// Returns the moles of O2 per mole of fuel of the stoichiometric combustion
     pub fn air_moles(&self) -> f64 {
         self.air_moles
     }
+    /// Returns the low heat value in J/kg
     pub fn lhv(&self) -> f64 {
         self.lhv
     }
+    /// Returns the heat of vaporization in J/kg
     pub fn heat_vap(&self) -> f64 {
         self.heat_vap
     }
+    /// Returns the composition, in mole fractions, such as `"C8H18:0.9, C2H5OH:0.1"`
     pub fn composition<'a>(&'a self) -> &'a str {
         &self.comp
     }
@@ -576,6 +1740,9 @@ pub struct Injector {
     fuel_mass_frac: f64,
     injected_fuel: f64,
     afr_afr_stoich: f64,
+    pulses: Vec<InjectionPulse>,
+    wall_film: Option<WallFilm>,
+    port_vapor: f64, // [kg] - fuel evaporated in the intake port waiting for the next intake
 }
 
 impl Injector {
@@ -595,11 +1762,36 @@ impl Injector {
             fuel_mass_frac,
             injected_fuel: 0.0,
             afr_afr_stoich,
+            pulses: Vec::new(),
+            wall_film: None,
+            port_vapor: 0.0,
         }
     }
     pub fn calc_port_injected_fuel(&self, intake_mass: f64) -> f64 {
         self.fuel_mass_frac * intake_mass
     }
+    /// Returns the fuel, in kg, entering the cylinder with the fresh charge `intake_mass` during `dt`.
+    /// Without a wall film, it is the injected fuel. Otherwise, part of the injected fuel deposits on the port
+    /// walls and the fuel evaporated from the film is carried by the next fresh charge. A negative `intake_mass`,
+    /// i.e. backflow, carries fuel vapor back to the port, where it does not reach the film
+    pub fn deliver_port_fuel(&mut self, intake_mass: f64, dt: f64) -> f64 {
+        let injected = self.calc_port_injected_fuel(intake_mass);
+        match &mut self.wall_film {
+            Some(film) => {
+                let evaporated = film.evaporate(injected.max(0.0), dt);
+                self.port_vapor += (1.0 - film.deposit_frac) * injected.max(0.0) + evaporated;
+                if injected < 0.0 {
+                    self.port_vapor -= injected;
+                    return injected;
+                }
+                // the fuel cannot exceed the fresh charge carrying it
+                let delivered = self.port_vapor.min(intake_mass);
+                self.port_vapor -= delivered;
+                delivered
+            }
+            None => injected,
+        }
+    }
     pub fn calc_direct_injected_fuel(&self, intake_mass: f64) -> f64 {
         intake_mass / self.afr_afr_stoich
     }
@@ -631,4 +1823,589 @@ impl Injector {
         self.fuel_mass_frac = fuel_mass_frac;
         self.afr_afr_stoich = afr_afr_stoich;
     }
+    /// Returns the stoichiometric air-fuel ratio, in mass
+    pub fn stoich_air_fuel_ratio(&self) -> f64 {
+        self.afr_afr_stoich / self.air_fuel_ratio
+    }
+    /// Returns the wall film of a port injector
+    pub fn wall_film(&self) -> Option<&WallFilm> {
+        self.wall_film.as_ref()
+    }
+    /// Set the wall film of a port injector. `None` removes the film and its fuel
+    pub fn set_wall_film(&mut self, wall_film: Option<WallFilm>) -> Result<(), String> {
+        if self.inj_type != "port" && wall_film.is_some() {
+            return Err(format!("wall film requires a \"port\" injector, not \"{}\"", self.inj_type));
+        }
+        self.wall_film = wall_film;
+        self.port_vapor = 0.0;
+        Ok(())
+    }
+    /// Returns the injection pulses of a direct injector
+    pub fn pulses(&self) -> &Vec<InjectionPulse> {
+        &self.pulses
+    }
+    /// Set the injection pulses of a direct injector. Their fractions of the fuel per cycle must add up to one.
+    /// Without pulses, the fuel is injected at the start of the closed phase
+    pub fn set_pulses(&mut self, pulses: Vec<InjectionPulse>) -> Result<(), String> {
+        if self.inj_type != "direct" && !pulses.is_empty() {
+            return Err(format!("injection pulses require a \"direct\" injector, not \"{}\"", self.inj_type));
+        }
+        if !pulses.is_empty() {
+            let total: f64 = pulses.iter().map(|p| p.fraction()).sum();
+            if (total - 1.0).abs() > 1e-6 {
+                return Err(format!("the fractions of the injection pulses must add up to 1.0: {}", total));
+            }
+        }
+        self.pulses = pulses;
+        Ok(())
+    }
+    /// Returns the fraction of the fuel per cycle injected between the crank-angles `from` and `to`, in radians.
+    /// `cycle_angle` is the crank-angle, in radians, of a full cycle
+    pub fn injected_fraction(&self, from: f64, to: f64, cycle_angle: f64) -> f64 {
+        self.pulses
+            .iter()
+            .map(|p| p.injected_between(from, to, cycle_angle))
+            .sum()
+    }
+}
+
+/// X-tau (Aquino) model of the fuel film on the intake port walls: a fraction `X` of the injected fuel deposits on the walls
+/// and the film evaporates with the time constant `tau`, `dm_film/dt = X*m_inj' - m_film/tau`.
+/// The fuel entering the cylinder lags the injected fuel when the air-fuel ratio or the speed change.
+/// The film starts dry and builds up during the first cycles.
+#[derive(Debug, Clone)]
+pub struct WallFilm {
+    deposit_frac: f64,
+    time_const: f64,    // [s]
+    mass: f64,          // [kg]
+}
+
+impl WallFilm {
+    /// `deposit_frac` is `X`, between 0 and 1, and `time_const` is `tau` in s
+    pub fn new(deposit_frac: f64, time_const: f64) -> Result<WallFilm, String> {
+        if !(0.0..=1.0).contains(&deposit_frac) {
+            return Err(format!("`deposit_frac` must be between 0.0 and 1.0: {}", deposit_frac));
+        }
+        if time_const <= 0.0 {
+            return Err(format!("`time_const` must be greater than zero: {}", time_const));
+        }
+        Ok(WallFilm {
+            deposit_frac,
+            time_const,
+            mass: 0.0,
+        })
+    }
+
+    /// Returns the fraction of the injected fuel deposited on the walls
+    pub fn deposit_frac(&self) -> f64 {
+        self.deposit_frac
+    }
+
+    /// Returns the evaporation time constant in s
+    pub fn time_const(&self) -> f64 {
+        self.time_const
+    }
+
+    /// Returns the fuel mass, in kg, of the film
+    pub fn mass(&self) -> f64 {
+        self.mass
+    }
+
+    // deposits part of `injected`, in kg, injected at constant rate during `dt` and returns the fuel evaporated from the film
+    fn evaporate(&mut self, injected: f64, dt: f64) -> f64 {
+        let decay = (-dt / self.time_const).exp();
+        let deposit = self.deposit_frac * injected;
+        // exact solution for constant injection rate
+        let mass = self.mass * decay + deposit * self.time_const / dt * (1.0 - decay);
+        let evaporated = self.mass + deposit - mass;
+        self.mass = mass;
+        evaporated
+    }
+}
+
+/// Injection pulse of a direct injector: its start of injection and injection rate shape
+#[derive(Debug, Clone)]
+pub struct InjectionPulse {
+    start: f64,           // [CA radian]
+    fraction: f64,        // of the fuel per cycle
+    angles: Vec<f64>,     // [CA radian] - after the start of injection
+    rates: Vec<f64>,      // relative injection rate
+    cumulative: Vec<f64>, // injected fraction of the pulse at `angles`
+}
+
+impl InjectionPulse {
+    /// Creates a pulse starting at `start`, in CA-deg, which injects `fraction` of the fuel per cycle with the rate shape
+    /// given by pairs of crank-angle after the start of injection, in CA-deg, and relative injection rate
+    pub fn new(start: f64, fraction: f64, rate_shape: &[(f64, f64)]) -> Result<InjectionPulse, String> {
+        if fraction <= 0.0 || fraction > 1.0 {
+            return Err(format!("pulse `fraction` must be between 0.0 and 1.0: {}", fraction));
+        }
+        if rate_shape.len() < 2 {
+            return Err("pulse rate shape must have at least two points".to_string());
+        }
+        if rate_shape[0].0 != 0.0 {
+            return Err(format!("pulse rate shape must start at 0.0 CA-deg: {}", rate_shape[0].0));
+        }
+        if rate_shape.windows(2).any(|w| w[1].0 <= w[0].0) {
+            return Err("pulse rate shape angles must be increasing".to_string());
+        }
+        if rate_shape.iter().any(|(_, rate)| *rate < 0.0) {
+            return Err("pulse injection rates cannot be negative".to_string());
+        }
+        let angles: Vec<f64> = rate_shape.iter().map(|(angle, _)| angle.to_radians()).collect();
+        let rates: Vec<f64> = rate_shape.iter().map(|(_, rate)| *rate).collect();
+        // trapezoidal integration of the rate shape
+        let mut cumulative = vec![0.0];
+        for i in 1..angles.len() {
+            let area = 0.5 * (rates[i] + rates[i - 1]) * (angles[i] - angles[i - 1]);
+            cumulative.push(cumulative[i - 1] + area);
+        }
+        let total = cumulative[cumulative.len() - 1];
+        if total <= 0.0 {
+            return Err("pulse rate shape must inject some fuel".to_string());
+        }
+        let cumulative = cumulative.iter().map(|c| c / total).collect();
+        Ok(InjectionPulse {
+            start: start.to_radians(),
+            fraction,
+            angles,
+            rates,
+            cumulative,
+        })
+    }
+
+    /// Creates a pulse with constant injection rate during `duration`, in CA-deg
+    pub fn rectangular(start: f64, duration: f64, fraction: f64) -> Result<InjectionPulse, String> {
+        if duration <= 0.0 {
+            return Err(format!("pulse `duration` must be greater than zero: {}", duration));
+        }
+        InjectionPulse::new(start, fraction, &[(0.0, 1.0), (duration, 1.0)])
+    }
+
+    /// Returns the start of injection in CA-deg
+    pub fn start(&self) -> f64 {
+        self.start.to_degrees()
+    }
+
+    /// Returns the duration in CA-deg
+    pub fn duration(&self) -> f64 {
+        self.angles[self.angles.len() - 1].to_degrees()
+    }
+
+    /// Returns the fraction of the fuel per cycle injected by the pulse
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+
+    // injected fraction of the pulse at `x`, in radians after the start of injection
+    fn injected_until(&self, x: f64) -> f64 {
+        if x <= 0.0 {
+            return 0.0;
+        }
+        match self.angles.iter().position(|a| *a >= x) {
+            Some(i) => {
+                // linear injection rate in the interval
+                let (a0, a1) = (self.angles[i - 1], self.angles[i]);
+                let rate = self.rates[i - 1] + (self.rates[i] - self.rates[i - 1]) * (x - a0) / (a1 - a0);
+                let total = self.cumulative[i] - self.cumulative[i - 1];
+                let area = 0.5 * (self.rates[i - 1] + rate) * (x - a0);
+                let full_area = 0.5 * (self.rates[i - 1] + self.rates[i]) * (a1 - a0);
+                let part = if full_area > 0.0 { area / full_area } else { 0.0 };
+                self.cumulative[i - 1] + total * part
+            }
+            None => 1.0,
+        }
+    }
+
+    /// Returns the fraction of the fuel per cycle injected by the pulse between the crank-angles `from` and `to`, in radians
+    pub fn injected_between(&self, from: f64, to: f64, cycle_angle: f64) -> f64 {
+        let x_from = (from - self.start).rem_euclid(cycle_angle);
+        let x_to = (to - self.start).rem_euclid(cycle_angle);
+        let injected = if x_to >= x_from {
+            self.injected_until(x_to) - self.injected_until(x_from)
+        } else {
+            // the interval contains the start of injection
+            1.0 - self.injected_until(x_from) + self.injected_until(x_to)
+        };
+        self.fraction * injected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn octane(gas: &Gas) -> Fuel {
+        let json = JsonFuel {
+            name: "C8H18".to_string(),
+            state: "liquid".to_string(),
+            lhv: None,
+            heat_vap: None,
+            components: Vec::new(),
+            basis: None,
+        };
+        Fuel::new(&json, gas).unwrap()
+    }
+
+    // sum of the fractions injected in steps of `step` CA-deg over a full four-stroke cycle
+    fn cycle_sum(fraction: impl Fn(f64, f64) -> f64, step: f64) -> f64 {
+        let steps = (720.0 / step) as usize;
+        (0..steps)
+            .map(|i| fraction((i as f64 * step).to_radians(), ((i + 1) as f64 * step).to_radians()))
+            .sum()
+    }
+
+    #[test]
+    fn pulse_fractions_across_cycle_wrap() {
+        let cycle = 4.0 * PI;
+        let wrapping = InjectionPulse::rectangular(700.0, 40.0, 0.6).unwrap();
+        let triangular = InjectionPulse::new(20.0, 0.4, &[(0.0, 0.0), (10.0, 1.0), (20.0, 0.0)]).unwrap();
+        assert!((wrapping.injected_between(700f64.to_radians(), cycle, cycle) - 0.3).abs() < 1e-12);
+        assert!((wrapping.injected_between(0.0, 20f64.to_radians(), cycle) - 0.3).abs() < 1e-12);
+        assert!((wrapping.injected_between(690f64.to_radians(), 30f64.to_radians(), cycle) - 0.6).abs() < 1e-12);
+        assert!((triangular.injected_between(20f64.to_radians(), 30f64.to_radians(), cycle) - 0.2).abs() < 1e-12);
+        assert!((triangular.injected_between(22f64.to_radians(), 24f64.to_radians(), cycle) - 0.4 * 0.06).abs() < 1e-12);
+        assert!((cycle_sum(|from, to| wrapping.injected_between(from, to, cycle), 0.75) - 0.6).abs() < 1e-12);
+        assert!((cycle_sum(|from, to| triangular.injected_between(from, to, cycle), 0.75) - 0.4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn injector_fractions_add_up_to_one() {
+        let gas = Gas::new("air.json");
+        let mut injector = Injector::new("direct".to_string(), 1.0, &octane(&gas), &gas);
+        let pulses = vec![
+            InjectionPulse::rectangular(710.0, 20.0, 0.25).unwrap(),
+            InjectionPulse::new(330.0, 0.75, &[(0.0, 0.0), (5.0, 1.0), (25.0, 1.0), (30.0, 0.0)]).unwrap(),
+        ];
+        injector.set_pulses(pulses).unwrap();
+        let cycle = 4.0 * PI;
+        assert!((cycle_sum(|from, to| injector.injected_fraction(from, to, cycle), 1.25) - 1.0).abs() < 1e-12);
+        assert!((injector.injected_fraction(0.0, cycle - 1e-9, cycle) - 1.0).abs() < 1e-6);
+
+        let unbalanced = vec![InjectionPulse::rectangular(330.0, 20.0, 0.5).unwrap()];
+        assert!(injector.set_pulses(unbalanced).is_err());
+        let mut port = Injector::new("port".to_string(), 1.0, &octane(&gas), &gas);
+        assert!(port.set_pulses(vec![InjectionPulse::rectangular(330.0, 20.0, 1.0).unwrap()]).is_err());
+    }
+
+    #[test]
+    fn wall_film_steady_state() {
+        let mut film = WallFilm::new(0.4, 0.05).unwrap();
+        let (injected, dt) = (2e-7, 1e-4);
+        // builds up from a dry port towards X*m_inj'*tau, conserving the fuel
+        let mut evaporated = 0.0;
+        for _ in 0..20000 {
+            evaporated += film.evaporate(injected, dt);
+        }
+        let equilibrium = 0.4 * injected / dt * 0.05;
+        assert!((film.mass() - equilibrium).abs() < 1e-6 * equilibrium);
+        assert!((evaporated + film.mass() - 0.4 * injected * 20000.0).abs() < 1e-15);
+        // at steady state, the evaporated fuel balances the deposit and the cylinder receives the injected fuel
+        assert!((film.evaporate(injected, dt) - 0.4 * injected).abs() < 1e-6 * injected);
+        assert!(WallFilm::new(1.5, 0.05).is_err());
+        assert!(WallFilm::new(0.4, 0.0).is_err());
+    }
+
+    #[test]
+    fn wall_film_delays_port_fuel() {
+        let gas = Gas::new("air.json");
+        let mut injector = Injector::new("port".to_string(), 1.0, &octane(&gas), &gas);
+        injector.set_wall_film(Some(WallFilm::new(0.4, 0.05).unwrap())).unwrap();
+        let (intake_mass, dt, steps) = (1e-6, 1e-4, 200);
+        let injected = injector.calc_port_injected_fuel(intake_mass);
+        // the fuel missing from the cylinder is in the film
+        let first: f64 = (0..steps).map(|_| injector.deliver_port_fuel(intake_mass, dt)).sum();
+        let film = injector.wall_film().unwrap().mass();
+        assert!(first < injected * steps as f64);
+        assert!((first + film - injected * steps as f64).abs() < 1e-12 * first);
+        // the film builds up towards X*m_inj'*tau and the cylinder receives the injected fuel
+        for _ in 0..20000 {
+            injector.deliver_port_fuel(intake_mass, dt);
+        }
+        let film = injector.wall_film().unwrap().mass();
+        assert!((film - 0.4 * injected / dt * 0.05).abs() < 1e-6 * film);
+        let delivered = injector.deliver_port_fuel(intake_mass, dt);
+        assert!((delivered - injected).abs() < 1e-6 * injected);
+
+        let mut direct = Injector::new("direct".to_string(), 1.0, &octane(&gas), &gas);
+        assert!(direct.set_wall_film(Some(WallFilm::new(0.4, 0.05).unwrap())).is_err());
+    }
+
+    #[test]
+    fn backflow_does_not_reach_wall_film() {
+        let gas = Gas::new("air.json");
+        let mut injector = Injector::new("port".to_string(), 1.0, &octane(&gas), &gas);
+        injector.set_wall_film(Some(WallFilm::new(0.4, 0.05).unwrap())).unwrap();
+        let dt = 1e-4;
+        let backflow_fuel = injector.calc_port_injected_fuel(1e-6);
+
+        // the fuel leaves the cylinder as vapor, the dry film is untouched
+        let delivered = injector.deliver_port_fuel(-1e-6, dt);
+        assert!((delivered + backflow_fuel).abs() < 1e-15);
+        assert_eq!(injector.wall_film().unwrap().mass(), 0.0);
+
+        // and returns with the next fresh charge, along with the fuel not deposited
+        let injected = injector.calc_port_injected_fuel(1e-6);
+        let film_before = injector.wall_film().unwrap().mass();
+        let delivered = injector.deliver_port_fuel(1e-6, dt);
+        let film = injector.wall_film().unwrap().mass();
+        assert!(film > film_before);
+        assert!((delivered - (backflow_fuel + injected - film)).abs() < 1e-12 * delivered);
+
+        // with a film, backflow only lets it evaporate
+        injector.deliver_port_fuel(-1e-6, dt);
+        let after_backflow = injector.wall_film().unwrap().mass();
+        assert!(after_backflow >= 0.0 && after_backflow < film);
+    }
+
+    #[test]
+    fn cylinder_statistics() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(mean(&values), 5.0);
+        assert_eq!(std_deviation(&values), 2.0);
+        assert_eq!(spread(&values), 7.0);
+        assert_eq!(relative(std_deviation(&values), mean(&values)), 0.4);
+        // a single cylinder does not vary
+        assert_eq!(std_deviation(&[3.0]), 0.0);
+        assert_eq!(spread(&[-3.0]), 0.0);
+        // negative values, i.e. motored cylinders
+        assert_eq!(spread(&[-1.0, -4.0]), 3.0);
+        // no active cylinder and zero reference
+        assert_eq!(mean(&[]), 0.0);
+        assert_eq!(std_deviation(&[]), 0.0);
+        assert_eq!(spread(&[]), 0.0);
+        assert_eq!(relative(1.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn trapping_pressure_across_the_cycle_end() {
+        let gas = Gas::new("air.json");
+        let mut engine = Engine::new("engine.json", &gas).unwrap();
+        // the intake closes at 719.8 CA-deg, closer to the first sample of the cycle than to the last one
+        engine.set_intake_actuation(ValveActuation::phase_shift(149.8));
+        let cyl = &engine.cylinders()[0];
+        assert!((cyl.trapping_angle() - 719.8).abs() < 1e-9);
+        let angle: Array1<f64> = Array1::range(0.0, 720.0, 1.0);
+        let vol = angle.mapv(|a| cyl.volume_at(a.to_radians()).0 * 1e6);
+        let press = angle.mapv(|a| 1.0 + 0.01 * a);
+        engine.calc_operational_param(vec![press.view()], vec![vol.view()], vec![angle.view()], 0.0);
+        let trapping_press = engine.operat_param.cylinders()[0][0].trapping_pressure;
+        assert!((trapping_press - 1.0).abs() < 1e-12);
+    }
+
+    // four-cylinder version of `engine.json` with `firing_order` and, if given, the `tdc_offset` of each cylinder
+    fn four_cylinder_engine(gas: &Gas, file_name: &str, firing_order: &str, tdc_offsets: Option<[f64; 4]>) -> Engine {
+        Engine::new(&four_cylinder_file(file_name, firing_order, tdc_offsets), gas).unwrap()
+    }
+
+    // writes the four-cylinder version of `engine.json` to the temporary directory and returns its path
+    fn four_cylinder_file(file_name: &str, firing_order: &str, tdc_offsets: Option<[f64; 4]>) -> String {
+        let mut json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string("engine.json").unwrap()).unwrap();
+        let cylinder = json["cylinders"][0].clone();
+        let cylinders: Vec<serde_json::Value> = (0..4)
+            .map(|i| {
+                let mut cyl = cylinder.clone();
+                cyl["name"] = format!("cyl_{}", i + 1).into();
+                cyl["intake_valves"][0]["name"] = format!("valve_int_{}", i + 1).into();
+                cyl["exhaust_valves"][0]["name"] = format!("valve_exh_{}", i + 1).into();
+                if let Some(offsets) = tdc_offsets {
+                    cyl["tdc_offset"] = offsets[i].into();
+                }
+                cyl
+            })
+            .collect();
+        json["cylinders"] = cylinders.into();
+        json["firing_order"] = firing_order.into();
+        let path = std::env::temp_dir().join(file_name);
+        std::fs::write(&path, json.to_string()).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn firing_order_matches_tdc_offsets() {
+        let gas = Gas::new("air.json");
+        let shorthand = four_cylinder_engine(&gas, "lmb_firing_order.json", "1-3-4-2", None);
+        let offsets = [0.0, 360.0, 180.0, 540.0];
+        let explicit = four_cylinder_engine(&gas, "lmb_tdc_offsets.json", "", Some(offsets));
+        assert_eq!(shorthand.tdc_offsets(), &offsets.to_vec());
+        for ((short, expl), order) in shorthand.cylinders().iter().zip(explicit.cylinders()).zip([1.0, 3.0, 4.0, 2.0]) {
+            // original placement of the shorthand
+            let start = (180.0 + (order - 1.0) * 180.0) % 720.0;
+            assert!((short.crank_angle().to_degrees() - start).abs() < 1e-9, "{}", short.name());
+            assert_eq!(short.crank_angle(), expl.crank_angle());
+        }
+    }
+
+    #[test]
+    fn reactivation_restores_valve_actuation() {
+        let gas = Gas::new("air.json");
+        let mut engine = four_cylinder_engine(&gas, "engine_deactivation.json", "1-3-4-2", None);
+        let phase = ValveActuation::phase_shift(20.0);
+        engine.set_exhaust_actuation(phase);
+        engine.set_deactivated_of("cyl_2", true);
+        let valve = |engine: &Engine, name: &str| engine.valves().iter().find(|v| v.name() == name).unwrap().actuation();
+        assert!(valve(&engine, "valve_int_2") == ValveActuation::deactivated());
+        assert!(valve(&engine, "valve_exh_2") == ValveActuation::deactivated());
+        assert_eq!(engine.active_cylinders(), 3);
+
+        // changes of the actuation skip the deactivated cylinder
+        let intake = ValveActuation::phase_shift(30.0);
+        engine.set_intake_actuation(intake);
+        assert!(valve(&engine, "valve_int_1") == intake);
+        assert!(valve(&engine, "valve_int_2") == ValveActuation::deactivated());
+        assert!((engine.cylinders()[0].intake_closing() - 600.0).abs() < 1e-9);
+        assert!((engine.cylinders()[1].intake_closing() - 570.0).abs() < 1e-9);
+
+        // reactivating takes the current actuation of the engine back, with its valve events
+        engine.set_deactivated_of("cyl_2", false);
+        assert!(valve(&engine, "valve_int_2") == intake);
+        assert!(valve(&engine, "valve_exh_2") == phase);
+        let cyl = &engine.cylinders()[1];
+        assert!(!cyl.is_deactivated());
+        assert!((cyl.intake_closing() - 600.0).abs() < 1e-9);
+        assert!((cyl.exhaust_opening() - 150.0).abs() < 1e-9);
+        assert!((cyl.exhaust_closing() - 395.0).abs() < 1e-9);
+        assert_eq!(engine.active_cylinders(), 4);
+    }
+
+    #[test]
+    fn deactivated_cylinder_takes_no_fuel() {
+        let gas = Gas::new("air.json");
+        let mut builder = crate::SystemBuilder::new();
+        builder
+            .add_engine(&four_cylinder_file("engine_deactivated_fuel.json", "1-3-4-2", None), &gas)
+            .add_environment("ambient", &gas);
+        for i in 1..=4 {
+            builder
+                .connect_from_to(&format!("valve_int_{}", i), "ambient")
+                .connect_from_to(&format!("valve_exh_{}", i), "ambient");
+        }
+        let mut system = builder.build_system();
+        system.engine_mut().unwrap().set_deactivated_of("cyl_2", true);
+        // one cycle, so that every cylinder closes its intake valves
+        let dt = 0.5f64.to_radians() / system.engine().unwrap().sec_to_rad();
+        for _ in 0..1440 {
+            system.advance(dt);
+        }
+
+        let engine = system.engine_mut().unwrap();
+        let fuel: Vec<f64> = engine.cylinders().iter().map(|c| c.fuel_mass()).collect();
+        assert_eq!(fuel[1], 0.0);
+        assert!(fuel.iter().enumerate().all(|(i, f)| i == 1 || *f > 0.0));
+
+        // the deactivated cylinder only adds its displacement and friction
+        let angle: Array1<f64> = Array1::range(0.0, 720.0, 1.0);
+        let vol: Vec<Array1<f64>> = engine
+            .cylinders()
+            .iter()
+            .map(|c| angle.mapv(|a| c.volume_at(a.to_radians()).0 * 1e6))
+            .collect();
+        let press: Vec<Array1<f64>> = (0..4)
+            .map(|i| angle.mapv(|a| if i != 1 && a < 180.0 { 20.0 } else { 1.01325 }))
+            .collect();
+        engine.calc_operational_param(
+            press.iter().map(|p| p.view()).collect(),
+            vol.iter().map(|v| v.view()).collect(),
+            (0..4).map(|_| angle.view()).collect(),
+            0.0,
+        );
+        let brake_power = engine.operat_param.brake_power[0];
+        assert!(brake_power > 0.0);
+        let lhv = engine.injector().unwrap().fuel().lhv();
+        let active_fuel = fuel[0] + fuel[2] + fuel[3];
+        let expected = 100.0 * brake_power / (engine.cycles_per_second() * active_fuel * lhv);
+        assert!((engine.operat_param.brake_effic[0] - expected).abs() < 1e-9 * expected);
+    }
+
+    fn read_friction(json: &str) -> Result<Friction, String> {
+        Friction::from_json(&serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn chen_flynn_friction() {
+        let friction = read_friction(r#"{"model": "Chen-Flynn", "a": 0.3, "b": 0.005, "c": 0.09, "d": 0.0009}"#).unwrap();
+        let fmep = 0.3 + 0.005 * 60.0 + 0.09 * 12.0 + 0.0009 * 144.0;
+        assert!((friction.fmep(3000.0, 60.0, 12.0) - fmep).abs() < 1e-12);
+        assert!(read_friction(r#"{"model": "Chen-Flynn", "a": 0.3, "b": 0.005, "c": 0.09}"#).is_err());
+        assert!(read_friction(r#"{"model": "Patton"}"#).is_err());
+    }
+
+    #[test]
+    fn friction_map_interpolation() {
+        let map = FrictionMap::new(&[1000.0, 3000.0], &[40.0, 80.0], array![[1.0, 1.4], [1.6, 2.2]]).unwrap();
+        assert_eq!(map.fmep(1000.0, 40.0), 1.0);
+        assert_eq!(map.fmep(3000.0, 80.0), 2.2);
+        assert!((map.fmep(2000.0, 40.0) - 1.3).abs() < 1e-12);
+        assert!((map.fmep(1000.0, 60.0) - 1.2).abs() < 1e-12);
+        assert!((map.fmep(2000.0, 60.0) - 1.55).abs() < 1e-12);
+        // constant beyond the limits
+        assert_eq!(map.fmep(500.0, 20.0), 1.0);
+        assert!((map.fmep(4000.0, 60.0) - 1.9).abs() < 1e-12);
+
+        // the map model reads the same table and ignores the piston speed
+        let json = r#"{"model": "map", "speed": [1000.0, 3000.0], "peak_pressure": [40.0, 80.0], "fmep": [[1.0, 1.4], [1.6, 2.2]]}"#;
+        assert!((read_friction(json).unwrap().fmep(2000.0, 60.0, 10.0) - 1.55).abs() < 1e-12);
+        let json = r#"{"model": "map", "speed": [1000.0, 3000.0], "peak_pressure": [40.0, 80.0], "fmep": [[1.0, 1.4]]}"#;
+        assert!(read_friction(json).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_friction_maps() {
+        let fmep = array![[1.0, 1.4], [1.6, 2.2]];
+        assert!(FrictionMap::new(&[3000.0, 1000.0], &[40.0, 80.0], fmep.clone()).is_err());
+        assert!(FrictionMap::new(&[1000.0, 3000.0], &[40.0], fmep.clone()).is_err());
+        assert!(FrictionMap::new(&[1000.0, 3000.0], &[40.0, 80.0], -fmep).is_err());
+        assert!(FrictionMap::new(&[], &[40.0], Array2::zeros((0, 1))).is_err());
+        let single = FrictionMap::new(&[2000.0], &[60.0], array![[1.5]]).unwrap();
+        assert_eq!(single.fmep(5000.0, 10.0), 1.5);
+    }
+
+    fn e10(basis: &str, octane: f64, ethanol: f64) -> JsonFuel {
+        let component = |specie: &str, fraction: f64| JsonFuelComponent {
+            specie: specie.to_string(),
+            fraction,
+            lhv: None,
+            heat_vap: None,
+        };
+        JsonFuel {
+            name: "E10".to_string(),
+            state: "liquid".to_string(),
+            lhv: None,
+            heat_vap: None,
+            components: vec![component("C8H18", octane), component("C2H5OH", ethanol)],
+            basis: Some(basis.to_string()),
+        }
+    }
+
+    #[test]
+    fn blend_properties() {
+        let gas = Gas::new("air.json");
+        let fuel = Fuel::new(&e10("mass", 0.9, 0.1), &gas).unwrap();
+        // mass weighted properties of the components
+        assert!((fuel.lhv() - (0.9 * 44.651e6 + 0.1 * 25.858e6)).abs() < 1.0);
+        assert!((fuel.heat_vap() - (0.9 * 307e3 + 0.1 * 918e3)).abs() < 1e-6);
+        let injector = Injector::new("port".to_string(), 1.0, &fuel, &gas);
+        assert!((injector.stoich_air_fuel_ratio() - 14.42).abs() < 0.02);
+        assert!((Injector::new("port".to_string(), 1.0, &octane(&gas), &gas).stoich_air_fuel_ratio() - 15.03).abs() < 0.02);
+
+        // the same blend given in mole fractions
+        let (w_octane, w_ethanol) = (gas.mole_weight_of("C8H18"), gas.mole_weight_of("C2H5OH"));
+        let moles = 0.9 / w_octane + 0.1 / w_ethanol;
+        let by_mole = Fuel::new(&e10("mole", 0.9 / w_octane / moles, 0.1 / w_ethanol / moles), &gas).unwrap();
+        assert!((by_mole.lhv() - fuel.lhv()).abs() < 1e-6 * fuel.lhv());
+        assert!((by_mole.mole_weight() - fuel.mole_weight()).abs() < 1e-9);
+        assert!((by_mole.air_moles() - fuel.air_moles()).abs() < 1e-9);
+
+        assert!(Fuel::new(&e10("mass", 0.9, 0.2), &gas).is_err());
+        assert!(Fuel::new(&e10("volume", 0.9, 0.1), &gas).is_err());
+    }
+
+    #[test]
+    fn low_heat_value_from_enthalpies_of_formation() {
+        let gas = Gas::new("air.json");
+        let octane = Fuel::calc_low_heat_value("C8H18", &gas).unwrap();
+        let methane = Fuel::calc_low_heat_value("CH4", &gas).unwrap();
+        assert!((octane - 44.651e6).abs() < 0.001 * 44.651e6);
+        assert!((methane - 50.0e6).abs() < 0.001 * 50.0e6);
+    }
 }
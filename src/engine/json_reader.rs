@@ -4,7 +4,7 @@
 //! 
 //! **Attention when entering the variables in crank-angle degree!** 
 //! The reference, where crank-angle is zero, is at top-dead-center (TDC) of compression phase and it only accepts positive numbers.
-//! Therefore, the full cycle starts in 0 CA-deg and finishes at 720 CA-deg. 
+//! Therefore, the full cycle starts in 0 CA-deg and finishes at 720 CA-deg, or at 360 CA-deg for two-stroke engines. 
  
 
 use serde::{Deserialize, Serialize};
@@ -22,8 +22,16 @@ pub struct JsonEngine {
     pub displacement: f64, 
     /// [mm]
     pub bore: f64,      
-    /// i.e "1-3-2"   
+    /// Position of each cylinder in the firing sequence, i.e "1-3-2". The cylinders fire evenly spaced.
+    /// Not required if all cylinders have a `tdc_offset`
+    #[serde(default)]
     pub firing_order: String,
+    /// Number of strokes per cycle: 4 (default) or 2
+    pub strokes: Option<u8>,
+    /// Scavenging model of the open phase, default: perfect mixing
+    pub scavenging: Option<JsonScavenging>,
+    /// Mechanical friction model. If not given, brake and indicated quantities are equal
+    pub friction: Option<JsonFriction>,
     pub combustion: Option<JsonCombustion>,
     pub injector: Option<JsonInjector>,
     pub cylinders: Vec<JsonCylinder>,
@@ -32,25 +40,149 @@ pub struct JsonEngine {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct JsonCylinder {
     pub name: String,
+    /// Crank-angle degree [CA-deg] at which the cylinder reaches its firing TDC, referred to the engine crank-angle.
+    /// It must be within the cycle, i.e. 0 to 720 CA-deg for four-stroke engines, and overrides `firing_order`
+    pub tdc_offset: Option<f64>,
     pub compression_ratio: f64,
     /// [K]
     pub wall_temperature: f64,
     pub store_species: Option<bool>,
+    #[serde(default)]
     pub intake_valves: Vec<JsonValve>,
+    #[serde(default)]
     pub exhaust_valves: Vec<JsonValve>,
+    /// Ports uncovered by the piston skirt, feeding the crankcase. Two-stroke engines only
+    #[serde(default)]
+    pub intake_ports: Vec<JsonPort>,
+    /// Ports uncovered by the piston crown, feeding the cylinder. Two-stroke engines only
+    #[serde(default)]
+    pub transfer_ports: Vec<JsonPort>,
+    /// Ports uncovered by the piston crown, discharging the cylinder. Two-stroke engines only
+    #[serde(default)]
+    pub exhaust_ports: Vec<JsonPort>,
+    /// Ratio between the crankcase volume at top and at bottom-dead-center. Two-stroke engines only
+    pub crankcase_compression_ratio: Option<f64>,
+    /// Leakage past the piston rings. If not given, the cylinder is sealed
+    pub blow_by: Option<JsonBlowBy>,
+    /// Volume of the piston-ring-liner crevices. If not given, it is neglected
+    pub crevice: Option<JsonCrevice>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct JsonValve {
     pub name: String,
-    /// Crank-angle degree [CA-deg]
+    /// Crank-angle degree [CA-deg]. Not required if `lift_profile` is given
+    #[serde(default)]
     pub opening_angle: f64,
-    /// Crank-angle degree [CA-deg]
+    /// Crank-angle degree [CA-deg]. Not required if `lift_profile` is given
+    #[serde(default)]
     pub closing_angle: f64,
     /// [mm]
     pub diameter: f64,
-    /// [mm]
+    /// [mm]. Not required if `lift_profile` is given
+    #[serde(default)]
     pub max_lift: f64,
+    /// Measured lift profile. If given, it replaces the synthetic lift curve and sets
+    /// `opening_angle`, `closing_angle` and `max_lift`
+    pub lift_profile: Option<JsonLiftProfile>,
+    /// Measured discharge coefficients. If not given, a default curve is used
+    pub discharge_coeff: Option<JsonDischargeCoeff>,
+}
+
+/// Port in the cylinder liner, see [`Port`](../../connector/port/struct.Port.html)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonPort {
+    pub name: String,
+    /// Crank-angle degree [CA-deg]. The closing angle follows from the piston motion
+    pub opening_angle: f64,
+    /// [mm] - width of each port
+    pub width: f64,
+    /// [mm] - height of each port
+    pub height: f64,
+    /// Number of identical ports, default: 1
+    pub number: Option<u32>,
+    /// Default: 0.7
+    pub discharge_coeff: Option<f64>,
+}
+
+/// Scavenging model, see [`Scavenging`](../../zero_dim/cylinder/enum.Scavenging.html)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonScavenging {
+    /// "perfect displacement", "perfect mixing" or "Benson-Brandham"
+    pub model: String,
+    /// Fraction of the delivered charge short-circuiting to the exhaust. Required by "Benson-Brandham"
+    pub short_circuit: Option<f64>,
+    /// Fraction of the cylinder mass scavenged by displacement. Required by "Benson-Brandham"
+    pub displacement: Option<f64>,
+}
+
+/// Blow-by model, see [`BlowBy`](../../zero_dim/cylinder/struct.BlowBy.html)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonBlowBy {
+    /// [mm²] - effective area of the ring gaps
+    pub ring_gap_area: f64,
+    /// [Pa] - default: 101325.0. Replaced by the pressure of the crankcase element, if the system has one
+    pub crankcase_pressure: Option<f64>,
+    /// Default: 0.7
+    pub discharge_coeff: Option<f64>,
+}
+
+/// Crevice model, see [`Crevice`](../../zero_dim/cylinder/struct.Crevice.html)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonCrevice {
+    /// [cm³]
+    pub volume: f64,
+}
+
+/// Mechanical friction model, see [`Friction`](../engine/enum.Friction.html)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonFriction {
+    /// "Chen-Flynn" or "map"
+    pub model: String,
+    /// Constant term of "Chen-Flynn" [bar]
+    pub a: Option<f64>,
+    /// Peak pressure term of "Chen-Flynn" [-]
+    pub b: Option<f64>,
+    /// Mean piston speed term of "Chen-Flynn" [bar.s/m]
+    pub c: Option<f64>,
+    /// Squared mean piston speed term of "Chen-Flynn" [bar.s²/m²]
+    pub d: Option<f64>,
+    /// Speeds of the "map" [RPM], increasing
+    pub speed: Option<Vec<f64>>,
+    /// Peak cylinder pressures of the "map" [bar], increasing
+    pub peak_pressure: Option<Vec<f64>>,
+    /// FMEP of the "map" [bar], one row per speed
+    pub fmep: Option<Vec<Vec<f64>>>,
+}
+
+/// Discharge coefficients from flow-bench measurements, see [`DischargeCoeffMap`](../../connector/valve/struct.DischargeCoeffMap.html)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonDischargeCoeff {
+    /// Lift over valve diameter, `L/D`, increasing
+    pub lift_diam: Vec<f64>,
+    /// Discharge coefficients of the flow into the cylinder
+    pub forward: Vec<f64>,
+    /// Discharge coefficients of the flow out of the cylinder
+    pub backward: Vec<f64>,
+    /// Area to which the coefficients are referred: "curtain", "inner seat", "port" or "throat"
+    pub reference_area: String,
+    /// [mm]. Required if `reference_area` is "port"
+    pub port_diameter: Option<f64>,
+}
+
+/// Measured valve lift, see [`LiftProfile`](../../connector/valve/struct.LiftProfile.html)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonLiftProfile {
+    /// Crank-angle degree [CA-deg], increasing
+    pub angle: Vec<f64>,
+    /// Cam lift [mm]
+    pub lift: Vec<f64>,
+    /// Valve lash [mm], default: 0.0
+    pub lash: Option<f64>,
+    /// Multiplier of the duration around the maximum lift, default: 1.0
+    pub duration_scale: Option<f64>,
+    /// Multiplier of the lift, default: 1.0
+    pub lift_scale: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -66,12 +198,61 @@ pub struct JsonInjector {
     pub inj_type: String,
     pub air_fuel_ratio: f64,
     pub fuel: JsonFuel,
+    /// Injection pulses of "direct" injectors. If empty, the fuel is injected at the start of the closed phase
+    #[serde(default)]
+    pub pulses: Vec<JsonInjectionPulse>,
+    /// Fuel film on the intake port walls of "port" injectors. If not given, all the injected fuel enters the cylinder
+    pub wall_film: Option<JsonWallFilm>,
+}
+
+/// X-tau wall film, see [`WallFilm`](../engine/struct.WallFilm.html)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonWallFilm {
+    /// Fraction of the injected fuel deposited on the walls, `X`
+    pub deposit_fraction: f64,
+    /// Evaporation time constant of the film, `tau` [s]
+    pub time_constant: f64,
+}
+
+/// Injection pulse, see [`InjectionPulse`](../engine/struct.InjectionPulse.html). Either `duration` or `rate_shape` is required
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonInjectionPulse {
+    /// Start of injection, crank-angle degree [CA-deg]
+    pub start: f64,
+    /// [CA-deg] - rectangular rate shape
+    pub duration: Option<f64>,
+    /// Pairs of crank-angle degree after the start of injection [CA-deg], increasing, and relative injection rate
+    pub rate_shape: Option<Vec<[f64; 2]>>,
+    /// Fraction of the fuel per cycle. If not given, the remaining fuel is split equally between the pulses
+    pub fraction: Option<f64>,
 }
 #[derive(Serialize, Deserialize, Debug)]
 pub struct JsonFuel {
+    /// Specie of the fuel or, if `components` is given, the name of the blend, i.e. "E10"
     pub name: String,
+    /// "liquid" or "gas"
     pub state: String,
+    /// [J/kg] - if not given, it is calculated from the components
+    pub lhv: Option<f64>,
+    /// [J/kg] - if not given, it is calculated from the components
+    pub heat_vap: Option<f64>,
+    /// Species of a blended fuel, i.e. iso-octane, n-heptane, toluene and ethanol
+    #[serde(default)]
+    pub components: Vec<JsonFuelComponent>,
+    /// Basis of the fractions of `components`: "mole" (default) or "mass"
+    pub basis: Option<String>,
+}
+
+/// Component of a blended fuel
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonFuelComponent {
+    /// Specie of the gas file
+    pub specie: String,
+    /// Mole or mass fraction, according to the `basis` of the fuel
+    pub fraction: f64,
+    /// [J/kg] - if not given, it is taken from the known fuels or calculated from the enthalpies of formation
     pub lhv: Option<f64>,
+    /// [J/kg] - if not given, it is taken from the known fuels
     pub heat_vap: Option<f64>,
 }
 
@@ -24,8 +24,10 @@
 //! which advances until the system reaches steady state. **OBS: So far, steady state conditions are not checked. 
 //! The method runs long enough that almost every system will have reached steady state by then.**
 //! For engine simulation, most commonly, it is used [`advance_to_steady_state()`](core/system/struct.System.html#method.advance_to_steady_state).
-//! After the simulation is finished, all stored variables can only be accessed by writing them into a 
-//! file via system method [`write_to_file()`](core/system/struct.System.html#method.write_to_file) 
+//! After the simulation is finished, the stored variables can be written into a file via system method
+//! [`write_to_file()`](core/system/struct.System.html#method.write_to_file) or accessed directly in memory
+//! via [`trace()`](core/system/struct.System.html#method.trace), which returns a view over the last cycle. 
+//! The names of the variables stored by each object are given by [`stored_variables()`](core/system/struct.System.html#method.stored_variables).
 //!
 //! ### Example
 //! A simple system with a [Reservoir](zero_dim/reservoir/struct.Reservoir.html) and [Environment](zero_dim/environment/struct.Environment.html)
@@ -54,6 +56,10 @@
 //!     // Writting data
 //!     system.write_to_file("chamber.txt", "chamber", None);
 //!     system.write_to_file("orifice.txt", "orifice", None);
+//!
+//!     // Accessing data in memory
+//!     let pressure = system.trace("chamber", "pressure [bar]").unwrap();
+//!     println!("final chamber pressure: {:.3} [bar]", pressure[pressure.len() - 1]);
 //! }
 
 //! ```
@@ -68,7 +74,7 @@
 //! * "conrod" in mm,
 //! * "displacement" in cm³,
 //! * "bore" in mm,
-//! * "firing_order" as a string - i.e "1-3-2",
+//! * "firing_order" as a string - i.e "1-3-2", or a "tdc_offset" in CA-deg for each cylinder, for uneven firing intervals,
 //! * "cylinders" as a vector of structs [`json_cylinder`](engine/json_reader/struct.JsonCylinder.html)
 //! 
 //! All possible attributes of the `engine_file.json` file can be found at the full documentation at [`Json Reader`](engine/json_reader/index.html).
@@ -139,6 +145,16 @@
 //! In this example, we added an [`injector`](engine/json_reader/struct.JsonInjector.html), with relative air fuel ratio equal 1.0 
 //! and ethanol (C2H5OH) as fuel, and a [`combustion`](engine/json_reader/struct.JsonCombustion.html) model. 
 //! If they are not added, the engine will run as a motoring. 
+//! The [`fuel`](engine/json_reader/struct.JsonFuel.html) can also be a blend of species of the gas file, such as "E10",
+//! given by its `components`; its low heat value and heat of vaporization are then calculated from them. 
+//! A "direct" injector can be given injection [`pulses`](engine/json_reader/struct.JsonInjectionPulse.html), each with its
+//! start of injection and duration or rate shape, and the evaporating fuel cools the charge by its "heat_vap". 
+//! A mechanical [`friction`](engine/json_reader/struct.JsonFriction.html) model, "Chen-Flynn" or a measured "map", can be added
+//! to the engine to obtain the brake power, torque, BMEP and BSFC besides the indicated quantities. 
+//! Cylinders can be deactivated at runtime with [`Engine::set_deactivated_of()`](engine/engine/struct.Engine.html#method.set_deactivated_of),
+//! holding their valves shut and cutting their injection and combustion, to compare the brake efficiency at part load. 
+//! Each cylinder may also have a [`blow_by`](engine/json_reader/struct.JsonBlowBy.html) leakage past the rings to the crankcase
+//! and a [`crevice`](engine/json_reader/struct.JsonCrevice.html) volume, which stores gas at wall temperature during compression. 
 //! Right now, the **only combustion model implemented** is the "Two-zone model". Notice that the 
 //! [`cylinder`](engine/json_reader/struct.JsonCylinder.html) requires both intake and exhaust [`valves`](engine/json_reader/struct.JsonValve.html) 
 //! connected to it. In the `main.rs`, we will need to connect these valves to their ports with 
@@ -174,6 +190,17 @@
 //! }
 //! ```
 //! 
+//! The most common engineering plots (pressure traces, P-V diagrams, valve mass flow, heat-release rate and
+//! performance curves) can be rendered into `.png` or `.svg` files with the functions in [`plot::figures`](plot/figures/index.html).
+//! 
+//! Measured cylinder pressure traces can be analysed with [`analysis::heat_release`](analysis/heat_release/index.html),
+//! which computes the heat-release rate, the mass fraction burned and fits the Wiebe function of the combustion model.
+//! Parameters of the model, such as discharge coefficients, heat transfer multipliers, wall temperatures and
+//! the Wiebe function, can be fitted to measured performance data with [`analysis::calibration`](analysis/calibration/index.html).
+//! The valve events can be shifted at runtime by cam phasers, with variable lift and duration, through
+//! [`Engine::set_intake_actuation()`](engine/engine/struct.Engine.html#method.set_intake_actuation), and the phases
+//! maximizing the torque at each speed can be found with [`analysis::cam_phasing`](analysis/cam_phasing/index.html).
+//! 
 //! For a real-life engine simulation, see [Engine Examples](doc/Ryobi_26cm3_engine/index.html)
 
 
@@ -182,12 +209,14 @@ use ndarray::*;
 use std::io::Write;
 use std::ops::Add;
 
+pub mod analysis;
 pub mod base;
 pub mod connector;
 pub mod core;
 pub mod engine;
 pub mod numerics;
 pub mod one_dim;
+pub mod plot;
 pub mod reaction;
 pub mod zero_dim;
 mod doc;
@@ -205,6 +234,7 @@ pub enum ObjectType {
     OneDim,
     Connector,
     Cylinder,
+    Shaft,
 }
 
 #[derive(Debug, Clone)]
@@ -217,6 +247,7 @@ pub struct BasicProperties<'a> {
     pub cp_cv: f64,
     pub gas_const: f64,           // J/(kg.K)
     pub crank_angle: Option<f64>, // CA rad
+    pub mass_frac: Array1<f64>,   // species mass fraction, same order as `Gas::species()`
 }
 
 impl<'a> std::fmt::Display for BasicProperties<'a> {
@@ -243,8 +274,9 @@ impl<'a> std::fmt::Display for BasicProperties<'a> {
 
 #[derive(Debug, Clone)]
 pub struct FlowRatio {
-    pub mass_flow: f64,     // kg/s
-    pub enthalpy_flow: f64, // J/s
+    pub mass_flow: f64,            // kg/s
+    pub enthalpy_flow: f64,        // J/s
+    pub species_flow: Array1<f64>, // kg/s of each specie, same order as `Gas::species()`. Empty if there is no flow
 }
 
 impl FlowRatio {
@@ -252,6 +284,17 @@ impl FlowRatio {
         FlowRatio {
             mass_flow: 0.0,
             enthalpy_flow: 0.0,
+            species_flow: Array1::zeros(0),
+        }
+    }
+
+    fn add_species_flow(a: &Array1<f64>, b: &Array1<f64>) -> Array1<f64> {
+        if a.is_empty() {
+            b.clone()
+        } else if b.is_empty() {
+            a.clone()
+        } else {
+            a + b
         }
     }
 }
@@ -262,6 +305,7 @@ impl Add for FlowRatio {
         FlowRatio {
             mass_flow: self.mass_flow + other.mass_flow,
             enthalpy_flow: self.enthalpy_flow + other.enthalpy_flow,
+            species_flow: FlowRatio::add_species_flow(&self.species_flow, &other.species_flow),
         }
     }
 }
@@ -272,6 +316,7 @@ impl<'a, 'b> Add<&'b FlowRatio> for &'a FlowRatio {
         FlowRatio {
             mass_flow: self.mass_flow + other.mass_flow,
             enthalpy_flow: self.enthalpy_flow + other.enthalpy_flow,
+            species_flow: FlowRatio::add_species_flow(&self.species_flow, &other.species_flow),
         }
     }
 }
@@ -310,6 +355,31 @@ impl StoreData {
         data
     }
 
+    /// Returns the name of every stored variable, in the same order as the columns of `data`
+    pub fn columns(&self) -> Vec<&str> {
+        self.header.split('\t').collect()
+    }
+
+    /// Returns the column index of the stored variable `var_name`, if it exists
+    pub fn column_index(&self, var_name: &str) -> Option<usize> {
+        self.columns().iter().position(|name| *name == var_name)
+    }
+
+    /// Returns the number of rows stored so far
+    pub fn len(&self) -> usize {
+        self.last_index
+    }
+
+    /// Returns `true` if no data has been stored
+    pub fn is_empty(&self) -> bool {
+        self.last_index == 0
+    }
+
+    /// Returns a view of the column `col` limited by the index `range`
+    pub fn column(&self, range: (usize, usize), col: usize) -> ArrayView1<'_, f64> {
+        self.data.slice(s![range.0..range.1, col])
+    }
+
     fn reset_data(&mut self) {
         let num_variables = self.data.ncols();
         self.data = Array::from_elem((MAX_ARRAY_LEN, num_variables), 0.);
@@ -389,8 +459,24 @@ impl ObjectInfo {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn stored_data_columns() {
+        let mut data = StoreData::new("pressure [bar]\ttemperature [K]", 2);
+        assert!(data.is_empty());
+        data.add_data(array![1.0, 300.0]);
+        data.add_data(array![1.5, 320.0]);
+        assert_eq!(data.len(), 2);
+        assert_eq!(data.columns(), vec!["pressure [bar]", "temperature [K]"]);
+        assert_eq!(data.column_index("temperature [K]"), Some(1));
+        assert_eq!(data.column_index("mass [kg]"), None);
+        assert_eq!(data.column((0, 2), 1).to_vec(), vec![300.0, 320.0]);
+        assert_eq!(data.column((1, 2), 0).to_vec(), vec![1.5]);
+    }
 }
@@ -1,2 +1,3 @@
 //! Contains multiple numerical techniques
 pub mod ode_solvers;
+pub mod optimization;
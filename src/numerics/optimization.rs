@@ -0,0 +1,120 @@
+//! Derivative-free minimization of multivariable functions
+
+use ndarray::*;
+
+/// Minimizes `f` with the Nelder–Mead simplex method and returns the best point found and its value.
+///
+/// `x0` is the initial guess and `step` is the size of the initial simplex in each direction.
+/// The search stops when the spread of the function values in the simplex is lower than `tol`
+/// or after `max_iter` iterations. Constraints can be imposed by returning `f64::INFINITY` from `f`.
+///
+/// # Examples
+///
+/// Let's minimize the Rosenbrock function:
+/// ```
+/// use lmb_engine_simulator::numerics::optimization::nelder_mead;
+/// use ndarray::*;
+///
+/// let rosenbrock = |x: &Array1<f64>| -> f64 {
+///     (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2)
+/// };
+/// let (x, _) = nelder_mead(rosenbrock, &array![-1.2, 1.0], &array![0.5, 0.5], 1e-12, 5000);
+/// assert!((x[0] - 1.0).abs() < 1e-3 && (x[1] - 1.0).abs() < 1e-3);
+/// ```
+pub fn nelder_mead<F>(
+    mut f: F,
+    x0: &Array1<f64>,
+    step: &Array1<f64>,
+    tol: f64,
+    max_iter: usize,
+) -> (Array1<f64>, f64)
+where
+    F: FnMut(&Array1<f64>) -> f64,
+{
+    // standard coefficients: reflection, expansion, contraction and shrink
+    let (alpha, gamma, rho, sigma) = (1.0, 2.0, 0.5, 0.5);
+    let n = x0.len();
+
+    let mut simplex: Vec<(Array1<f64>, f64)> = Vec::with_capacity(n + 1);
+    simplex.push((x0.clone(), f(x0)));
+    for i in 0..n {
+        let mut x = x0.clone();
+        x[i] += step[i];
+        let value = f(&x);
+        simplex.push((x, value));
+    }
+
+    for _ in 0..max_iter {
+        simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        if (simplex[n].1 - simplex[0].1).abs() < tol {
+            break;
+        }
+
+        // centroid of all points but the worst
+        let mut centroid: Array1<f64> = Array1::zeros(n);
+        simplex[..n].iter().for_each(|(x, _)| centroid += x);
+        centroid /= n as f64;
+
+        let reflected = &centroid + &((&centroid - &simplex[n].0) * alpha);
+        let f_reflected = f(&reflected);
+        if f_reflected < simplex[0].1 {
+            let expanded = &centroid + &((&reflected - &centroid) * gamma);
+            let f_expanded = f(&expanded);
+            simplex[n] = if f_expanded < f_reflected {
+                (expanded, f_expanded)
+            } else {
+                (reflected, f_reflected)
+            };
+        } else if f_reflected < simplex[n - 1].1 {
+            simplex[n] = (reflected, f_reflected);
+        } else {
+            let contracted = &centroid + &((&simplex[n].0 - &centroid) * rho);
+            let f_contracted = f(&contracted);
+            if f_contracted < simplex[n].1 {
+                simplex[n] = (contracted, f_contracted);
+            } else {
+                // shrinking towards the best point
+                let best = simplex[0].0.clone();
+                for point in simplex.iter_mut().skip(1) {
+                    point.0 = &best + &((&point.0 - &best) * sigma);
+                    point.1 = f(&point.0);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    simplex.swap_remove(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimizes_with_mutable_state() {
+        let mut evaluations = 0;
+        let paraboloid = |x: &Array1<f64>| -> f64 {
+            evaluations += 1;
+            (x[0] - 2.0).powi(2) + 3.0 * (x[1] + 1.0).powi(2)
+        };
+        let (x, value) = nelder_mead(paraboloid, &array![0.0, 0.0], &array![1.0, 1.0], 1e-14, 500);
+        assert!((x[0] - 2.0).abs() < 1e-5 && (x[1] + 1.0).abs() < 1e-5);
+        assert!(value < 1e-10);
+        assert!(evaluations > 3 && evaluations < 2000);
+    }
+
+    #[test]
+    fn respects_infinite_constraints() {
+        // minimum at x = -1, constrained to x >= 0
+        let f = |x: &Array1<f64>| -> f64 {
+            if x[0] < 0.0 {
+                f64::INFINITY
+            } else {
+                (x[0] + 1.0).powi(2)
+            }
+        };
+        let (x, _) = nelder_mead(f, &array![2.0], &array![0.5], 1e-14, 500);
+        assert!(x[0] >= 0.0 && x[0] < 1e-4);
+    }
+}
@@ -0,0 +1,202 @@
+//! # Figures
+//!
+//! Functions to render the most common engineering plots of a simulated [`System`](../../core/system/struct.System.html).
+//! All figures are written into files, since no display is assumed to be available. The image format
+//! is chosen by the extension of `file_name`, which must be either `.png` or `.svg`.
+//! **gnuplot** must be installed and available in PATH.
+//!
+//! Except for [`performance_curves()`](fn.performance_curves.html), the plotted data is the one stored in
+//! the last cycle, the same returned by [`System::trace()`](../../core/system/struct.System.html#method.trace).
+//!
+//! ### Example
+//! ```no_run
+//! use lmb::Gas;
+//! use lmb::plot::figures;
+//! use lmb_engine_simulator as lmb;
+//!
+//! let gas = Gas::new("air.json");
+//! let mut builder = lmb::SystemBuilder::new();
+//! builder
+//!     .add_engine("engine.json", &gas)
+//!     .add_environment("intake_port", &gas)
+//!     .add_environment("exhaust_port", &gas)
+//!     .connect_from_to("valve_int", "intake_port")
+//!     .connect_from_to("valve_exh", "exhaust_port");
+//! let mut system = builder.build_system();
+//! system.advance_to_steady_state();
+//!
+//! figures::pressure_vs_angle(&system, &["cyl_1"], "pressure.png").unwrap();
+//! figures::log_p_log_v(&system, "cyl_1", "log_p_log_v.svg").unwrap();
+//! figures::valve_mass_flow(&system, &["valve_int", "valve_exh"], "valves.png").unwrap();
+//! figures::heat_release_rate(&system, "cyl_1", "hrr.png").unwrap();
+//! figures::performance_curves(system.engine().unwrap(), "performance.png").unwrap();
+//! ```
+
+use crate::analysis::heat_release::apparent_heat_release_rate;
+use crate::core::system::System;
+use crate::engine::engine::Engine;
+use gnuplot::{AxesCommon, Caption, Color, Figure};
+use ndarray::*;
+
+const WIDTH: u32 = 800; // [px]
+const HEIGHT: u32 = 600; // [px]
+const COLORS: [&str; 6] = ["black", "red", "blue", "dark-green", "orange", "purple"];
+
+/// Plots the pressure versus crank-angle of the cylinders in `cylinders`
+pub fn pressure_vs_angle(system: &System, cylinders: &[&str], file_name: &str) -> Result<(), String> {
+    let mut fg = Figure::new();
+    let axes = fg.axes2d();
+    axes.set_x_label("crank-angle [deg]", &[])
+        .set_y_label("pressure [bar]", &[])
+        .set_x_grid(true)
+        .set_y_grid(true);
+    for (i, cyl) in cylinders.iter().enumerate() {
+        let angle = system.trace(cyl, "crank-angle [deg]")?;
+        let press = system.trace(cyl, "pressure [bar]")?;
+        let (angle, press) = sort_by_angle(angle, press);
+        axes.lines(&angle, &press, &[Caption(cyl), Color(COLORS[i % COLORS.len()])]);
+    }
+    save(&mut fg, file_name)
+}
+
+/// Plots the pressure versus volume of `cylinder` in logarithmic scale
+pub fn log_p_log_v(system: &System, cylinder: &str, file_name: &str) -> Result<(), String> {
+    let press = system.trace(cylinder, "pressure [bar]")?;
+    let vol = system.trace(cylinder, "volume [cm³]")?;
+    let mut fg = Figure::new();
+    fg.axes2d()
+        .set_x_label("volume [cm³]", &[])
+        .set_y_label("pressure [bar]", &[])
+        .set_x_log(Some(10.0))
+        .set_y_log(Some(10.0))
+        .set_x_grid(true)
+        .set_y_grid(true)
+        .lines(vol, press, &[Caption(cylinder), Color(COLORS[0])]);
+    save(&mut fg, file_name)
+}
+
+/// Plots the mass flow versus crank-angle of the valves in `valves`. Positive values enter the cylinder.
+pub fn valve_mass_flow(system: &System, valves: &[&str], file_name: &str) -> Result<(), String> {
+    let mut fg = Figure::new();
+    let axes = fg.axes2d();
+    axes.set_x_label("crank-angle [deg]", &[])
+        .set_y_label("mass flow [g/s]", &[])
+        .set_x_grid(true)
+        .set_y_grid(true);
+    for (i, valve) in valves.iter().enumerate() {
+        let angle = system.trace(valve, "crank-angle [deg]")?;
+        let mass_flow = system.trace(valve, "mass flow [kg/s]")?;
+        let (angle, mass_flow) = sort_by_angle(angle, mass_flow);
+        let mass_flow: Vec<f64> = mass_flow.iter().map(|m| m * 1e3).collect();
+        axes.lines(&angle, &mass_flow, &[Caption(valve), Color(COLORS[i % COLORS.len()])]);
+    }
+    save(&mut fg, file_name)
+}
+
+/// Plots the apparent heat-release rate versus crank-angle of `cylinder`.
+/// The rate is estimated from the stored pressure, volume and temperature by a single-zone first law analysis.
+pub fn heat_release_rate(system: &System, cylinder: &str, file_name: &str) -> Result<(), String> {
+    let angle = system.trace(cylinder, "crank-angle [deg]")?;
+    let press = system.trace(cylinder, "pressure [bar]")?;
+    let vol = system.trace(cylinder, "volume [cm³]")?;
+    let temp = system.trace(cylinder, "temperature [K]")?;
+    let cycle_angle = system.engine().map_or(720.0, |eng| eng.cycle_angle().to_degrees());
+    let hrr = apparent_heat_release_rate(angle, press, vol, temp, cycle_angle);
+    let (angle, hrr) = sort_by_angle(angle.slice(s![1..]), hrr.view());
+    let mut fg = Figure::new();
+    fg.axes2d()
+        .set_x_label("crank-angle [deg]", &[])
+        .set_y_label("heat-release rate [J/CA-deg]", &[])
+        .set_x_grid(true)
+        .set_y_grid(true)
+        .lines(&angle, &hrr, &[Caption(cylinder), Color(COLORS[0])]);
+    save(&mut fg, file_name)
+}
+
+/// Plots the performance curves of all speeds simulated by `engine`: power, torque, IMEP,
+/// thermal efficiency, volumetric efficiency and residual mass fraction.
+pub fn performance_curves(engine: &Engine, file_name: &str) -> Result<(), String> {
+    let op = engine.operat_param();
+    if op.speed().is_empty() {
+        return Err("the engine has no operational parameters stored".to_string());
+    }
+    let curves: [(&str, &Vec<f64>); 6] = [
+        ("Power [W]", op.power()),
+        ("Torque [Nm]", op.torque()),
+        ("IMEP [bar]", op.imep()),
+        ("Efficiency [%]", op.thermal_effic()),
+        ("Volumetric effic [%]", op.vol_effic()),
+        ("Residual mass [%]", op.residual_mass()),
+    ];
+    let mut fg = Figure::new();
+    fg.set_multiplot_layout(3, 2);
+    for (label, values) in curves.iter() {
+        fg.axes2d()
+            .set_x_label("Speed [RPM]", &[])
+            .set_y_label(label, &[])
+            .set_x_grid(true)
+            .set_y_grid(true)
+            .lines_points(op.speed(), *values, &[Color(COLORS[0])]);
+    }
+    save(&mut fg, file_name)
+}
+
+/// Reorders the data of one cycle so that crank-angle is ascending, removing the jump from the end of the cycle to 0 CA-deg
+fn sort_by_angle(angle: ArrayView1<f64>, values: ArrayView1<f64>) -> (Vec<f64>, Vec<f64>) {
+    let start = (1..angle.len())
+        .find(|i| angle[*i] < angle[*i - 1])
+        .unwrap_or(0);
+    let angle: Vec<f64> = angle.iter().cycle().skip(start).take(angle.len()).cloned().collect();
+    let values: Vec<f64> = values.iter().cycle().skip(start).take(values.len()).cloned().collect();
+    (angle, values)
+}
+
+/// Writes the figure into `file_name` as `.png` or `.svg`
+fn save(fg: &mut Figure, file_name: &str) -> Result<(), String> {
+    let result = if file_name.ends_with(".png") {
+        fg.save_to_png(file_name, WIDTH, HEIGHT)
+    } else if file_name.ends_with(".svg") {
+        fg.save_to_svg(file_name, WIDTH, HEIGHT)
+    } else {
+        return Err(format!(
+            "unknown image format of '{}'. It must be either `.png` or `.svg`",
+            file_name
+        ));
+    };
+    result.map_err(|err| format!("unable to write '{}'\n {}", file_name, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_one_cycle_by_angle() {
+        let angle = array![540.0, 600.0, 660.0, 0.0, 60.0, 120.0];
+        let press = array![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let (sorted_angle, sorted_press) = sort_by_angle(angle.view(), press.view());
+        assert_eq!(sorted_angle, vec![0.0, 60.0, 120.0, 540.0, 600.0, 660.0]);
+        assert_eq!(sorted_press, vec![4.0, 5.0, 6.0, 1.0, 2.0, 3.0]);
+
+        // a two-stroke cycle
+        let angle = array![300.0, 0.0, 100.0, 200.0];
+        let (sorted_angle, sorted_press) = sort_by_angle(angle.view(), press.slice(s![..4]));
+        assert_eq!(sorted_angle, vec![0.0, 100.0, 200.0, 300.0]);
+        assert_eq!(sorted_press, vec![2.0, 3.0, 4.0, 1.0]);
+    }
+
+    #[test]
+    fn keeps_ascending_angles() {
+        let angle = array![0.0, 180.0, 360.0];
+        let press = array![3.0, 2.0, 1.0];
+        let (sorted_angle, sorted_press) = sort_by_angle(angle.view(), press.view());
+        assert_eq!(sorted_angle, angle.to_vec());
+        assert_eq!(sorted_press, press.to_vec());
+    }
+
+    #[test]
+    fn rejects_unknown_image_format() {
+        let mut fg = Figure::new();
+        assert!(save(&mut fg, "figure.jpg").is_err());
+    }
+}
@@ -0,0 +1,2 @@
+//! Contains functions to render engineering plots from the stored data via **gnuplot**
+pub mod figures;
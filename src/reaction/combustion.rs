@@ -19,6 +19,16 @@ pub trait Combustion: DynClone {
     ) -> Array1<f64>;
     /// Returns the initial of combustion phase in crank-angle radians
     fn ini_combustion(&self) -> f64;
+    /// Returns the Wiebe function of the model, if it uses one
+    fn wiebe_function(&self) -> Option<&WiebeFunction> {
+        None
+    }
+    /// Set the initial of combustion phase, `ign_angle` in [CA-deg], and the Wiebe function of the model
+    fn set_wiebe_function(&mut self, _ign_angle: f64, _wiebe: WiebeFunction) -> Result<(), String> {
+        Err(format!("model `{}` does not use a Wiebe function", self.model_name()))
+    }
+    /// Set the crank-angle, in radians, of a full cycle: `4*PI` for four-stroke and `2*PI` for two-stroke engines
+    fn set_cycle_angle(&mut self, _cycle_angle: f64) {}
 }
 
 dyn_clone::clone_trait_object!(Combustion);
@@ -36,11 +46,13 @@ pub struct TwoZoneCombustion {
     air_fuel_ratio: f64,
     is_comb_ready: bool,
     wiebe_function: WiebeFunction,
+    cycle_angle: f64, // [CA radian]
 }
 impl TwoZoneCombustion {
     /// Creates a TwoZoneCombustion object. Inputs are: `ign_angle` [CA-deg]; relative air-fuel ratio `afr`;
     /// a `wiebe` function; a reference `gas` (usually the same `gas` of the object in which the model is operating at);
-    /// `fuel` object; `air_comp` contains the composition of the reference air"
+    /// `fuel` object; `air_comp` contains the composition of the reference air".
+    /// The cycle is the four-stroke one, 720 CA-deg, until it is changed by `set_cycle_angle()`
     pub fn new(
         ign_angle: f64,
         afr: f64,
@@ -50,15 +62,16 @@ impl TwoZoneCombustion {
         air_comp: &Gas,
     ) -> Result<TwoZoneCombustion, String> {
         let ign_angle = ign_angle.to_radians();
+        let cycle_angle = 4.0 * PI;
         let mut end_combustion = ign_angle + wiebe_function.comb_duration;
-        if end_combustion > 4.0 * PI {
-            end_combustion -= 4.0 * PI;
+        if end_combustion > cycle_angle {
+            end_combustion -= cycle_angle;
         }
         let fuel = fuel.clone();
 
         // burned-zone mole fraction - Fixed composition: assuming complete combustion
         let mut comb_species = vec!["O2", "CO2", "H2O", "O2", "N2", "CO", "H2"];
-        comb_species.push(fuel.name());
+        fuel.species().iter().for_each(|(s, _)| comb_species.push(s));
         for s in comb_species {
             if !gas.contains_specie(s) {
                 return Err(format!("Error at TwoZoneCombustion::new()\n Specie {} no found in `gas`", s));
@@ -126,9 +139,19 @@ impl TwoZoneCombustion {
             air_fuel_ratio: afr,
             is_comb_ready: false,
             wiebe_function,
+            cycle_angle,
         })
     }
 
+    // crank-angle from the initial of combustion, wrapping at the end of the cycle
+    fn unwrapped_angle(&self, angle: f64) -> f64 {
+        if angle < self.ini_combustion {
+            angle + self.cycle_angle
+        } else {
+            angle
+        }
+    }
+
     fn has_started(&self, angle: f64) -> bool {
         // checking if combustion has started
         if self.delta_angle < 0.0 {
@@ -164,7 +187,7 @@ impl Combustion for TwoZoneCombustion {
                 * self.fuel.lhv()
                 * self
                     .wiebe_function
-                    .derivative_burned_mass_frac(angle, self.ini_combustion)
+                    .derivative_burned_mass_frac(self.unwrapped_angle(angle), self.ini_combustion)
         } else {
             self.is_comb_ready = false;
             0.0
@@ -188,7 +211,7 @@ impl Combustion for TwoZoneCombustion {
         if self.has_started(angle) && self.is_comb_ready {
             burned_mass_frac = self
                 .wiebe_function
-                .burned_mass_frac(angle, self.ini_combustion);
+                .burned_mass_frac(self.unwrapped_angle(angle), self.ini_combustion);
             if burned_mass_frac > 0.993 {
                 return self.burned_zone.mole_frac().clone();
             }
@@ -219,6 +242,31 @@ impl Combustion for TwoZoneCombustion {
     fn ini_combustion(&self) -> f64 {
         self.ini_combustion
     }
+    fn wiebe_function(&self) -> Option<&WiebeFunction> {
+        Some(&self.wiebe_function)
+    }
+    fn set_wiebe_function(&mut self, ign_angle: f64, wiebe: WiebeFunction) -> Result<(), String> {
+        let ign_angle = ign_angle.to_radians();
+        let mut end_combustion = ign_angle + wiebe.comb_duration;
+        if end_combustion > self.cycle_angle {
+            end_combustion -= self.cycle_angle;
+        }
+        self.ini_combustion = ign_angle;
+        self.end_combustion = end_combustion;
+        self.delta_angle = end_combustion - ign_angle;
+        self.wiebe_function = wiebe;
+        self.is_comb_ready = false;
+        Ok(())
+    }
+    fn set_cycle_angle(&mut self, cycle_angle: f64) {
+        self.cycle_angle = cycle_angle;
+        let mut end_combustion = self.ini_combustion + self.wiebe_function.comb_duration;
+        if end_combustion > cycle_angle {
+            end_combustion -= cycle_angle;
+        }
+        self.end_combustion = end_combustion;
+        self.delta_angle = end_combustion - self.ini_combustion;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -237,29 +285,38 @@ impl WiebeFunction {
         }
     }
 
-    // angle and ini_combustion must be in crank-angle degree
-    fn burned_mass_frac(&self, angle: f64, ini_combustion: f64) -> f64 {
+    /// Returns the efficiency factor `a`
+    pub fn a(&self) -> f64 {
+        self.a
+    }
+
+    /// Returns the form factor `m`
+    pub fn m(&self) -> f64 {
+        self.m
+    }
+
+    /// Returns the combustion duration in [CA-deg]
+    pub fn comb_duration(&self) -> f64 {
+        self.comb_duration.to_degrees()
+    }
+
+    /// Returns the burned mass fraction. `angle` and `ini_combustion` must be in crank-angle radians.
+    /// `angle` is not wrapped at the end of the cycle: when combustion crosses it, `angle` must be unwrapped
+    /// by adding the cycle angle. Angles before `ini_combustion` return zero
+    pub fn burned_mass_frac(&self, angle: f64, ini_combustion: f64) -> f64 {
         let a = self.a;
         let m = self.m;
         let comb_duration = self.comb_duration;
-        let d_angle = if (angle - ini_combustion) >= 0.0 {
-            angle - ini_combustion
-        } else {
-            angle - ini_combustion + 4.0 * PI
-        };
+        let d_angle = (angle - ini_combustion).max(0.0);
         1.0 - (-a * (d_angle / comb_duration).powf(m + 1.0)).exp()
     }
 
-    // angle and ini_combustion must be in crank-angle degree
+    // angle and ini_combustion must be in crank-angle radians, with angle unwrapped as in `burned_mass_frac()`
     fn derivative_burned_mass_frac(&self, angle: f64, ini_combustion: f64) -> f64 {
         let a = self.a;
         let m = self.m;
         let comb_duration = self.comb_duration;
-        let d_angle = if (angle - ini_combustion) >= 0.0 {
-            angle - ini_combustion
-        } else {
-            angle - ini_combustion + 4.0 * PI
-        };
+        let d_angle = (angle - ini_combustion).max(0.0);
         let tmp = d_angle / comb_duration;
         a * (m + 1.0) / comb_duration * tmp.powf(m) * (-a * tmp.powf(m + 1.0)).exp()
     }
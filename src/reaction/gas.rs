@@ -136,14 +136,29 @@ impl Gas {
     /// //mix_mole_frac has: O2:0.21, N2:0.79
     /// ```
     pub fn if_mixed_with(&self, mass: f64, add_gas: Vec<(f64, &str)>) -> Array1<f64> {
+        let compositions: Vec<Array1<f64>> = add_gas
+            .iter()
+            .map(|(_, composition)| self.break_str_into_X_array(composition))
+            .collect();
+        self.if_mixed_with_array(
+            mass,
+            add_gas
+                .iter()
+                .zip(compositions.iter())
+                .map(|((m, _), mole_frac)| (*m, mole_frac))
+                .collect(),
+        )
+    }
+
+    /// Same as `if_mixed_with()`, but the compositions of the added gases are given as arrays of mole fractions
+    pub fn if_mixed_with_array(&self, mass: f64, add_gas: Vec<(f64, &Array1<f64>)>) -> Array1<f64> {
         if add_gas.iter().all(|(m, _)| *m == 0.0 as f64) {
             return self.mole_frac().clone();
         }
         let mut added_moles = Array::from_elem(self.num_species, 0.0);
-        for (m, composition) in add_gas.iter() {
-            let mole_frac = self.break_str_into_X_array(composition);
+        for (m, mole_frac) in add_gas.iter() {
             let molar_weight = mole_frac.dot(&self.species_molar_weight);
-            added_moles = added_moles + mole_frac * (*m) / molar_weight;
+            added_moles = added_moles + *mole_frac * (*m) / molar_weight;
         }
 
         let new_mole_frac: Array1<f64>;
@@ -209,6 +224,20 @@ impl Gas {
     pub fn mole_frac(&self) -> &Array1<f64> {
         &self.mole_frac
     }
+    /// array of species mass fraction
+    pub fn mass_frac(&self) -> Array1<f64> {
+        &self.mole_frac * &self.species_molar_weight / self.M()
+    }
+    /// array of species mole fraction of a mixture with the mass, in kg, of each specie given by `masses`
+    pub fn mole_frac_from_masses(&self, masses: &Array1<f64>) -> Array1<f64> {
+        let moles = masses.mapv(|m| m.max(0.0)) / &self.species_molar_weight;
+        let total_moles = moles.sum();
+        if total_moles > 0.0 {
+            moles / total_moles
+        } else {
+            self.mole_frac.clone()
+        }
+    }
     /// array of all species molar weight [kg/kmol]
     pub fn mole_weight(&self) -> &Array1<f64> {
         &self.species_molar_weight
@@ -320,7 +349,8 @@ impl Gas {
         &self.species_atoms[i]
     }
 
-    fn break_str_into_X_array(&self, mole_frac: &str) -> Array1<f64> {
+    /// Returns the array of mole fractions of a composition such as `"O2:0.21, N2:0.79"`
+    pub fn break_str_into_X_array(&self, mole_frac: &str) -> Array1<f64> {
         let strings: Vec<String> = mole_frac
             .replace(&[',', '\"'][..], "")
             .split_whitespace()
@@ -0,0 +1,228 @@
+use crate::core::traits::{SaveData, ZeroD, ZeroDim};
+use crate::numerics::ode_solvers as ode;
+use crate::reaction::gas::Gas;
+use crate::zero_dim::cylinder::{Crankshaft, Cylinder};
+use crate::{BasicProperties, FlowRatio};
+use ndarray::*;
+use std::f64::consts::PI;
+
+/// Zero-Dimensional crankcase of a crankcase-scavenged two-stroke engine. It shares the crankshaft of its
+/// [`Cylinder`](../cylinder/struct.Cylinder.html): the descending piston compresses the crankcase while it expands
+/// the cylinder, so the volume is the largest at top-dead-center and the smallest at bottom-dead-center.
+///
+/// The crank-angle is taken from the cylinder at every step and is provided to the connectors, i.e. the intake ports.
+pub struct Crankcase {
+    name: String,
+    cylinder: String,
+    gas: Gas,
+    mass: f64,   // [kg]
+    volume: f64, // [m³]
+    crankshaft: Crankshaft,
+    piston_area: f64,  // [m²]
+    displacement: f64, // [m³]
+    clearance: f64,    // [m³] - volume at bottom-dead-center
+    angle: f64,        // [CA radian]
+    sec_to_rad: f64,   // constant: 2*PI*speed
+    flow_ratio: FlowRatio,
+}
+
+impl Crankcase {
+    /// Creates the crankcase of `cylinder`, which must have a `crankcase_compression_ratio`
+    pub fn new(name: String, gas: &Gas, cylinder: &Cylinder) -> Result<Crankcase, String> {
+        let clearance = match cylinder.crankcase_clearance() {
+            Some(clearance) => clearance,
+            None => {
+                return Err(format!(
+                    "cylinder `{}` has no \"crankcase_compression_ratio\"",
+                    cylinder.name()
+                ))
+            }
+        };
+        let mut crankcase = Crankcase {
+            name,
+            cylinder: cylinder.name().to_string(),
+            gas: gas.clone(),
+            mass: 0.0,
+            volume: 0.0,
+            crankshaft: cylinder.crankshaft().clone(),
+            piston_area: cylinder.piston_area(),
+            displacement: cylinder.displacement(),
+            clearance,
+            angle: cylinder.crank_angle(),
+            sec_to_rad: 0.0,
+            flow_ratio: FlowRatio::new(),
+        };
+        let (volume, _) = crankcase.volume_at(crankcase.angle);
+        crankcase.volume = volume;
+        crankcase.mass = gas.P() * volume / (gas.R() * gas.T());
+        Ok(crankcase)
+    }
+
+    /// Returns the instant volume and volume's derivative with crank angle radian, respectively.
+    /// Input `angle` must be in radian, with the same reference as the cylinder crank-angle.
+    /// Outputs are `m³` and `m³/CA-rad`
+    pub fn volume_at(&self, angle: f64) -> (f64, f64) {
+        let (position, d_position) = self.crankshaft.kinematics(angle);
+        (
+            self.clearance + self.displacement - self.piston_area * position,
+            -self.piston_area * d_position,
+        )
+    }
+}
+
+impl ZeroDim for Crankcase {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn get_state(&self) -> BasicProperties<'_> {
+        BasicProperties {
+            name: self.name(),
+            pressure: self.gas.P(),
+            temperature: self.gas.T(),
+            cp: self.gas.cp(),
+            cv: self.gas.cv(),
+            cp_cv: self.gas.k(),
+            gas_const: self.gas.R(),
+            crank_angle: Some(self.angle),
+            mass_frac: self.gas.mass_frac(),
+        }
+    }
+    fn advance(&mut self, dt: f64) {
+        let cv = self.gas.R() / (self.gas.k() - 1.0);
+        let cv_inv = 1.0 / cv;
+        let system_equations = |time: &f64, x: &Array1<f64>, _: &Vec<f64>| -> Array1<f64> {
+            // x[0] = temperature, x[1] = mass
+            let (vol, d_vol) = self.volume_at(self.angle + time * self.sec_to_rad);
+            let press = x[1] * self.gas.R() * x[0] / vol;
+            let d_mass = self.flow_ratio.mass_flow;
+            let d_temp = cv_inv / x[1]
+                * (self.flow_ratio.enthalpy_flow - press * d_vol * self.sec_to_rad - cv * x[0] * d_mass);
+            array![d_temp, d_mass]
+        };
+
+        // Runge-Kutta 4th order solution
+        let ini_condition = array![self.gas.T(), self.mass];
+        let integrated = ode::rk4_step(system_equations, &ini_condition, &Vec::new(), &0.0, dt);
+        let temp = integrated[0];
+        let mass = integrated[1];
+
+        // species transport: the mass flow is constant during the step
+        let mole_frac = if self.flow_ratio.species_flow.len() == self.gas.num_species() {
+            let species_mass = self.gas.mass_frac() * self.mass + &self.flow_ratio.species_flow * dt;
+            self.gas.mole_frac_from_masses(&species_mass)
+        } else {
+            self.gas.mole_frac().clone()
+        };
+
+        // updating: angle, volume, pressure and temperature
+        self.angle = (self.angle + dt * self.sec_to_rad).rem_euclid(2.0 * PI);
+        let (vol, _) = self.volume_at(self.angle);
+        let press = mass * self.gas.R() * temp / vol;
+        self.gas.TPX_array(temp, press, &mole_frac);
+        self.mass = mass;
+        self.volume = vol;
+    }
+    fn update_flow_ratio(&mut self, total_flow_ratio: Vec<(&str, &FlowRatio)>) {
+        let mut flow_ratio = FlowRatio::new();
+        total_flow_ratio
+            .iter()
+            .for_each(|(_, f)| flow_ratio = &flow_ratio + *f);
+        self.flow_ratio = flow_ratio;
+    }
+    fn driving_cylinder(&self) -> Option<&str> {
+        Some(&self.cylinder)
+    }
+    fn set_crank_angle(&mut self, angle: f64, sec_to_rad: f64) {
+        self.angle = angle;
+        self.sec_to_rad = sec_to_rad;
+    }
+}
+
+impl SaveData for Crankcase {
+    fn get_headers(&self) -> String {
+        let species = self.gas.species().join("\t");
+        format!(
+            "crank-angle [deg]\tpressure [bar]\ttemperature [K]\tvolume [cm³]\tmass [mg]\t{}",
+            species
+        )
+    }
+    fn num_storable_variables(&self) -> usize {
+        5 + self.gas.num_species()
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        stack![
+            Axis(0),
+            array![
+                self.angle.to_degrees(),
+                self.gas.P() / 1e5,
+                self.gas.T(),
+                self.volume * 1e6,
+                self.mass * 1e6
+            ],
+            self.gas.mole_frac().clone()
+        ]
+    }
+}
+
+impl ZeroD for Crankcase {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::engine::Engine;
+
+    #[test]
+    fn volume_follows_the_piston() {
+        let gas = Gas::new("air.json");
+        let engine = Engine::new("engine_Ryobi.json", &gas).unwrap();
+        let cyl = &engine.cylinders()[0];
+        let crankcase = Crankcase::new("crankcase".to_string(), &gas, cyl).unwrap();
+
+        // largest at top-dead-center, with the crankcase compression ratio of 1.4
+        let (max_vol, d_vol) = crankcase.volume_at(0.0);
+        let (min_vol, _) = crankcase.volume_at(PI);
+        assert!((max_vol / min_vol - 1.4).abs() < 1e-9);
+        assert!((max_vol - min_vol - cyl.displacement()).abs() < 1e-12);
+        assert!(d_vol.abs() < 1e-12);
+
+        // the crankcase shrinks as much as the cylinder grows
+        for angle in [0.5, 1.0, 2.0, 4.0] {
+            let (vol, d_vol) = crankcase.volume_at(angle);
+            let (cyl_vol, cyl_d_vol) = cyl.volume_at(angle);
+            let (cyl_max_vol, _) = cyl.volume_at(PI);
+            assert!((vol - min_vol - (cyl_max_vol - cyl_vol)).abs() < 1e-12);
+            assert!((d_vol + cyl_d_vol).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn closed_crankcase_expands_adiabatically() {
+        let gas = Gas::new("air.json");
+        let engine = Engine::new("engine_Ryobi.json", &gas).unwrap();
+        // the engine starts at bottom-dead-center
+        let mut crankcase = Crankcase::new("crankcase".to_string(), &gas, &engine.cylinders()[0]).unwrap();
+        let sec_to_rad = engine.sec_to_rad();
+        crankcase.set_crank_angle(PI, sec_to_rad);
+        crankcase.update_flow_ratio(Vec::new());
+        let (mass, k, press) = (crankcase.mass, crankcase.gas.k(), crankcase.gas.P());
+
+        // to top-dead-center
+        let steps = 360;
+        let dt = PI / sec_to_rad / steps as f64;
+        for _ in 0..steps {
+            crankcase.advance(dt);
+        }
+        assert!((crankcase.volume - crankcase.volume_at(0.0).0).abs() < 1e-15);
+        assert_eq!(crankcase.mass, mass);
+        let expected = press / 1.4f64.powf(k);
+        assert!((crankcase.gas.P() - expected).abs() < 1e-2 * expected);
+        assert!(crankcase.gas.T() < gas.T());
+    }
+
+    #[test]
+    fn requires_crankcase_compression_ratio() {
+        let gas = Gas::new("air.json");
+        let engine = Engine::new("engine.json", &gas).unwrap();
+        assert!(Crankcase::new("crankcase".to_string(), &gas, &engine.cylinders()[0]).is_err());
+    }
+}
@@ -1,8 +1,8 @@
 use crate::numerics::ode_solvers as ode;
 use crate::reaction::gas::Gas;
 use crate::reaction::combustion::{Combustion};
-use crate::engine::json_reader::{JsonEngine, JsonCylinder, JsonValve};
-use crate::engine::engine::Injector;
+use crate::engine::json_reader::{JsonEngine, JsonCylinder, JsonValve, JsonScavenging, JsonBlowBy};
+use crate::engine::engine::{InjectionPulse, Injector, WallFilm};
 use crate::core::traits::{ZeroDim, SaveData, ZeroD};
 use crate::{BasicProperties, FlowRatio};
 use ansi_term::Style;
@@ -36,9 +36,27 @@ pub struct Cylinder {
     total_fresh_charge: f64,
     closed_phase_mass: f64,
     residual_mass_frac: f64,
-
-    // blow_by: bool,
-    // crevice: bool,
+    fresh_charge_co2: f64, // [kg] - CO2 in the fresh charge of the current cycle
+    intake_co2_frac: f64,  // CO2 mass fraction of the fresh charge
+    air_co2_frac: f64,     // CO2 mass fraction of the ambient air
+    egr_rate: f64,
+    cycle_angle: f64,          // [CA radian] - 4*PI for four-stroke and 2*PI for two-stroke engines
+    scavenging: Scavenging,
+    fresh_mass: f64,           // [kg] - fresh charge in the cylinder
+    fresh_temp: f64,           // [K] - temperature of the charge entering the cylinder
+    trapping_effic: f64,
+    crankcase_clearance: Option<f64>, // [m³] - crankcase volume at bottom-dead-center
+    direct_fuel: f64,     // [kg] - fuel per cycle of the direct injector
+    open_phase_fuel: f64, // [kg] - fuel directly injected during the open phase
+    trapped_air: f64,     // [kg] - fresh charge trapped in the last cycle, without fuel
+    deactivated: bool,    // valves held shut, no injection or combustion
+    blow_by: Option<BlowBy>,
+    crevice: Option<Crevice>,
+    blow_by_rate: f64,    // [kg/s] - leakage to the crankcase at the current step
+    blow_by_cycle: f64,   // [kg] - leakage accumulated in the current cycle
+    blow_by_mass: f64,    // [kg] - leakage of the last cycle
+    crevice_mass: f64,    // [kg] - gas stored in the crevices at wall temperature
+    burning_frac: Option<f64>, // fraction of the fuel out of the crevices, fixed when combustion starts
 }
 
 impl Cylinder {
@@ -63,6 +81,22 @@ impl Cylinder {
         } else if engine_info.speed < 0.0 {
             return Err(format!("engine speed cannot be lower than zero"));
         }
+        let strokes = engine_info.strokes.unwrap_or(4);
+        let cycle_angle = match strokes {
+            4 => 4.0 * PI,
+            2 => 2.0 * PI,
+            _ => return Err(format!("engine must have 2 or 4 strokes: {}", strokes)),
+        };
+        let has_ports = !cylinder_info.intake_ports.is_empty()
+            || !cylinder_info.transfer_ports.is_empty()
+            || !cylinder_info.exhaust_ports.is_empty();
+        if strokes == 4 && (has_ports || cylinder_info.crankcase_compression_ratio.is_some()) {
+            return Err(format!("cylinder `{}`: ports and crankcase compression require a two-stroke engine", name));
+        }
+        let scavenging = match &engine_info.scavenging {
+            Some(json) => Scavenging::from_json(json)?,
+            None => Scavenging::PerfectMixing,
+        };
 
         // In SI uinits
         let speed = engine_info.speed;
@@ -82,6 +116,13 @@ impl Cylinder {
             area: geometry.transverse_area,
         };
         let (volume, _) = Cylinder::calc_volume(&geometry, &crankshaft, ini_angle.to_radians());
+        let crankcase_clearance = match cylinder_info.crankcase_compression_ratio {
+            Some(ratio) if ratio <= 1.0 => {
+                return Err(format!("crankcase compression ratio must be greater than one: {}", ratio))
+            }
+            Some(ratio) => Some(geometry.displacement / (ratio - 1.0)),
+            None => None,
+        };
 
         // instantiating valves
         let intake_gas_comp = "O2:0.21, N2:0.79".to_string();
@@ -94,15 +135,42 @@ impl Cylinder {
         for v in exh_valves_info {
             exhaust_valves_info.push(ValveBasicInfo::new(v.name.clone(), v.opening_angle, v.closing_angle))
         }
+        for p in cylinder_info.transfer_ports.iter() {
+            let closing = crankshaft.port_closing(p.opening_angle.to_radians()).to_degrees();
+            intake_valves_info.push(ValveBasicInfo::new(p.name.clone(), p.opening_angle, closing))
+        }
+        for p in cylinder_info.exhaust_ports.iter() {
+            let closing = crankshaft.port_closing(p.opening_angle.to_radians()).to_degrees();
+            exhaust_valves_info.push(ValveBasicInfo::new(p.name.clone(), p.opening_angle, closing))
+        }
+
+        let int_valves = ValvesInfo::new(intake_valves_info, gas.break_str_into_X_array(&intake_gas_comp));
+        let exh_valves = ValvesInfo::new(exhaust_valves_info, gas.break_str_into_X_array(&exhaust_gas_comp));
 
-        let int_valves = ValvesInfo::new(intake_valves_info, intake_gas_comp.clone());
-        let exh_valves = ValvesInfo::new(exhaust_valves_info, exhaust_gas_comp.clone());
+        let blow_by = match &cylinder_info.blow_by {
+            Some(json) => Some(BlowBy::from_json(json)?),
+            None => None,
+        };
+        let crevice = match &cylinder_info.crevice {
+            Some(json) => Some(Crevice::new(json.volume)?),
+            None => None,
+        };
+        let crevice_mass = crevice.as_ref().map_or(0.0, |c| gas.P() * c.volume / (gas.R() * wall_temp));
 
         let store_species = match cylinder_info.store_species {
             Some(s) => s,
             None => false,
         };
 
+        let mut combustion = combustion;
+        combustion.set_cycle_angle(cycle_angle);
+
+        // first guess of the fuel per cycle: the displacement filled with the initial gas
+        let direct_fuel = match &injector {
+            Some(inj) if inj.inj_type() == "direct" => inj.calc_direct_injected_fuel(geometry.displacement * gas.rho()),
+            _ => 0.0,
+        };
+
         Ok(Cylinder {
             name,
             gas: gas.clone(),
@@ -116,7 +184,7 @@ impl Cylinder {
             crankshaft,
             piston,
             head,
-            heat_transfer: HeatTransfer {},
+            heat_transfer: HeatTransfer { multiplier: 1.0 },
             combustion,
             injector,
             int_valves,
@@ -128,23 +196,35 @@ impl Cylinder {
             total_fresh_charge: 0.0,
             closed_phase_mass: 0.0,
             residual_mass_frac: 0.0,
+            fresh_charge_co2: 0.0,
+            intake_co2_frac: 0.0,
+            air_co2_frac: Cylinder::co2_mass_frac(gas, gas.mole_frac()),
+            egr_rate: 0.0,
+            cycle_angle,
+            scavenging,
+            fresh_mass: 0.0,
+            fresh_temp: gas.T(),
+            trapping_effic: 0.0,
+            crankcase_clearance,
+            direct_fuel,
+            open_phase_fuel: 0.0,
+            trapped_air: 0.0,
+            deactivated: false,
+            blow_by,
+            crevice,
+            blow_by_rate: 0.0,
+            blow_by_cycle: 0.0,
+            blow_by_mass: 0.0,
+            crevice_mass,
+            burning_frac: None,
         })
     }
     /// Returns the instant volume and volume's derivative with crank angle radian, respectively.
     /// Input `angle` must be in radian.
     /// Outputs are `m³` and `m³/CA-rad`
     fn calc_volume(geometry: &Geometry, crankshaft: &Crankshaft, angle: f64) -> (f64, f64) {
-        let angle = angle - crankshaft.angle_tdc;
-        let sin_gama =
-            (crankshaft.crank * angle.sin() - crankshaft.eccentricity) / crankshaft.conrod;
-        let cos_gama = (1.0 - sin_gama * sin_gama).sqrt();
-        let piston_position = crankshaft.mech_total_length
-            - crankshaft.crank * angle.cos()
-            - crankshaft.conrod * cos_gama;
+        let (piston_position, dpiston_position_dangle) = crankshaft.kinematics(angle);
         let volume = geometry.transverse_area * piston_position + geometry.clearance;
-        let tmp = (crankshaft.crank / crankshaft.conrod) * (angle.cos() / cos_gama);
-        let dpiston_position_dangle =
-            crankshaft.crank * angle.sin() * (1.0 + tmp) - crankshaft.eccentricity * tmp;
         let d_volume = geometry.transverse_area * dpiston_position_dangle;
         (volume, d_volume)
     }
@@ -152,45 +232,67 @@ impl Cylinder {
     /// `d_angle` in crank angle radians
     fn closed_phase(&mut self, d_angle: f64) -> (f64, f64, f64, f64, Array1<f64>) {
         // Closed Phase -----------------------------------------------------------
+        let is_start = !self.closed_phase_start;
+        let trapped_mass = self.mass + self.crevice_mass;
+        let trapped_charge = if is_start {self.trapped_charge()} else {0.0};
         if let Some(inj) = &mut self.injector {
             if inj.inj_type() == "port" {
-                if self.fuel_mass == 0.0 {
-                    self.fuel_mass = inj.injected_fuel();
-                    self.total_injected_fuel = self.fuel_mass;
-                    self.closed_phase_mass = self.mass; 
-                    let backflow = self.int_valves.get_backflow();
-                    self.residual_mass_frac = 1.0 - (self.total_fresh_charge - backflow)/self.mass;
-                    self.total_fresh_charge = 0.0;
-                } else {
-                    inj.set_injected_fuel(0.0);
-                }
-            } else if inj.inj_type() == "direct" {
-                if self.fuel_mass == 0.0 {
-                    self.fuel_mass = inj.calc_direct_injected_fuel(0.0);
-                    self.total_injected_fuel = self.fuel_mass;
-                    self.closed_phase_mass = self.mass;
-                    let backflow = self.int_valves.get_backflow();
-                    self.residual_mass_frac = 1.0 - (self.total_fresh_charge - backflow)/self.mass;
-                    self.total_fresh_charge = 0.0;
+                // the port wall film keeps evaporating while the intake valves are closed
+                inj.deliver_port_fuel(0.0, d_angle / self.sec_to_rad);
+            }
+            if is_start {
+                self.closed_phase_mass = trapped_mass;
+                if inj.inj_type() == "direct" {
+                    // the fuel injected during the open phase is not part of the fresh charge
+                    self.direct_fuel = inj.calc_direct_injected_fuel(trapped_charge);
+                    self.fuel_mass = self.direct_fuel;
+                    self.residual_mass_frac = 1.0 - (trapped_charge + self.open_phase_fuel)/trapped_mass;
+                    self.trapped_air = trapped_charge;
                 } else {
-                    inj.set_injected_fuel(0.0);
+                    self.fuel_mass = inj.injected_fuel();
+                    self.residual_mass_frac = 1.0 - trapped_charge/trapped_mass;
+                    self.trapped_air = trapped_charge - self.fuel_mass;
                 }
+                self.total_injected_fuel = self.fuel_mass;
+                self.reset_fresh_charge();
+            } else {
+                inj.set_injected_fuel(0.0);
             }
-        } else {
-            self.closed_phase_mass = self.mass;
-            self.total_fresh_charge = 0.0;
+        } else if is_start {
+            // without fuel the trapped charge is all air, the residual gas is not
+            self.closed_phase_mass = trapped_mass;
+            self.trapped_air = trapped_charge;
+            self.reset_fresh_charge();
         }
-        
-        let heat_combustion = self.combustion.get_heat_release_rate(&self.gas, self.fuel_mass, self.angle);
-        let const_1 = 1.0 / (self.mass * self.gas.cv());
+        self.closed_phase_start = true;
+
+        // direct injection: the fuel evaporates taking its heat of vaporization from the charge
+        let injected_fuel = self.direct_injection(d_angle, is_start);
+        let fuel_rate = injected_fuel / d_angle; // [kg/CA radian]
+        let heat_vap = self.injector.as_ref().map_or(0.0, |inj| inj.fuel().heat_vap());
+
+        // the fuel stored in the crevices when combustion starts does not burn
+        if is_start {
+            self.burning_frac = None;
+        }
+        let burning_frac = self.burning_frac.unwrap_or(self.mass / trapped_mass);
+        let heat_combustion = self.combustion.get_heat_release_rate(&self.gas, self.fuel_mass * burning_frac, self.angle);
+        if heat_combustion != 0.0 {
+            self.burning_frac = Some(burning_frac);
+        }
+        let blow_by = self.blow_by_rate / self.sec_to_rad; // [kg/CA radian]
+        let cv = self.gas.cv();
         let closed_phase_equations = |angle: &f64, x: &Array1<f64>, _: &Vec<f64>| -> Array1<f64> {
             // x[0] = P
             let (vol, d_vol) = Cylinder::calc_volume(&self.geometry, &self.crankshaft, *angle);    
-            let temp = x[0]*vol/(self.mass*self.gas.R());
+            let mass = self.mass + (fuel_rate - blow_by) * (*angle - self.angle);
+            let temp = x[0]*vol/(mass*self.gas.R());
             let heat_transfer = self.heat_transfer.calculate(vol, temp, x[0], &self); // [J/s]
             let heat_transfer = heat_transfer / self.sec_to_rad; // [J/CA radian]
-            let d_temp = const_1 * (heat_combustion + heat_transfer - x[0] * d_vol); // [K/CA radian]
-            let d_press = x[0] * (d_temp / temp - d_vol / vol); // [Pa/CA radian]
+            let fuel_energy = fuel_rate * (self.gas.R() * temp - heat_vap); // [J/CA radian]
+            let blow_by_energy = blow_by * self.gas.R() * temp; // [J/CA radian]
+            let d_temp = 1.0 / (mass * cv) * (heat_combustion + heat_transfer - x[0] * d_vol + fuel_energy - blow_by_energy); // [K/CA radian]
+            let d_press = x[0] * (d_temp / temp + (fuel_rate - blow_by) / mass - d_vol / vol); // [Pa/CA radian]
             array![d_press]
         };
         let ini = array![self.gas.P()];
@@ -206,11 +308,48 @@ impl Cylinder {
         // let temp = closed_phase_integrated[0];  
         let press = closed_phase_integrated[0];       
         let (vol, _) = Cylinder::calc_volume(&self.geometry, &self.crankshaft, self.angle + d_angle);
-        let temp = press*vol/(self.mass*self.gas.R());
+        let mass = self.mass + injected_fuel - blow_by * d_angle;
+        let temp = press*vol/(mass*self.gas.R());
 
         // Estimating final compositions:
         let mole_frac = self.combustion.update_composition(&mut self.gas, self.mass, self.angle + d_angle, press, vol);
-        ( temp, press, self.mass, vol, mole_frac )
+        let mole_frac = if injected_fuel > 0.0 {
+            self.add_fuel(&mole_frac, injected_fuel)
+        } else {
+            mole_frac
+        };
+        ( temp, press, mass, vol, mole_frac )
+    }
+
+    /// `d_angle` in crank angle radians. The valves of a deactivated cylinder are held shut and
+    /// the trapped gas is compressed and expanded as a spring, losing heat to the walls
+    fn deactivated_phase(&mut self, d_angle: f64) -> (f64, f64, f64, f64, Array1<f64>) {
+        self.closed_phase_mass = self.mass + self.crevice_mass;
+        self.residual_mass_frac = 1.0;
+        self.trapped_air = 0.0;
+        self.fuel_mass = 0.0;
+        self.total_injected_fuel = 0.0;
+        self.reset_fresh_charge();
+
+        // the gas spring leaks to the crankcase
+        let blow_by = self.blow_by_rate / self.sec_to_rad; // [kg/CA radian]
+        let cv = self.gas.cv();
+        let gas_spring_equations = |angle: &f64, x: &Array1<f64>, _: &Vec<f64>| -> Array1<f64> {
+            // x[0] = P
+            let (vol, d_vol) = Cylinder::calc_volume(&self.geometry, &self.crankshaft, *angle);
+            let mass = self.mass - blow_by * (*angle - self.angle);
+            let temp = x[0]*vol/(mass*self.gas.R());
+            let heat_transfer = self.heat_transfer.calculate(vol, temp, x[0], self) / self.sec_to_rad; // [J/CA radian]
+            let blow_by_energy = blow_by * self.gas.R() * temp; // [J/CA radian]
+            let d_temp = 1.0 / (mass * cv) * (heat_transfer - x[0] * d_vol - blow_by_energy); // [K/CA radian]
+            let d_press = x[0] * (d_temp / temp - blow_by / mass - d_vol / vol); // [Pa/CA radian]
+            array![d_press]
+        };
+        let press = ode::rk4_step(gas_spring_equations, &array![self.gas.P()], &Vec::new(), &self.angle, d_angle)[0];
+        let (vol, _) = Cylinder::calc_volume(&self.geometry, &self.crankshaft, self.angle + d_angle);
+        let mass = self.mass - blow_by * d_angle;
+        let temp = press*vol/(mass*self.gas.R());
+        ( temp, press, mass, vol, self.gas.mole_frac().clone() )
     }
 
     /// `d_angle` in crank angle radians
@@ -222,21 +361,36 @@ impl Cylinder {
             self.int_valves.basic_info.iter_mut().for_each(|v| v.reset_back_flow());
             self.exh_valves.basic_info.iter_mut().for_each(|v| v.reset_back_flow());
             self.open_phase_start = true;
+            self.fresh_mass = 0.0;
+            self.open_phase_fuel = 0.0;
+
+            // EGR rate: the CO2 of the fresh charge above the one of the ambient air comes from recirculated exhaust gas
+            let exhaust_co2_frac = Cylinder::co2_mass_frac(&self.gas, self.gas.mole_frac());
+            self.egr_rate = Cylinder::egr_from_co2(self.intake_co2_frac, exhaust_co2_frac, self.air_co2_frac);
         }
 
         let cv = self.gas.cv();
         let cv_inv = 1.0 / cv;
-        let mass_flow = (self.int_valves.flow_info.mass_flow + self.exh_valves.flow_info.mass_flow) / self.sec_to_rad; // [kg/CA radian]
-        let enthalpy_flow = (self.int_valves.flow_info.enthalpy_flow + self.exh_valves.flow_info.enthalpy_flow) / self.sec_to_rad; // [J/kg/CA radian]
+        let mass_flow = (self.int_valves.flow_info.mass_flow + self.exh_valves.flow_info.mass_flow - self.blow_by_rate) / self.sec_to_rad; // [kg/CA radian]
+        let enthalpy_flow = (self.int_valves.flow_info.enthalpy_flow + self.exh_valves.flow_info.enthalpy_flow
+            - self.blow_by_rate * self.gas.cp() * self.gas.T()) / self.sec_to_rad; // [J/kg/CA radian]
+
+        // direct injection: the fuel evaporates taking its heat of vaporization from the charge
+        let direct_fuel = self.direct_injection(d_angle, false);
+        let fuel_rate = direct_fuel / d_angle; // [kg/CA radian]
+        let heat_vap = self.injector.as_ref().map_or(0.0, |inj| inj.fuel().heat_vap());
+        let cp = self.gas.cp();
+
         let open_phase_equations = |angle: &f64, x: &Array1<f64>, _: &Vec<f64>| -> Array1<f64> {
             //x[0] = temperature, x[1] = mass
             let (vol, d_vol) = Cylinder::calc_volume(&self.geometry, &self.crankshaft, *angle);
             let press = x[1] * self.gas.R() * x[0] / vol;
             let heat_transfer = self.heat_transfer.calculate(vol, x[0], press, &self); // [J/s]
             let heat_transfer = heat_transfer / self.sec_to_rad; // [J/CA radian]
-            let d_mass = mass_flow; // [kg/CA radian]
+            let fuel_enthalpy = fuel_rate * (cp * x[0] - heat_vap); // [J/CA radian]
+            let d_mass = mass_flow + fuel_rate; // [kg/CA radian]
             let d_temp = cv_inv / x[1]
-                * (heat_transfer - press * d_vol + enthalpy_flow - cv * x[0] * d_mass); // [K/CA radian]
+                * (heat_transfer - press * d_vol + enthalpy_flow + fuel_enthalpy - cv * x[0] * d_mass); // [K/CA radian]
             array![d_temp, d_mass]
         };
 
@@ -263,31 +417,115 @@ impl Cylinder {
             fresh_charge_mass += v.get_charge(dt);
         });
         self.total_fresh_charge += fresh_charge_mass;
+        self.fresh_charge_co2 += fresh_charge_mass * Cylinder::co2_mass_frac(&self.gas, &self.int_valves.mole_frac);
+
+        // fresh charge lost through the exhaust
+        let exhaust_outflow = (-self.exh_valves.flow_info.mass_flow).max(0.0) * dt;
+        self.fresh_mass = (self.fresh_mass + fresh_charge_mass - self.exhaust_purity() * exhaust_outflow)
+            .clamp(0.0, mass);
 
         // injecting fuel
         self.fuel_mass = 0.0;
         let fuel_mass: f64;
-        let additional_mass: Vec<(f64, &str)>;
+        let mut injected_fuel = 0.0;
+        let fuel_mole_frac: Array1<f64>;
+        let additional_mass: Vec<(f64, &Array1<f64>)>;
         if let Some(inj) = &mut self.injector {
+            // port injected fuel is part of the fresh charge, directly injected fuel is added to it
+            let port_fuel: f64;
             if inj.inj_type() == "port" {
-                fuel_mass = inj.calc_port_injected_fuel(fresh_charge_mass);
+                fuel_mass = inj.deliver_port_fuel(fresh_charge_mass, dt);
+                port_fuel = fuel_mass;
             } else if inj.inj_type() == "direct" {
-                fuel_mass = 0.0;
+                fuel_mass = direct_fuel;
+                port_fuel = 0.0;
+                self.open_phase_fuel += direct_fuel;
             } else {panic!("Unknown injector type!")}
             inj.set_injected_fuel(fuel_mass + inj.injected_fuel());
-            additional_mass = vec![(fresh_charge_mass - fuel_mass, &self.int_valves.gas_comp), (fuel_mass, inj.fuel().composition())];
+            injected_fuel = port_fuel;
+            fuel_mole_frac = self.gas.break_str_into_X_array(inj.fuel().composition());
+            additional_mass = vec![(fresh_charge_mass - port_fuel, &self.int_valves.mole_frac), (fuel_mass, &fuel_mole_frac)];
         } else {
-            additional_mass = vec![(fresh_charge_mass, &self.int_valves.gas_comp)];
+            additional_mass = vec![(fresh_charge_mass, &self.int_valves.mole_frac)];
         }
-        let new_mole_frac = self.gas.if_mixed_with(self.mass, additional_mass); 
+        let new_mole_frac = match self.scavenging {
+            Scavenging::PerfectMixing => self.gas.if_mixed_with_array(self.mass, additional_mass),
+            _ => {
+                // the gas leaving through the exhaust differs from the cylinder content: balance of each specie
+                let mut species_mass = self.gas.mass_frac() * self.mass;
+                for flow in [&self.int_valves.flow_info, &self.exh_valves.flow_info].iter() {
+                    if flow.species_flow.len() == self.gas.num_species() {
+                        species_mass = species_mass + &flow.species_flow * dt;
+                    }
+                }
+                if let Some(inj) = &self.injector {
+                    // the port injected fuel replaces part of the fresh charge
+                    species_mass = species_mass - injected_fuel * &self.fresh_mass_frac();
+                    species_mass = species_mass + (injected_fuel + direct_fuel) * &inj.fuel().mass_frac_in(&self.gas);
+                }
+                self.gas.mole_frac_from_masses(&species_mass)
+            }
+        };
 
         ( temp, press, mass, vol, new_mole_frac )
     }
 
+    // fuel, in kg, directly injected during the next `d_angle`. Without injection pulses, all the fuel is injected
+    // at the start of the closed phase
+    fn direct_injection(&self, d_angle: f64, closed_phase_start: bool) -> f64 {
+        match &self.injector {
+            Some(inj) if inj.inj_type() == "direct" => {
+                if inj.pulses().is_empty() {
+                    if closed_phase_start {
+                        self.direct_fuel
+                    } else {
+                        0.0
+                    }
+                } else {
+                    self.direct_fuel * inj.injected_fraction(self.angle, self.angle + d_angle, self.cycle_angle)
+                }
+            }
+            _ => 0.0,
+        }
+    }
+
+    // exchange of gas with the crevices, which keep their pressure equal to the cylinder one at wall temperature.
+    // Filling, they take gas at the cylinder temperature; emptying, they release it at wall temperature.
+    // Returns the new temperature, pressure and mass of the cylinder
+    fn crevice_exchange(&mut self, temp: f64, press: f64, mass: f64, vol: f64) -> (f64, f64, f64) {
+        let crevice = match &self.crevice {
+            Some(c) => c,
+            None => return (temp, press, mass),
+        };
+        let gas_const = self.gas.R();
+        let cv = self.gas.cv();
+        let wall_temp = self.geometry.wall_temp;
+        let stored = press * crevice.volume / (gas_const * wall_temp);
+        let released = self.crevice_mass - stored;
+        let inflow_temp = if released > 0.0 {wall_temp} else {temp};
+        let new_mass = mass + released;
+        let new_temp = (mass * cv * temp + released * (cv + gas_const) * inflow_temp) / (new_mass * cv);
+        self.crevice_mass = stored;
+        (new_temp, new_mass * gas_const * new_temp / vol, new_mass)
+    }
+
+    // mole fraction of `mole_frac`, the composition of the cylinder mass, after adding `fuel_mass`, in kg, of fuel
+    fn add_fuel(&self, mole_frac: &Array1<f64>, fuel_mass: f64) -> Array1<f64> {
+        let inj = match &self.injector {
+            Some(inj) => inj,
+            None => return mole_frac.clone(),
+        };
+        let fuel_mole_frac = self.gas.break_str_into_X_array(inj.fuel().composition());
+        let moles = mole_frac * (self.mass / mole_frac.dot(self.gas.mole_weight()))
+            + fuel_mole_frac * (fuel_mass / inj.fuel().mole_weight());
+        let total = moles.sum();
+        moles / total
+    }
+
     fn is_open_phase(&self) -> bool {
-        if self.angle >= self.exh_valves.opening && self.angle <= self.int_valves.closing {
-            true
-        } else {false}
+        // the exhaust ports of two-stroke engines close after the transfer ports
+        let closing = self.int_valves.closing.max(self.exh_valves.closing);
+        self.angle >= self.exh_valves.opening && self.angle <= closing
     }
 
     pub fn fuel_mass(&self) -> f64 {
@@ -301,8 +539,265 @@ impl Cylinder {
 
     pub fn closed_phase_mass(&self) -> f64 {self.closed_phase_mass}
 
+    /// Returns the fresh charge, in kg, trapped in the last cycle, without fuel
+    pub fn trapped_air(&self) -> f64 {self.trapped_air}
+
+    /// Returns the relative air-fuel ratio of the charge trapped in the last cycle
+    pub fn air_fuel_ratio(&self) -> Option<f64> {
+        match &self.injector {
+            Some(inj) if self.total_injected_fuel > 0.0 => {
+                Some(self.trapped_air / (self.total_injected_fuel * inj.stoich_air_fuel_ratio()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Set the relative air-fuel ratio of the injector
+    pub fn set_air_fuel_ratio(&mut self, afr: f64) {
+        if let Some(inj) = &mut self.injector {
+            inj.set_air_fuel_ratio(afr);
+        }
+    }
+
+    /// Set the intake port wall film of the port injector, see [`WallFilm`](../../engine/engine/struct.WallFilm.html)
+    pub fn set_wall_film(&mut self, wall_film: Option<WallFilm>) -> Result<(), String> {
+        match &mut self.injector {
+            Some(inj) => inj.set_wall_film(wall_film),
+            None => Err(format!("cylinder `{}` has no injector", self.name)),
+        }
+    }
+
+    /// Set the injection pulses of the direct injector, see [`InjectionPulse`](../../engine/engine/struct.InjectionPulse.html)
+    pub fn set_injection_pulses(&mut self, pulses: Vec<InjectionPulse>) -> Result<(), String> {
+        match &mut self.injector {
+            Some(inj) => inj.set_pulses(pulses),
+            None => Err(format!("cylinder `{}` has no injector", self.name)),
+        }
+    }
+
     pub fn residual_mass_frac(&self) -> f64 {self.residual_mass_frac}
 
+    /// Returns the mass fraction of recirculated exhaust gas in the fresh charge. It is estimated, as in test benches,
+    /// by the ratio of the CO2 concentrations of the fresh charge and of the exhaust gas, both above the CO2
+    /// concentration of the ambient air, i.e. the initial gas of the cylinder.
+    pub fn egr_rate(&self) -> f64 {self.egr_rate}
+
+    // fresh charge trapped at the start of the closed phase: the charge delivered minus the intake backflow.
+    // Only two-stroke engines limit it to the fresh mass left in the cylinder, since part of the charge
+    // short-circuits to the exhaust during scavenging
+    fn trapped_charge(&mut self) -> f64 {
+        let delivered = self.total_fresh_charge - self.int_valves.get_backflow();
+        if self.cycle_angle < 4.0 * PI {
+            delivered.min(self.fresh_mass)
+        } else {
+            delivered
+        }
+    }
+
+    // end of the intake: the fresh charge of the cycle is finished
+    fn reset_fresh_charge(&mut self) {
+        if self.total_fresh_charge > 0.0 {
+            self.intake_co2_frac = self.fresh_charge_co2 / self.total_fresh_charge;
+            self.trapping_effic = (self.fresh_mass / self.total_fresh_charge).min(1.0);
+        }
+        self.total_fresh_charge = 0.0;
+        self.fresh_charge_co2 = 0.0;
+    }
+
+    // fraction of fresh charge in the gas leaving the cylinder through the exhaust
+    fn exhaust_purity(&self) -> f64 {
+        let purity = if self.mass > 0.0 {
+            (self.fresh_mass / self.mass).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        match self.scavenging {
+            Scavenging::PerfectMixing => purity,
+            Scavenging::PerfectDisplacement => {
+                if purity < 0.999 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Scavenging::BensonBrandham { short_circuit, displacement } => {
+                let zone_purity = if (1.0 - short_circuit) * self.total_fresh_charge < displacement * self.mass {
+                    0.0
+                } else {
+                    purity
+                };
+                let outflow = (-self.exh_valves.flow_info.mass_flow).max(0.0);
+                if outflow > 0.0 {
+                    let inflow = self.int_valves.flow_info.mass_flow.max(0.0);
+                    let short = (short_circuit * inflow).min(outflow);
+                    (short + (outflow - short) * zone_purity) / outflow
+                } else {
+                    zone_purity
+                }
+            }
+        }
+    }
+
+    // mass fraction of the charge entering the cylinder
+    fn fresh_mass_frac(&self) -> Array1<f64> {
+        let masses = &self.int_valves.mole_frac * self.gas.mole_weight();
+        let total = masses.sum();
+        masses / total
+    }
+
+    fn co2_mass_frac(gas: &Gas, mole_frac: &Array1<f64>) -> f64 {
+        if !gas.contains_specie("CO2") {
+            return 0.0;
+        }
+        let i = gas.get_specie_index("CO2");
+        mole_frac[i] * gas.mole_weight()[i] / mole_frac.dot(gas.mole_weight())
+    }
+
+    // mass fraction of recirculated exhaust gas from the CO2 mass fractions of the fresh charge, exhaust gas and ambient air
+    fn egr_from_co2(intake_co2_frac: f64, exhaust_co2_frac: f64, air_co2_frac: f64) -> f64 {
+        if exhaust_co2_frac > air_co2_frac {
+            ((intake_co2_frac - air_co2_frac) / (exhaust_co2_frac - air_co2_frac)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the instant volume and volume's derivative with crank angle radian, respectively.
+    /// Input `angle` must be in radian, with the same reference as the stored crank-angle.
+    /// Outputs are `m³` and `m³/CA-rad`
+    pub fn volume_at(&self, angle: f64) -> (f64, f64) {
+        Cylinder::calc_volume(&self.geometry, &self.crankshaft, angle)
+    }
+
+    /// Returns the distance, in m, between the piston and its top-dead-center position.
+    /// Input `angle` must be in radian, with the same reference as the stored crank-angle.
+    pub fn piston_position(&self, angle: f64) -> f64 {
+        self.crankshaft.kinematics(angle).0
+    }
+
+    /// Returns the crank-angle, in radian, at which a port opening at `opening_angle`, in radian, is covered again by the piston
+    pub fn port_closing(&self, opening_angle: f64) -> f64 {
+        self.crankshaft.port_closing(opening_angle)
+    }
+
+    /// Returns the crank-angle in CA radian
+    pub fn crank_angle(&self) -> f64 {self.angle}
+
+    /// Returns the displacement in m³
+    pub fn displacement(&self) -> f64 {self.geometry.displacement}
+
+    /// Returns the piston area in m²
+    pub fn piston_area(&self) -> f64 {self.geometry.transverse_area}
+
+    // crankcase volume, in m³, at bottom-dead-center
+    pub(crate) fn crankcase_clearance(&self) -> Option<f64> {self.crankcase_clearance}
+
+    pub(crate) fn crankshaft(&self) -> &Crankshaft {&self.crankshaft}
+
+    /// Returns the crank-angle, in radian, of a full cycle: `4*PI` for four-stroke and `2*PI` for two-stroke engines
+    pub fn cycle_angle(&self) -> f64 {self.cycle_angle}
+
+    /// Returns the scavenging model
+    pub fn scavenging(&self) -> Scavenging {self.scavenging}
+
+    /// Returns `true` if the cylinder is deactivated
+    pub fn is_deactivated(&self) -> bool {self.deactivated}
+
+    /// Deactivates or reactivates the cylinder. A deactivated cylinder has no injection or combustion and its gas
+    /// works as a spring. Its valves must be held shut as well, see `Engine::set_deactivated_of()`
+    pub fn set_deactivated(&mut self, deactivated: bool) {
+        self.deactivated = deactivated;
+    }
+
+    /// Returns the mass, in kg, that leaked to the crankcase in the last cycle
+    pub fn blow_by_mass(&self) -> f64 {self.blow_by_mass}
+
+    /// Returns the mass, in kg, currently stored in the crevices
+    pub fn crevice_mass(&self) -> f64 {self.crevice_mass}
+
+    /// Returns the blow-by model, if any
+    pub fn blow_by(&self) -> Option<&BlowBy> {self.blow_by.as_ref()}
+
+    /// Returns the crevice model, if any
+    pub fn crevice(&self) -> Option<&Crevice> {self.crevice.as_ref()}
+
+    /// Set the blow-by model. `None` seals the cylinder
+    pub fn set_blow_by(&mut self, blow_by: Option<BlowBy>) {
+        self.blow_by = blow_by;
+    }
+
+    // pressure, in Pa, of the crankcase element under the piston, which replaces the one of the blow-by model
+    pub(crate) fn set_crankcase_pressure(&mut self, press: f64) {
+        if let Some(blow_by) = &mut self.blow_by {
+            blow_by.crankcase_press = press;
+        }
+    }
+
+    /// Set the crevice model. `None` removes the crevices and their stored mass
+    pub fn set_crevice(&mut self, crevice: Option<Crevice>) {
+        self.crevice_mass = crevice.as_ref().map_or(0.0, |c| {
+            self.gas.P() * c.volume / (self.gas.R() * self.geometry.wall_temp)
+        });
+        self.crevice = crevice;
+    }
+
+    /// Set the scavenging model
+    pub fn set_scavenging(&mut self, scavenging: Scavenging) {
+        self.scavenging = scavenging;
+    }
+
+    /// Returns the trapping efficiency: the fraction of the charge delivered to the cylinder during the last
+    /// open phase that remained trapped at its end
+    pub fn trapping_effic(&self) -> f64 {self.trapping_effic}
+
+    /// Returns the heat transferred from the walls to the gas in `[J/s]`. Inputs must be in SI units.
+    pub fn wall_heat_transfer(&self, vol: f64, temp: f64, press: f64) -> f64 {
+        self.heat_transfer.calculate(vol, temp, press, self)
+    }
+
+    /// Returns the crank-angle, in degrees, at which the last intake valve closes
+    pub fn intake_closing(&self) -> f64 {self.int_valves.closing.to_degrees()}
+
+    /// Returns the crank-angle, in degrees, at which the first exhaust valve opens
+    pub fn exhaust_opening(&self) -> f64 {self.exh_valves.opening.to_degrees()}
+
+    /// Returns the crank-angle, in degrees, at which the last exhaust valve closes
+    pub fn exhaust_closing(&self) -> f64 {self.exh_valves.closing.to_degrees()}
+
+    /// Returns the crank-angle, in degrees, at which the last valve or port closes and the closed phase starts,
+    /// i.e. the intake closing of four-stroke engines
+    pub fn trapping_angle(&self) -> f64 {self.int_valves.closing.max(self.exh_valves.closing).to_degrees()}
+
+    /// Returns the effective compression ratio: the cylinder volume at `trapping_angle()` over the clearance volume
+    pub fn effective_compression_ratio(&self) -> f64 {
+        self.volume_at(self.trapping_angle().to_radians()).0 / self.geometry.clearance
+    }
+
+    /// Returns the effective expansion ratio: the cylinder volume at `exhaust_opening()` over the clearance volume
+    pub fn effective_expansion_ratio(&self) -> f64 {
+        self.volume_at(self.exhaust_opening().to_radians()).0 / self.geometry.clearance
+    }
+
+    /// Returns the effective displacement in m³: the cylinder volume at `trapping_angle()` less the clearance volume
+    pub fn effective_displacement(&self) -> f64 {
+        self.volume_at(self.trapping_angle().to_radians()).0 - self.geometry.clearance
+    }
+
+    /// Set the opening and closing angles, in degrees, of the valve `valve_name`, i.e. after changing its actuation
+    pub fn set_valve_events(&mut self, valve_name: &str, opening: f64, closing: f64) -> Result<(), String> {
+        // the closing angle follows the opening one
+        let closing = if closing < opening {closing + self.cycle_angle.to_degrees()} else {closing};
+        for valves in [&mut self.int_valves, &mut self.exh_valves] {
+            if let Some(v) = valves.basic_info.iter_mut().find(|v| v.name == valve_name) {
+                v.opening_angle = opening;
+                v.closing_angle = closing;
+                valves.update_events();
+                return Ok(());
+            }
+        }
+        Err(format!("valve `{}` is not connected to cylinder `{}`", valve_name, self.name))
+    }
+
     pub fn set_speed(&mut self, speed: f64) {
         if speed.is_sign_negative() {
             println!("Error at Cylinder::set_speed()");
@@ -338,12 +833,41 @@ impl Cylinder {
         self.geometry.total_volume = self.geometry.displacement + self.geometry.clearance;
     }
 
+    /// Set the temperature, in K, of the cylinder wall, head and piston
+    pub fn set_wall_temperature(&mut self, temp: f64) {
+        if temp <= 0.0 {
+            println!("Error at Cylinder::set_wall_temperature()");
+            println!(" wall temperature must be a positive value! {}", temp);
+            std::process::exit(1);
+        }
+        self.geometry.wall_temp = temp;
+        self.head.temperature = temp;
+        self.piston.temperature = temp;
+    }
+
+    /// Returns the temperature of the cylinder wall in K
+    pub fn wall_temperature(&self) -> f64 {self.geometry.wall_temp}
+
+    /// Set the multiplier of the heat transfer coefficient, `1.0` is the original correlation
+    pub fn set_heat_transfer_multiplier(&mut self, multiplier: f64) {
+        if multiplier.is_sign_negative() {
+            println!("Error at Cylinder::set_heat_transfer_multiplier()");
+            println!(" multiplier must be a positive value! {}", multiplier);
+            std::process::exit(1);
+        }
+        self.heat_transfer.multiplier = multiplier;
+    }
+
+    /// Returns the multiplier of the heat transfer coefficient
+    pub fn heat_transfer_multiplier(&self) -> f64 {self.heat_transfer.multiplier}
+
     pub fn set_store_species(&mut self, state: bool) {
         self.store_species = state;
     }
 
     pub fn set_combustion_model(&mut self, comb: Box<dyn Combustion>) {
         self.combustion = comb;
+        self.combustion.set_cycle_angle(self.cycle_angle);
     }
 
     /// Test the compression phase of a cylinder.
@@ -368,8 +892,8 @@ impl Cylinder {
             let vol = new_prop.3;
 
             // update: T, P, V, angle, mass and composition
-            self.angle = if self.angle + d_angle >= 4.0 * PI {
-                self.angle + d_angle - 4.0 * PI
+            self.angle = if self.angle + d_angle >= self.cycle_angle {
+                self.angle + d_angle - self.cycle_angle
             } else {
                 self.angle + d_angle
             };
@@ -413,27 +937,60 @@ impl ZeroDim for Cylinder {
             cp_cv: self.gas.k(),
             gas_const: self.gas.R(),
             crank_angle: Some(self.angle),
+            mass_frac: self.gas.mass_frac(),
+        }
+    }
+    fn get_state_to(&self, conn_name: &str) -> BasicProperties<'_> {
+        let mut state = self.get_state();
+        if self.scavenging == Scavenging::PerfectMixing
+            || !self.exh_valves.basic_info.iter().any(|v| v.name == conn_name)
+        {
+            return state;
         }
+
+        // splitting the cylinder content into fresh charge and burned gas
+        let purity = (self.fresh_mass / self.mass).clamp(0.0, 0.999);
+        let fresh_mass_frac = self.fresh_mass_frac();
+        let burned_mass_frac = ((&state.mass_frac - &(purity * &fresh_mass_frac)) / (1.0 - purity))
+            .mapv(|y| y.max(0.0));
+        let burned_mass_frac = &burned_mass_frac / burned_mass_frac.sum();
+        let burned_temp = ((state.temperature - purity * self.fresh_temp) / (1.0 - purity))
+            .max(self.fresh_temp.min(state.temperature));
+
+        let exhaust_purity = self.exhaust_purity();
+        state.temperature = exhaust_purity * self.fresh_temp + (1.0 - exhaust_purity) * burned_temp;
+        state.mass_frac = exhaust_purity * fresh_mass_frac + (1.0 - exhaust_purity) * burned_mass_frac;
+        state
     }
     fn advance(&mut self, dt: f64) {
         let d_angle = dt * self.sec_to_rad; // [CA radian]
+        self.blow_by_rate = match &self.blow_by {
+            Some(blow_by) => blow_by.mass_flow(&self.gas),
+            None => 0.0,
+        };
+        self.blow_by_cycle += self.blow_by_rate * dt;
         let new_prop: (f64, f64, f64, f64, Array1<f64>);
-        if !self.is_open_phase() {
+        if self.deactivated {
+            new_prop = self.deactivated_phase(d_angle);
+            // the cycle restarts when the cylinder is reactivated
+            self.open_phase_start = false;
+            self.closed_phase_start = false;
+        } else if !self.is_open_phase() {
             new_prop = self.closed_phase(d_angle);
             self.open_phase_start = false;
         } else {
             new_prop = self.open_phase(d_angle);
             self.closed_phase_start = false;
         }
-        let temp = new_prop.0;
-        let press = new_prop.1;
-        let mass = new_prop.2;
+        let (temp, press, mass) = self.crevice_exchange(new_prop.0, new_prop.1, new_prop.2, new_prop.3);
         let vol = new_prop.3;
         let mole_frac = new_prop.4;
 
         // update: T, P, V, angle, mass and composition
-        self.angle = if self.angle + d_angle >= 4.0 * PI {
-            self.angle + d_angle - 4.0 * PI
+        self.angle = if self.angle + d_angle >= self.cycle_angle {
+            self.blow_by_mass = self.blow_by_cycle;
+            self.blow_by_cycle = 0.0;
+            self.angle + d_angle - self.cycle_angle
         } else {
             self.angle + d_angle
         };
@@ -481,6 +1038,13 @@ impl ZeroDim for Cylinder {
                 }
             }
         }
+        // composition of the charge entering the cylinder through the intake valves
+        if intake_flow_ratio.mass_flow > 0.0 && intake_flow_ratio.species_flow.len() == self.gas.num_species() {
+            self.int_valves.mole_frac = self.gas.mole_frac_from_masses(&intake_flow_ratio.species_flow);
+        }
+        if intake_flow_ratio.mass_flow > 0.0 {
+            self.fresh_temp = intake_flow_ratio.enthalpy_flow / (intake_flow_ratio.mass_flow * self.gas.cp());
+        }
         self.int_valves.flow_info = intake_flow_ratio;
         self.exh_valves.flow_info = exhaust_flow_ratio;
     }
@@ -590,8 +1154,132 @@ impl Geometry {
     }
 }
 
+/// Scavenging model of the open phase. It sets the composition and temperature of the gas leaving the
+/// cylinder through the exhaust while fresh charge is entering it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scavenging {
+    /// The fresh charge pushes the burned gas out without mixing: only burned gas leaves the cylinder until it is fully replaced
+    PerfectDisplacement,
+    /// The fresh charge mixes instantly with the cylinder content, which leaves with its mean composition
+    PerfectMixing,
+    /// Benson-Brandham model: a fraction `short_circuit` of the delivered charge flows straight to the exhaust and the rest
+    /// displaces burned gas until a fraction `displacement` of the cylinder mass is scavenged, mixing perfectly afterwards.
+    /// It yields the S-shaped curve of scavenging efficiency against delivery ratio
+    BensonBrandham { short_circuit: f64, displacement: f64 },
+}
+
+impl Scavenging {
+    fn from_json(json: &JsonScavenging) -> Result<Scavenging, String> {
+        match json.model.as_str() {
+            "perfect displacement" => Ok(Scavenging::PerfectDisplacement),
+            "perfect mixing" => Ok(Scavenging::PerfectMixing),
+            "Benson-Brandham" => match (json.short_circuit, json.displacement) {
+                (Some(short_circuit), Some(displacement)) => {
+                    if !(0.0..1.0).contains(&short_circuit) || !(0.0..=1.0).contains(&displacement) {
+                        return Err(format!(
+                            "scavenging `short_circuit` and `displacement` must be between 0.0 and 1.0: {}, {}",
+                            short_circuit, displacement
+                        ));
+                    }
+                    Ok(Scavenging::BensonBrandham { short_circuit, displacement })
+                }
+                _ => Err("scavenging model \"Benson-Brandham\" requires \"short_circuit\" and \"displacement\"".to_string()),
+            },
+            model => Err(format!(
+                "unknown scavenging model \"{}\". Options: \"perfect displacement\", \"perfect mixing\" or \"Benson-Brandham\"",
+                model
+            )),
+        }
+    }
+}
+
+/// Leakage of gas past the piston rings to the crankcase, modelled as an orifice with the effective area of the
+/// ring gaps. The crankcase is held at a constant pressure, unless the system has a
+/// [`Crankcase`](../crankcase/struct.Crankcase.html) under the piston, whose pressure is used instead.
+/// No gas flows back to the cylinder
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlowBy {
+    ring_gap_area: f64,   // [m²]
+    crankcase_press: f64, // [Pa]
+    discharge_coeff: f64,
+}
+
+impl BlowBy {
+    /// `ring_gap_area` in mm² and `crankcase_press` in Pa
+    pub fn new(ring_gap_area: f64, crankcase_press: f64, discharge_coeff: f64) -> Result<BlowBy, String> {
+        if ring_gap_area < 0.0 {
+            return Err(format!("ring gap area cannot be lower than zero: {}", ring_gap_area));
+        } else if crankcase_press <= 0.0 {
+            return Err(format!("crankcase pressure must be greater than zero: {}", crankcase_press));
+        } else if discharge_coeff <= 0.0 || discharge_coeff > 1.0 {
+            return Err(format!("blow-by discharge coefficient must be between 0.0 and 1.0: {}", discharge_coeff));
+        }
+        Ok(BlowBy {
+            ring_gap_area: ring_gap_area * 1e-6,
+            crankcase_press,
+            discharge_coeff,
+        })
+    }
+
+    fn from_json(json: &JsonBlowBy) -> Result<BlowBy, String> {
+        BlowBy::new(
+            json.ring_gap_area,
+            json.crankcase_pressure.unwrap_or(101325.0),
+            json.discharge_coeff.unwrap_or(0.7),
+        )
+    }
+
+    /// Returns the effective area of the ring gaps in mm²
+    pub fn ring_gap_area(&self) -> f64 {self.ring_gap_area * 1e6}
+
+    /// Returns the crankcase pressure in Pa: the last one of the `Crankcase` element, if any
+    pub fn crankcase_pressure(&self) -> f64 {self.crankcase_press}
+
+    /// Returns the discharge coefficient of the ring gaps
+    pub fn discharge_coeff(&self) -> f64 {self.discharge_coeff}
+
+    /// Returns the mass flow, in kg/s, leaking from the cylinder `gas` to the crankcase
+    pub fn mass_flow(&self, gas: &Gas) -> f64 {
+        let press = gas.P();
+        if press <= self.crankcase_press {
+            return 0.0;
+        }
+        let k = gas.k();
+        let kp = k + 1.0;
+        let km = k - 1.0;
+        let press_ratio = self.crankcase_press / press;
+        let flow_function = if press_ratio > (2.0 / kp).powf(k / km) {
+            (2.0 * k / km * (press_ratio.powf(2.0 / k) - press_ratio.powf(kp / k))).sqrt()
+        } else {
+            // chocked flow: independent of the crankcase pressure
+            (k * (2.0 / kp).powf(kp / km)).sqrt()
+        };
+        self.discharge_coeff * self.ring_gap_area * press / (gas.R() * gas.T()).sqrt() * flow_function
+    }
+}
+
+/// Crevices between the piston, its rings and the liner. Their gas is at wall temperature and at the cylinder
+/// pressure: they store mass while the pressure rises and release it during the expansion
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crevice {
+    volume: f64, // [m³]
+}
+
+impl Crevice {
+    /// `volume` in cm³
+    pub fn new(volume: f64) -> Result<Crevice, String> {
+        if volume < 0.0 {
+            return Err(format!("crevice volume cannot be lower than zero: {}", volume));
+        }
+        Ok(Crevice { volume: volume * 1e-6 })
+    }
+
+    /// Returns the crevice volume in cm³
+    pub fn volume(&self) -> f64 {self.volume * 1e6}
+}
+
 #[derive(Debug, Clone)]
-struct Crankshaft {
+pub(crate) struct Crankshaft {
     conrod: f64,            // [m]
     crank: f64,             // [m]
     eccentricity: f64,      // [m]
@@ -609,6 +1297,40 @@ impl Crankshaft {
             angle_tdc: angle_tdc.to_radians(),
         }
     }
+
+    /// Returns the piston position, from top-dead-center, and its derivative with crank angle radian.
+    /// Input `angle` must be in radian. Outputs are `m` and `m/CA-rad`
+    pub(crate) fn kinematics(&self, angle: f64) -> (f64, f64) {
+        let angle = angle - self.angle_tdc;
+        let sin_gama = (self.crank * angle.sin() - self.eccentricity) / self.conrod;
+        let cos_gama = (1.0 - sin_gama * sin_gama).sqrt();
+        let piston_position = self.mech_total_length - self.crank * angle.cos() - self.conrod * cos_gama;
+        let tmp = (self.crank / self.conrod) * (angle.cos() / cos_gama);
+        let dpiston_position_dangle = self.crank * angle.sin() * (1.0 + tmp) - self.eccentricity * tmp;
+        (piston_position, dpiston_position_dangle)
+    }
+
+    /// Returns the angle, in radian, at which the piston returns to its position at `opening_angle`.
+    /// It lies in the other half of the revolution
+    pub(crate) fn port_closing(&self, opening_angle: f64) -> f64 {
+        let edge = self.kinematics(opening_angle).0;
+        let start = if (opening_angle - self.angle_tdc).rem_euclid(2.0 * PI) < PI {
+            PI
+        } else {
+            0.0
+        };
+        let (mut low, mut high) = (start + self.angle_tdc, start + PI + self.angle_tdc);
+        let is_low_above = self.kinematics(low).0 > edge;
+        for _ in 0..60 {
+            let mid = 0.5 * (low + high);
+            if (self.kinematics(mid).0 > edge) == is_low_above {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        0.5 * (low + high)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -637,11 +1359,14 @@ struct Head {
 }
 
 #[derive(Debug, Clone)]
-struct HeatTransfer {}
+struct HeatTransfer {
+    multiplier: f64, // [-] - scales the heat transfer coefficient
+}
 
 impl HeatTransfer {
     fn calculate(&self, vol: f64, temp: f64, press: f64, cyl: &Cylinder) -> f64 {
-        let heat_trans_coeff = 130.0
+        let heat_trans_coeff = self.multiplier
+            * 130.0
             * vol.powf(-0.06)
             * (press * 1e-5).powf(0.8)
             * temp.powf(-0.4)
@@ -659,16 +1384,29 @@ struct ValvesInfo {
     basic_info: Vec<ValveBasicInfo>,
     flow_info: FlowRatio,
     backflow: f64,
-    gas_comp: String,
+    mole_frac: Array1<f64>, // composition of the gas entering the cylinder
     opening: f64,
     closing: f64,
 }
 
 impl ValvesInfo {
-    fn new(basic_info: Vec<ValveBasicInfo>, gas_comp: String) -> ValvesInfo {
+    fn new(basic_info: Vec<ValveBasicInfo>, mole_frac: Array1<f64>) -> ValvesInfo {
+        let mut info = ValvesInfo {
+            basic_info,
+            flow_info: FlowRatio::new(),
+            backflow: 0.0,
+            mole_frac,
+            opening: 0.0,
+            closing: 0.0,
+        };
+        info.update_events();
+        info
+    }
+    // first opening and last closing of the valves
+    fn update_events(&mut self) {
         let mut opens_at = std::f64::INFINITY;
         let mut closes_at = std::f64::NEG_INFINITY;
-        for v in basic_info.iter() {
+        for v in self.basic_info.iter() {
             if v.opening_angle < opens_at {
                 opens_at = v.opening_angle;
             }
@@ -676,14 +1414,8 @@ impl ValvesInfo {
                 closes_at = v.closing_angle;
             }
         }
-        ValvesInfo {
-            basic_info,
-            flow_info: FlowRatio::new(),
-            backflow: 0.0,
-            gas_comp,
-            opening: opens_at.to_radians(),
-            closing: closes_at.to_radians(),
-        }
+        self.opening = opens_at.to_radians();
+        self.closing = closes_at.to_radians();
     }
     fn get_backflow(&mut self) -> f64 {
         let mut total_back_flow = 0.0;
@@ -736,3 +1468,321 @@ impl ValveBasicInfo {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reaction::combustion::NoCombustion;
+
+    // first cylinder of the `file_name` engine, without combustion and starting at bottom-dead-center
+    fn cylinder(file_name: &str, gas: &Gas) -> Cylinder {
+        cylinder_of(&read_engine(file_name), gas)
+    }
+
+    fn read_engine(file_name: &str) -> JsonEngine {
+        let json_file = std::fs::read_to_string(file_name).unwrap();
+        serde_json::from_str(&json_file).unwrap()
+    }
+
+    fn cylinder_of(engine: &JsonEngine, gas: &Gas) -> Cylinder {
+        let cyl = &engine.cylinders[0];
+        Cylinder::new(
+            cyl.name.clone(),
+            180.0,
+            engine,
+            cyl,
+            &cyl.intake_valves,
+            &cyl.exhaust_valves,
+            Box::new(NoCombustion::new()),
+            None,
+            gas,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn valve_events_wrap_across_the_cycle_end() {
+        let gas = Gas::new("air.json");
+        let mut cyl = cylinder("engine.json", &gas);
+        assert!((cyl.intake_closing() - 570.0).abs() < 1e-9);
+        // an intake closing past the end of the cycle follows the opening
+        cyl.set_valve_events("valve_int", 540.0, 50.0).unwrap();
+        assert!((cyl.intake_closing() - 770.0).abs() < 1e-9);
+        assert!((cyl.trapping_angle() - 770.0).abs() < 1e-9);
+        cyl.set_valve_events("valve_exh", 130.0, 375.0).unwrap();
+        assert!((cyl.exhaust_opening() - 130.0).abs() < 1e-9);
+        assert!((cyl.exhaust_closing() - 375.0).abs() < 1e-9);
+        assert!(cyl.set_valve_events("valve_egr", 0.0, 100.0).is_err());
+    }
+
+    #[test]
+    fn valve_events_of_two_stroke_cycle() {
+        let gas = Gas::new("air.json");
+        let mut engine = read_engine("engine_Ryobi.json");
+        engine.cylinders[0].exhaust_valves.push(JsonValve {
+            name: "valve_exh".to_string(),
+            opening_angle: 100.0,
+            closing_angle: 260.0,
+            diameter: 20.0,
+            max_lift: 6.0,
+            lift_profile: None,
+            discharge_coeff: None,
+        });
+        let mut cyl = cylinder_of(&engine, &gas);
+        // the closing is shifted by a 360 CA-deg cycle, the exhaust port still opens first
+        cyl.set_valve_events("valve_exh", 220.0, 20.0).unwrap();
+        assert!((cyl.exhaust_opening() - 100.0).abs() < 1e-9);
+        assert!((cyl.exhaust_closing() - 380.0).abs() < 1e-9);
+        assert!((cyl.trapping_angle() - 380.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn effective_ratios_follow_the_valve_events() {
+        let gas = Gas::new("air.json");
+        let mut cyl = cylinder("engine.json", &gas);
+        let clearance = cyl.geometry.clearance;
+        let volume = |cyl: &Cylinder, angle: f64| Cylinder::calc_volume(&cyl.geometry, &cyl.crankshaft, angle.to_radians()).0;
+        // intake closing at 570 and exhaust opening at 130 CA-deg
+        assert!((cyl.effective_compression_ratio() - volume(&cyl, 570.0) / clearance).abs() < 1e-9);
+        assert!((cyl.effective_expansion_ratio() - volume(&cyl, 130.0) / clearance).abs() < 1e-9);
+        assert!((cyl.effective_displacement() - (volume(&cyl, 570.0) - clearance)).abs() < 1e-15);
+        assert!(cyl.effective_compression_ratio() < cyl.geometry.compression_ratio);
+        assert!(cyl.effective_expansion_ratio() < cyl.geometry.compression_ratio);
+        // closing at bottom-dead-center traps the whole cylinder
+        cyl.set_valve_events("valve_int", 340.0, 540.0).unwrap();
+        assert!((cyl.effective_compression_ratio() - cyl.geometry.compression_ratio).abs() < 1e-9);
+        assert!((cyl.effective_displacement() - cyl.geometry.displacement).abs() < 1e-15);
+        // a closing past the end of the cycle has the volume of the next cycle
+        cyl.set_valve_events("valve_int", 540.0, 50.0).unwrap();
+        assert!((cyl.effective_compression_ratio() - volume(&cyl, 50.0) / clearance).abs() < 1e-9);
+        assert!((cyl.effective_displacement() - cyl.volume_at(50f64.to_radians()).0 + clearance).abs() < 1e-15);
+    }
+
+    #[test]
+    fn egr_rate_discounts_ambient_co2() {
+        // without CO2 in the ambient air, the EGR rate is the ratio of the CO2 concentrations
+        assert!((Cylinder::egr_from_co2(0.03, 0.15, 0.0) - 0.2).abs() < 1e-12);
+        assert!((Cylinder::egr_from_co2(0.03, 0.15, 0.01) - 0.02 / 0.14).abs() < 1e-12);
+        // fresh air only
+        assert_eq!(Cylinder::egr_from_co2(0.01, 0.15, 0.01), 0.0);
+        assert_eq!(Cylinder::egr_from_co2(0.005, 0.15, 0.01), 0.0);
+        assert_eq!(Cylinder::egr_from_co2(0.2, 0.15, 0.01), 1.0);
+        assert_eq!(Cylinder::egr_from_co2(0.01, 0.01, 0.01), 0.0);
+    }
+
+    #[test]
+    fn co2_mass_fraction_of_mixture() {
+        let gas = Gas::new("air.json");
+        assert_eq!(Cylinder::co2_mass_frac(&gas, gas.mole_frac()), 0.0);
+        let mole_frac = gas.break_str_into_X_array("N2:0.5, CO2:0.5");
+        let weight = gas.mole_weight();
+        let (n2, co2) = (weight[gas.get_specie_index("N2")], weight[gas.get_specie_index("CO2")]);
+        assert!((Cylinder::co2_mass_frac(&gas, &mole_frac) - co2 / (n2 + co2)).abs() < 1e-12);
+    }
+
+    fn scavenging(model: &str, short_circuit: Option<f64>, displacement: Option<f64>) -> Result<Scavenging, String> {
+        Scavenging::from_json(&JsonScavenging { model: model.to_string(), short_circuit, displacement })
+    }
+
+    #[test]
+    fn exhaust_purity_of_scavenging_models() {
+        let gas = Gas::new("air.json");
+        let mut cyl = cylinder("engine_Ryobi.json", &gas);
+        cyl.mass = 1e-4;
+        cyl.fresh_mass = 0.5e-4;
+        cyl.total_fresh_charge = 0.2e-4;
+
+        cyl.set_scavenging(Scavenging::PerfectMixing);
+        assert!((cyl.exhaust_purity() - 0.5).abs() < 1e-12);
+
+        // burned gas leaves until it is fully replaced
+        cyl.set_scavenging(Scavenging::PerfectDisplacement);
+        assert_eq!(cyl.exhaust_purity(), 0.0);
+        cyl.fresh_mass = cyl.mass;
+        assert_eq!(cyl.exhaust_purity(), 1.0);
+        cyl.fresh_mass = 0.5e-4;
+
+        // during displacement only the short-circuited charge leaves with the exhaust
+        cyl.set_scavenging(scavenging("Benson-Brandham", Some(0.15), Some(0.3)).unwrap());
+        assert_eq!(cyl.exhaust_purity(), 0.0);
+        cyl.int_valves.flow_info.mass_flow = 0.02;
+        cyl.exh_valves.flow_info.mass_flow = -0.01;
+        assert!((cyl.exhaust_purity() - 0.3).abs() < 1e-12);
+        // then the rest of the outflow is mixed
+        cyl.total_fresh_charge = 0.5e-4;
+        assert!((cyl.exhaust_purity() - (0.003 + 0.007 * 0.5) / 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reads_scavenging_models() {
+        assert_eq!(scavenging("perfect mixing", None, None), Ok(Scavenging::PerfectMixing));
+        assert_eq!(scavenging("perfect displacement", None, None), Ok(Scavenging::PerfectDisplacement));
+        assert_eq!(
+            scavenging("Benson-Brandham", Some(0.1), Some(0.4)),
+            Ok(Scavenging::BensonBrandham { short_circuit: 0.1, displacement: 0.4 })
+        );
+        assert!(scavenging("Benson-Brandham", Some(0.1), None).is_err());
+        assert!(scavenging("Benson-Brandham", Some(1.0), Some(0.4)).is_err());
+        assert!(scavenging("loop", None, None).is_err());
+    }
+
+    #[test]
+    fn only_two_stroke_limits_trapped_charge() {
+        let gas = Gas::new("air.json");
+        for (file_name, limited) in [("engine.json", false), ("engine_Ryobi.json", true)] {
+            let mut cyl = cylinder(file_name, &gas);
+            cyl.total_fresh_charge = 3e-4;
+            cyl.fresh_mass = 2e-4;
+            let expected = if limited { 2e-4 } else { 3e-4 };
+            assert_eq!(cyl.trapped_charge(), expected, "{}", file_name);
+        }
+    }
+
+    #[test]
+    fn trapped_air_of_motored_cylinder_excludes_residual_gas() {
+        let gas = Gas::new("air.json");
+        let mut cyl = cylinder("engine.json", &gas);
+        let fresh_charge = 0.6 * cyl.mass;
+        cyl.total_fresh_charge = fresh_charge;
+        cyl.fresh_mass = fresh_charge;
+        cyl.closed_phase(1e-3);
+        assert_eq!(cyl.trapped_air(), fresh_charge);
+        assert_eq!(cyl.closed_phase_mass(), cyl.mass + cyl.crevice_mass);
+        // kept until the next closed phase
+        cyl.closed_phase(1e-3);
+        assert_eq!(cyl.trapped_air(), fresh_charge);
+    }
+
+    #[test]
+    fn blow_by_mass_flow() {
+        let mut gas = Gas::new("air.json");
+        let blow_by = BlowBy::new(1.0, 1e5, 0.7).unwrap();
+        let (area, temp) = (1e-6, 800.0);
+        // choked: independent of the crankcase pressure
+        gas.TPX(temp, 20e5, "O2:0.21, N2:0.79");
+        let k = gas.k();
+        let choked = 0.7 * area * 20e5 / (gas.R() * temp).sqrt() * (k * (2.0 / (k + 1.0)).powf((k + 1.0) / (k - 1.0))).sqrt();
+        assert!((blow_by.mass_flow(&gas) - choked).abs() < 1e-12 * choked);
+        let higher_crankcase = BlowBy::new(1.0, 2e5, 0.7).unwrap();
+        assert_eq!(higher_crankcase.mass_flow(&gas), blow_by.mass_flow(&gas));
+        // subsonic
+        gas.TPX(temp, 1.5e5, "O2:0.21, N2:0.79");
+        let ratio: f64 = 1.0 / 1.5;
+        let subsonic = 0.7 * area * 1.5e5 / (gas.R() * temp).sqrt()
+            * (2.0 * k / (k - 1.0) * (ratio.powf(2.0 / k) - ratio.powf((k + 1.0) / k))).sqrt();
+        assert!((blow_by.mass_flow(&gas) - subsonic).abs() < 1e-12 * subsonic);
+        // continuous at the critical pressure ratio
+        let critical = 1.5e5 * (2.0 / (k + 1.0)).powf(k / (k - 1.0));
+        let below = BlowBy::new(1.0, critical * (1.0 - 1e-9), 0.7).unwrap().mass_flow(&gas);
+        let above = BlowBy::new(1.0, critical * (1.0 + 1e-9), 0.7).unwrap().mass_flow(&gas);
+        assert!((below - above).abs() < 1e-6 * below);
+        // no flow back to the cylinder
+        assert_eq!(higher_crankcase.mass_flow(&gas), 0.0);
+    }
+
+    #[test]
+    fn crevice_exchange_conserves_mass_and_energy() {
+        let gas = Gas::new("air.json");
+        let mut cyl = cylinder("engine.json", &gas);
+        cyl.set_crevice(Some(Crevice::new(2.0).unwrap()));
+        let (gas_const, cv) = (cyl.gas.R(), cyl.gas.cv());
+        let wall_temp = cyl.geometry.wall_temp;
+        let (vol, mass) = (cyl.volume, cyl.mass);
+        // compression fills the crevices with cylinder gas, expansion empties them at wall temperature
+        for (press, temp) in [(30e5, 900.0), (5e5, 1500.0)] {
+            let stored = cyl.crevice_mass;
+            let (new_temp, new_press, new_mass) = cyl.crevice_exchange(temp, press, mass, vol);
+            let released = stored - cyl.crevice_mass;
+            assert!((cyl.crevice_mass - press * cyl.crevice.as_ref().unwrap().volume / (gas_const * wall_temp)).abs() < 1e-15);
+            assert!((new_mass + cyl.crevice_mass - (mass + stored)).abs() < 1e-15);
+            let inflow_temp = if released > 0.0 { wall_temp } else { temp };
+            let energy = mass * cv * temp + released * (cv + gas_const) * inflow_temp;
+            assert!((new_mass * cv * new_temp - energy).abs() < 1e-9 * energy);
+            assert!((new_press * vol - new_mass * gas_const * new_temp).abs() < 1e-9 * new_press * vol);
+        }
+        // without crevices the state is kept
+        cyl.set_crevice(None);
+        assert_eq!(cyl.crevice_exchange(900.0, 30e5, mass, vol), (900.0, 30e5, mass));
+    }
+
+    #[test]
+    fn blow_by_mass_accumulates_over_the_cycle() {
+        let gas = Gas::new("air.json");
+        let mut cyl = cylinder("engine.json", &gas);
+        cyl.set_blow_by(Some(BlowBy::new(1.0, 101325.0, 0.7).unwrap()));
+        let dt = 1f64.to_radians() / cyl.sec_to_rad;
+        // from bottom-dead-center to the end of the first cycle
+        let mut steps = 0;
+        let mut first_cycle = 0.0;
+        while cyl.blow_by_mass() == 0.0 {
+            first_cycle = cyl.blow_by_cycle;
+            cyl.advance(dt);
+            steps += 1;
+        }
+        assert!(steps == 540 || steps == 541);
+        assert!(first_cycle > 0.0);
+        assert_eq!(cyl.blow_by_mass(), first_cycle + cyl.blow_by_rate * dt);
+        assert_eq!(cyl.blow_by_cycle, 0.0);
+
+        // a whole cycle, with the leakage taken from the cylinder
+        let first_cycle = cyl.blow_by_mass();
+        let trapped = cyl.mass + cyl.crevice_mass;
+        let mut leaked = 0.0;
+        steps = 0;
+        while cyl.blow_by_mass() == first_cycle {
+            leaked += cyl.blow_by().unwrap().mass_flow(&cyl.gas) * dt;
+            cyl.advance(dt);
+            steps += 1;
+        }
+        assert!(steps == 720 || steps == 721);
+        assert!(cyl.blow_by_mass() > first_cycle);
+        assert!((cyl.blow_by_mass() - leaked).abs() < 1e-12 * leaked);
+        assert!((trapped - cyl.mass - cyl.crevice_mass - leaked).abs() < 1e-6 * leaked);
+
+        // the crankcase element replaces the pressure of the model
+        cyl.set_crankcase_pressure(2e5);
+        assert_eq!(cyl.blow_by().unwrap().crankcase_pressure(), 2e5);
+    }
+
+    // releases heat from the start of the closed phase, at a rate proportional to the burning fuel
+    #[derive(Clone)]
+    struct ConstantCombustion;
+
+    impl Combustion for ConstantCombustion {
+        fn model_name(&self) -> &str {
+            "constant combustion"
+        }
+        fn get_heat_release_rate(&mut self, _: &Gas, fuel_mass: f64, _: f64) -> f64 {
+            1e3 * fuel_mass
+        }
+        fn update_composition(&mut self, gas: &Gas, _: f64, _: f64, _: f64, _: f64) -> Array1<f64> {
+            gas.mole_frac().clone()
+        }
+        fn ini_combustion(&self) -> f64 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn crevice_fuel_is_fixed_when_combustion_starts() {
+        let gas = Gas::new("air.json");
+        let mut cyl = cylinder("engine.json", &gas);
+        cyl.combustion = Box::new(ConstantCombustion);
+        cyl.set_crevice(Some(Crevice::new(2.0).unwrap()));
+        cyl.fuel_mass = 1e-5;
+        cyl.closed_phase(1e-3);
+        let burning_frac = cyl.mass / (cyl.mass + cyl.crevice_mass);
+        assert_eq!(cyl.burning_frac, Some(burning_frac));
+        // the crevices fill during the compression, the fuel out of them was already set
+        let (temp, press) = (cyl.gas.T(), 2.0 * cyl.gas.P());
+        let (_, _, mass) = cyl.crevice_exchange(temp, press, cyl.mass, cyl.volume);
+        cyl.mass = mass;
+        cyl.closed_phase(1e-3);
+        assert_eq!(cyl.burning_frac, Some(burning_frac));
+        assert!(cyl.mass / (cyl.mass + cyl.crevice_mass) < burning_frac);
+        // fixed again at the next cycle
+        cyl.closed_phase_start = false;
+        cyl.closed_phase(1e-3);
+        assert_eq!(cyl.burning_frac, Some(cyl.mass / (cyl.mass + cyl.crevice_mass)));
+    }
+}
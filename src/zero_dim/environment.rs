@@ -34,6 +34,7 @@ impl ZeroDim for Environment {
             cp_cv: self.gas.k(),
             gas_const: self.gas.R(),
             crank_angle: None,
+            mass_frac: self.gas.mass_frac(),
         }
     }
     fn advance(&mut self, _: f64) {}
@@ -0,0 +1,279 @@
+use crate::connector::egr_cooler::HeatExchange;
+use crate::core::traits::{SaveData, ZeroD, ZeroDim};
+use crate::numerics::ode_solvers as ode;
+use crate::reaction::gas::Gas;
+use crate::{BasicProperties, FlowRatio};
+use ndarray::*;
+
+// time constant, in s, with which the pressure drop follows the mass flow. Without it, the pressure drop
+// and the flow of the upstream connectors feed back on each other and oscillate.
+const PRESS_DROP_TIME_CONST: f64 = 1e-4;
+
+/// Zero-Dimensional heat exchanger, i.e. a charge-air cooler. It behaves as a [`Reservoir`](../reservoir/struct.Reservoir.html)
+/// with a pressure drop `k*m_dot²` between its inlet and outlet and rejects heat from the incoming gas to a fixed coolant temperature.
+///
+/// The state of the gas corresponds to the outlet. The connectors delivering flow into the intercooler see the pressure
+/// increased by the pressure drop.
+pub struct Intercooler {
+    name: String,
+    gas: Gas,
+    volume: f64,
+    mass: f64,
+    press_drop_coeff: f64, // [Pa/(kg/s)²]
+    heat_exchange: HeatExchange,
+    coolant_temp: f64, // [K]
+    flow_ratio: FlowRatio,
+    inlets: Vec<String>,
+    mass_flow_in: f64,     // [kg/s]
+    enthalpy_flow_in: f64, // [J/s]
+    throughput: f64,       // [kg/s] - mass flow setting the pressure drop
+    heat_flow: f64,        // [W] - rejected to the coolant
+}
+
+impl Intercooler {
+    /// `volume` in m³, `press_drop_coeff` in Pa/(kg/s)² and `coolant_temp` in K
+    pub fn new(
+        name: String,
+        gas: &Gas,
+        volume: f64,
+        press_drop_coeff: f64,
+        heat_exchange: HeatExchange,
+        coolant_temp: f64,
+    ) -> Result<Intercooler, String> {
+        if volume <= 0.0 {
+            return Err("`volume` must be greater than zero".to_string());
+        }
+        if press_drop_coeff < 0.0 {
+            return Err(format!("pressure drop coefficient must be positive: {}", press_drop_coeff));
+        }
+        match heat_exchange {
+            HeatExchange::Effectiveness(eff) if !(0.0..=1.0).contains(&eff) => {
+                return Err(format!("effectiveness must be between 0.0 and 1.0: {}", eff));
+            }
+            HeatExchange::UA(ua) if ua < 0.0 => {
+                return Err(format!("`UA` must be positive: {}", ua));
+            }
+            _ => {}
+        }
+        if coolant_temp <= 0.0 {
+            return Err(format!("`coolant_temp` must be greater than zero: {}", coolant_temp));
+        }
+        Ok(Intercooler {
+            name,
+            gas: gas.clone(),
+            volume,
+            mass: gas.P() * volume / (gas.R() * gas.T()),
+            press_drop_coeff,
+            heat_exchange,
+            coolant_temp,
+            flow_ratio: FlowRatio::new(),
+            inlets: Vec::new(),
+            mass_flow_in: 0.0,
+            enthalpy_flow_in: 0.0,
+            throughput: 0.0,
+            heat_flow: 0.0,
+        })
+    }
+
+    /// Returns the pressure drop, in Pa, between inlet and outlet
+    pub fn pressure_drop(&self) -> f64 {
+        self.press_drop_coeff * self.throughput * self.throughput
+    }
+
+    /// Returns the heat, in W, rejected to the coolant
+    pub fn heat_flow(&self) -> f64 {
+        self.heat_flow
+    }
+}
+
+impl ZeroDim for Intercooler {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn get_state(&self) -> BasicProperties<'_> {
+        BasicProperties {
+            name: self.name(),
+            pressure: self.gas.P(),
+            temperature: self.gas.T(),
+            cp: self.gas.cp(),
+            cv: self.gas.cv(),
+            cp_cv: self.gas.k(),
+            gas_const: self.gas.R(),
+            crank_angle: None,
+            mass_frac: self.gas.mass_frac(),
+        }
+    }
+    fn get_state_to(&self, conn_name: &str) -> BasicProperties<'_> {
+        let mut state = self.get_state();
+        if self.inlets.iter().any(|name| name == conn_name) {
+            state.pressure += self.pressure_drop();
+        }
+        state
+    }
+    fn advance(&mut self, dt: f64) {
+        let cv = self.gas.R() / (self.gas.k() - 1.0);
+        let cv_inv = 1.0 / cv;
+
+        // heat rejected from the incoming gas
+        let cp = self.gas.cp();
+        self.heat_flow = if self.mass_flow_in > 0.0 {
+            let inlet_temp = self.enthalpy_flow_in / (self.mass_flow_in * cp);
+            let effectiveness = self.heat_exchange.effectiveness(self.mass_flow_in, cp);
+            effectiveness * self.mass_flow_in * cp * (inlet_temp - self.coolant_temp)
+        } else {
+            0.0
+        };
+
+        let heat_flow = self.heat_flow;
+        let system_equations = |_: &f64, x: &Array1<f64>, _: &Vec<f64>| -> Array1<f64> {
+            // x[0] = temperature, x[1] = mass
+            let d_mass = self.flow_ratio.mass_flow;
+            let d_temp = cv_inv / x[1]
+                * (self.flow_ratio.enthalpy_flow - heat_flow - cv * x[0] * d_mass);
+            array![d_temp, d_mass]
+        };
+
+        // Runge-Kutta 4th order solution
+        let ini_condition = array![self.gas.T(), self.mass];
+        let integrated = ode::rk4_step(system_equations, &ini_condition, &Vec::new(), &0.0, dt);
+        let temp = integrated[0];
+        let mass = integrated[1];
+
+        // species transport: the mass flow is constant during the step
+        let mole_frac = if self.flow_ratio.species_flow.len() == self.gas.num_species() {
+            let species_mass =
+                self.gas.mass_frac() * self.mass + &self.flow_ratio.species_flow * dt;
+            self.gas.mole_frac_from_masses(&species_mass)
+        } else {
+            self.gas.mole_frac().clone()
+        };
+        self.gas.X_array(&mole_frac);
+
+        // calculating new pressure
+        let press = mass * self.gas.R() * temp / self.volume;
+        self.gas.TP(temp, press);
+        self.mass = mass;
+
+        let relax = (dt / PRESS_DROP_TIME_CONST).min(1.0);
+        self.throughput += relax * (self.mass_flow_in - self.throughput);
+    }
+    fn update_flow_ratio(&mut self, total_flow_ratio: Vec<(&str, &FlowRatio)>) {
+        let mut flow_ratio = FlowRatio::new();
+        total_flow_ratio
+            .iter()
+            .for_each(|(_, f)| flow_ratio = &flow_ratio + *f);
+        self.flow_ratio = flow_ratio;
+
+        // incoming flows
+        self.inlets.clear();
+        self.mass_flow_in = 0.0;
+        self.enthalpy_flow_in = 0.0;
+        for (name, f) in total_flow_ratio.iter() {
+            if f.mass_flow > 0.0 {
+                self.inlets.push(name.to_string());
+                self.mass_flow_in += f.mass_flow;
+                self.enthalpy_flow_in += f.enthalpy_flow;
+            }
+        }
+    }
+}
+
+impl SaveData for Intercooler {
+    fn get_headers(&self) -> String {
+        let species = self.gas.species().join("\t");
+        format!(
+            "pressure [bar]\ttemperature [K]\tmass [g]\tpressure drop [bar]\theat rejected [W]\t{}",
+            species
+        )
+    }
+    fn num_storable_variables(&self) -> usize {
+        5 + self.gas.num_species()
+    }
+    fn get_storable_data(&self) -> Array1<f64> {
+        stack![
+            Axis(0),
+            array![
+                self.gas.P() / 1e5,
+                self.gas.T(),
+                self.mass * 1e3,
+                self.pressure_drop() / 1e5,
+                self.heat_flow
+            ],
+            self.gas.mole_frac().clone()
+        ]
+    }
+}
+
+impl ZeroD for Intercooler {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // steady flow of `mass_flow`, in kg/s, at `temp` through the intercooler
+    fn flows(gas: &Gas, mass_flow: f64, temp: f64) -> (FlowRatio, FlowRatio) {
+        let enthalpy = gas.cp() * temp;
+        let inflow = FlowRatio {
+            mass_flow,
+            enthalpy_flow: mass_flow * enthalpy,
+            species_flow: mass_flow * gas.mass_frac(),
+        };
+        let outflow = FlowRatio {
+            mass_flow: -mass_flow,
+            enthalpy_flow: -mass_flow * gas.cp() * gas.T(),
+            species_flow: -mass_flow * gas.mass_frac(),
+        };
+        (inflow, outflow)
+    }
+
+    #[test]
+    fn pressure_drop_follows_mass_flow() {
+        let gas = Gas::new("air.json");
+        let mut cooler = Intercooler::new("ic".to_string(), &gas, 1e-3, 2e5, HeatExchange::Effectiveness(0.8), 300.0).unwrap();
+        let (inflow, outflow) = flows(&gas, 0.05, 400.0);
+        cooler.update_flow_ratio(vec![("compressor", &inflow), ("throttle", &outflow)]);
+        assert_eq!(cooler.pressure_drop(), 0.0);
+
+        // the pressure drop relaxes towards `k*m_dot²`
+        let dt = 0.25 * PRESS_DROP_TIME_CONST;
+        for _ in 0..200 {
+            cooler.advance(dt);
+        }
+        let expected = 2e5 * 0.05 * 0.05;
+        assert!((cooler.pressure_drop() - expected).abs() < 1e-9 * expected);
+
+        // only the inlets see the pressure upstream of the core
+        let outlet_press = cooler.get_state().pressure;
+        assert!((cooler.get_state_to("compressor").pressure - outlet_press - expected).abs() < 1e-6);
+        assert_eq!(cooler.get_state_to("throttle").pressure, outlet_press);
+    }
+
+    #[test]
+    fn rejects_heat_with_effectiveness() {
+        let gas = Gas::new("air.json");
+        let cp = gas.cp();
+        let mut cooler = Intercooler::new("ic".to_string(), &gas, 1e-3, 0.0, HeatExchange::Effectiveness(0.8), 300.0).unwrap();
+        let (inflow, outflow) = flows(&gas, 0.05, 400.0);
+        cooler.update_flow_ratio(vec![("compressor", &inflow), ("throttle", &outflow)]);
+        cooler.advance(1e-6);
+        let expected = 0.8 * 0.05 * cp * (400.0 - 300.0);
+        assert!((cooler.heat_flow() - expected).abs() < 1e-9 * expected);
+
+        // with `UA`, the effectiveness is `1 - exp(-NTU)`
+        let ua = 0.05 * cp;
+        let mut cooler = Intercooler::new("ic".to_string(), &gas, 1e-3, 0.0, HeatExchange::UA(ua), 300.0).unwrap();
+        cooler.update_flow_ratio(vec![("compressor", &inflow), ("throttle", &outflow)]);
+        cooler.advance(1e-6);
+        let expected = (1.0 - (-1f64).exp()) * 0.05 * cp * (400.0 - 300.0);
+        assert!((cooler.heat_flow() - expected).abs() < 1e-9 * expected);
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let gas = Gas::new("air.json");
+        assert!(Intercooler::new("ic".to_string(), &gas, 0.0, 1e5, HeatExchange::Effectiveness(0.8), 300.0).is_err());
+        assert!(Intercooler::new("ic".to_string(), &gas, 1e-3, -1.0, HeatExchange::Effectiveness(0.8), 300.0).is_err());
+        assert!(Intercooler::new("ic".to_string(), &gas, 1e-3, 1e5, HeatExchange::Effectiveness(1.2), 300.0).is_err());
+        assert!(Intercooler::new("ic".to_string(), &gas, 1e-3, 1e5, HeatExchange::UA(-1.0), 300.0).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 //! Contains all **0D elements**
+pub mod crankcase;
 pub mod cylinder;
 pub mod environment;
+pub mod intercooler;
 pub mod reservoir;
-
@@ -4,13 +4,84 @@ use crate::{BasicProperties, FlowRatio};
 use crate::numerics::ode_solvers as ode;
 use ndarray::*;
 
-/// Zero-Dimensional chamber of finite volume. Gas composition is assumed constant.
+/// Zero-Dimensional chamber of finite volume. The gas is assumed perfectly mixed and its composition
+/// follows the species carried by the incoming and outgoing flows. The chamber is adiabatic unless
+/// a [`ReservoirWall`](struct.ReservoirWall.html) is given.
 pub struct Reservoir {
     name: String,
     gas: Gas,
     volume: f64,
     mass: f64,
     flow_ratio: FlowRatio,
+    wall: Option<ReservoirWall>,
+    mass_flow_in: f64, // [kg/s]
+    store_species: bool,
+}
+
+/// Lumped wall of a [`Reservoir`](struct.Reservoir.html). The gas exchanges heat with the wall by forced convection,
+/// `Nu = 0.023*Re^0.8*Pr^0.4` (not lower than the laminar 3.66), where the length scale is the diameter of the sphere
+/// with the reservoir volume and the velocity is given by the incoming mass flow. The wall loses heat to the
+/// surroundings with a constant coefficient and its temperature evolves with its thermal mass.
+/// The wall usually takes many more cycles than simulated to warm up, so its initial temperature
+/// should be close to the expected operating value.
+#[derive(Debug, Clone)]
+pub struct ReservoirWall {
+    area: f64,          // [m²]
+    thermal_mass: f64,  // [J/K]
+    temperature: f64,   // [K]
+    ext_htc: f64,       // [W/(m²*K)]
+    ambient_temp: f64,  // [K]
+    heat_flow: f64,     // [W] - from the gas to the wall
+}
+
+impl ReservoirWall {
+    /// `area` in m², `thermal_mass` (mass times specific heat) in J/K, `temperature` is the initial wall temperature in K,
+    /// `ext_htc` is the heat transfer coefficient to the surroundings in W/(m²*K) and `ambient_temp` is in K
+    pub fn new(
+        area: f64,
+        thermal_mass: f64,
+        temperature: f64,
+        ext_htc: f64,
+        ambient_temp: f64,
+    ) -> Result<ReservoirWall, String> {
+        if area <= 0.0 || thermal_mass <= 0.0 {
+            return Err("wall `area` and `thermal_mass` must be greater than zero".to_string());
+        }
+        if temperature <= 0.0 || ambient_temp <= 0.0 {
+            return Err("wall and ambient temperatures must be greater than zero".to_string());
+        }
+        if ext_htc < 0.0 {
+            return Err(format!("`ext_htc` must be positive: {}", ext_htc));
+        }
+        Ok(ReservoirWall {
+            area,
+            thermal_mass,
+            temperature,
+            ext_htc,
+            ambient_temp,
+            heat_flow: 0.0,
+        })
+    }
+
+    /// Returns the wall temperature in K
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Returns the heat, in W, transferred from the gas to the wall
+    pub fn heat_flow(&self) -> f64 {
+        self.heat_flow
+    }
+
+    // convective coefficient between the gas and the wall [W/(m²*K)]
+    fn heat_transfer_coeff(gas: &Gas, volume: f64, mass_flow: f64) -> f64 {
+        let prandtl: f64 = 0.7;
+        let diam = (6.0 * volume / std::f64::consts::PI).powf(1.0 / 3.0);
+        let reynolds = 4.0 * mass_flow / (std::f64::consts::PI * diam * gas.mu());
+        let nusselt = (0.023 * reynolds.powf(0.8) * prandtl.powf(0.4)).max(3.66);
+        let conductivity = gas.mu() * gas.cp() / prandtl;
+        nusselt * conductivity / diam
+    }
 }
 
 impl Reservoir {
@@ -26,8 +97,23 @@ impl Reservoir {
             volume,
             mass: gas.P()*volume/(gas.R()*gas.T()),
             flow_ratio: FlowRatio::new(),
+            wall: None,
+            mass_flow_in: 0.0,
+            store_species: false,
         } )
     }
+
+    /// Reservoir with heat transfer to `wall`. `volume` in m³
+    pub fn with_wall(name: String, gas: &Gas, volume: f64, wall: ReservoirWall) -> Result<Reservoir, &'static str> {
+        let mut reservoir = Reservoir::new(name, gas, volume)?;
+        reservoir.wall = Some(wall);
+        Ok(reservoir)
+    }
+
+    /// Returns the wall, if any
+    pub fn wall(&self) -> Option<&ReservoirWall> {
+        self.wall.as_ref()
+    }
 }
 
 
@@ -43,15 +129,29 @@ impl ZeroDim for Reservoir {
             cp_cv: self.gas.k(),
             gas_const: self.gas.R(),
             crank_angle: None,
+            mass_frac: self.gas.mass_frac(),
         }
     }
     fn advance(&mut self, dt: f64) {
         let cv = self.gas.R() / (self.gas.k() - 1.0);
         let cv_inv = 1.0 / cv;
+
+        // heat transferred to the wall, constant during the step
+        let heat_flow = match &mut self.wall {
+            Some(wall) => {
+                let htc = ReservoirWall::heat_transfer_coeff(&self.gas, self.volume, self.mass_flow_in);
+                wall.heat_flow = htc * wall.area * (self.gas.T() - wall.temperature);
+                let heat_loss = wall.ext_htc * wall.area * (wall.temperature - wall.ambient_temp);
+                wall.temperature += dt * (wall.heat_flow - heat_loss) / wall.thermal_mass;
+                wall.heat_flow
+            }
+            None => 0.0,
+        };
+
         let system_equations = |_: &f64, x: &Array1<f64>, _: &Vec<f64>| -> Array1<f64> {
             // x[0] = temperature, x[1] = mass
             let d_mass = self.flow_ratio.mass_flow;
-            let d_temp = cv_inv / x[1] * (self.flow_ratio.enthalpy_flow - cv * x[0] * d_mass); // [K/CA radian]
+            let d_temp = cv_inv / x[1] * (self.flow_ratio.enthalpy_flow - heat_flow - cv * x[0] * d_mass); // [K/CA radian]
             array![d_temp, d_mass]
         };
 
@@ -66,7 +166,16 @@ impl ZeroDim for Reservoir {
         );
         let temp = integrated[0];
         let mass = integrated[1];
-        
+
+        // species transport: the mass flow is constant during the step
+        let mole_frac = if self.flow_ratio.species_flow.len() == self.gas.num_species() {
+            let species_mass = self.gas.mass_frac() * self.mass + &self.flow_ratio.species_flow * dt;
+            self.gas.mole_frac_from_masses(&species_mass)
+        } else {
+            self.gas.mole_frac().clone()
+        };
+        self.gas.X_array(&mole_frac);
+
         // calculating new pressure
         let press = mass*self.gas.R()*temp/self.volume;
         self.gas.TP(temp, press);
@@ -77,17 +186,123 @@ impl ZeroDim for Reservoir {
         let mut flow_ratio = FlowRatio::new();
         total_flow_ratio.iter().for_each(|(_,f)| flow_ratio = &flow_ratio + *f);
         self.flow_ratio = flow_ratio;
+        self.mass_flow_in = total_flow_ratio.iter().map(|(_,f)| f.mass_flow.max(0.0)).sum();
+    }
+    fn set_store_species(&mut self, state: bool) -> Result<(), String> {
+        self.store_species = state;
+        Ok(())
     }
 }
 
 impl SaveData for Reservoir {
     fn get_headers(&self) -> String {
-        "pressure [bar]\ttemperature [K]\tmass [g]".to_string()
+        let mut header = match self.wall {
+            Some(_) => "pressure [bar]\ttemperature [K]\tmass [g]\twall temperature [K]\twall heat flow [W]".to_string(),
+            None => "pressure [bar]\ttemperature [K]\tmass [g]".to_string(),
+        };
+        if self.store_species {
+            header = format!("{}\t{}", header, self.gas.species().join("\t"));
+        }
+        header
+    }
+    fn num_storable_variables(&self) -> usize {
+        let num_prop: usize = match self.wall {
+            Some(_) => 5,
+            None => 3,
+        };
+        if self.store_species {
+            num_prop + self.gas.num_species()
+        } else {num_prop}
     }
-    fn num_storable_variables(&self) -> usize {3}
     fn get_storable_data(&self) -> Array1<f64> {
-        array![self.gas.P()/1e5, self.gas.T(), self.mass*1e3]
+        let props = match &self.wall {
+            Some(wall) => array![self.gas.P()/1e5, self.gas.T(), self.mass*1e3, wall.temperature, wall.heat_flow],
+            None => array![self.gas.P()/1e5, self.gas.T(), self.mass*1e3],
+        };
+        if self.store_species {
+            stack![Axis(0), props, self.gas.mole_frac().clone()]
+        } else {props}
     }
 }
 
-impl ZeroD for Reservoir {}
\ No newline at end of file
+impl ZeroD for Reservoir {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_species_only_when_requested() {
+        let gas = Gas::new("air.json");
+        let mut res = Reservoir::new("res".to_string(), &gas, 1e-3).unwrap();
+        assert_eq!(res.get_headers(), "pressure [bar]\ttemperature [K]\tmass [g]");
+        assert_eq!(res.num_storable_variables(), 3);
+        assert_eq!(res.get_storable_data().len(), 3);
+
+        res.set_store_species(true).unwrap();
+        let num_vars = 3 + gas.num_species();
+        assert_eq!(res.get_headers().split('\t').count(), num_vars);
+        assert_eq!(res.num_storable_variables(), num_vars);
+        assert_eq!(res.get_storable_data().len(), num_vars);
+    }
+
+    #[test]
+    fn wall_columns_come_before_species() {
+        let gas = Gas::new("air.json");
+        let wall = ReservoirWall::new(0.1, 500.0, 300.0, 10.0, 300.0).unwrap();
+        let mut res = Reservoir::with_wall("res".to_string(), &gas, 1e-3, wall).unwrap();
+        assert_eq!(res.num_storable_variables(), 5);
+        assert_eq!(res.get_storable_data().len(), 5);
+
+        res.set_store_species(true).unwrap();
+        let headers: Vec<String> = res.get_headers().split('\t').map(|h| h.to_string()).collect();
+        assert_eq!(headers[4], "wall heat flow [W]");
+        assert_eq!(&headers[5..], gas.species().as_slice());
+        assert_eq!(res.get_storable_data().len(), 5 + gas.num_species());
+    }
+
+    #[test]
+    fn wall_heat_flow_without_flow() {
+        let mut gas = Gas::new("air.json");
+        gas.TP(600.0, 2e5);
+        let volume = 1e-3;
+        let wall = ReservoirWall::new(0.05, 200.0, 400.0, 20.0, 300.0).unwrap();
+        let mut res = Reservoir::with_wall("res".to_string(), &gas, volume, wall).unwrap();
+        res.update_flow_ratio(Vec::new());
+
+        // laminar limit of the Nusselt number
+        let diam = (6.0 * volume / std::f64::consts::PI).powf(1.0 / 3.0);
+        let htc = 3.66 * gas.mu() * gas.cp() / 0.7 / diam;
+        assert!((ReservoirWall::heat_transfer_coeff(&gas, volume, 0.0) - htc).abs() < 1e-12 * htc);
+
+        let dt = 1e-4;
+        let mass = res.mass;
+        let cv = gas.R() / (gas.k() - 1.0);
+        res.advance(dt);
+        let wall = res.wall().unwrap();
+        let heat_flow = htc * 0.05 * (600.0 - 400.0);
+        assert!((wall.heat_flow() - heat_flow).abs() < 1e-9 * heat_flow);
+        let heat_loss = 20.0 * 0.05 * (400.0 - 300.0);
+        assert!((wall.temperature() - 400.0 - dt * (heat_flow - heat_loss) / 200.0).abs() < 1e-12);
+
+        // the gas loses the heat taken by the wall
+        assert_eq!(res.mass, mass);
+        let temp_drop = heat_flow * dt / (mass * cv);
+        assert!((600.0 - res.gas.T() - temp_drop).abs() < 1e-9 * temp_drop);
+    }
+
+    #[test]
+    fn wall_heat_flow_grows_with_mass_flow() {
+        let gas = Gas::new("air.json");
+        let laminar = ReservoirWall::heat_transfer_coeff(&gas, 1e-3, 0.0);
+        let turbulent = ReservoirWall::heat_transfer_coeff(&gas, 1e-3, 0.1);
+        assert!(turbulent > laminar);
+        assert!(ReservoirWall::heat_transfer_coeff(&gas, 1e-3, 0.2) > turbulent);
+    }
+
+    #[test]
+    fn rejects_invalid_walls() {
+        assert!(ReservoirWall::new(0.0, 200.0, 400.0, 20.0, 300.0).is_err());
+        assert!(ReservoirWall::new(0.05, 200.0, -1.0, 20.0, 300.0).is_err());
+        assert!(ReservoirWall::new(0.05, 200.0, 400.0, -20.0, 300.0).is_err());
+    }
+}